@@ -0,0 +1,6 @@
+// Shared by every program, updated once per frame (binding 0)
+layout (std140) uniform CameraBlock {
+    mat4 view;
+    mat4 projection;
+    vec3 viewPos;
+};
@@ -0,0 +1,63 @@
+// Per-part color gradients, evaluated from the fragment's position in the part's local space.
+// Mirrors graphics::gradient::Gradient
+#define MAX_GRADIENT_STOPS 8
+
+#define GRADIENT_AXIS_X 0
+#define GRADIENT_AXIS_Y 1
+#define GRADIENT_AXIS_Z 2
+#define GRADIENT_AXIS_RADIAL 3
+
+#define GRADIENT_LINEAR 0
+#define GRADIENT_SMOOTH 1
+#define GRADIENT_STEP 2
+
+uniform int uGradientStopCount; // fewer than 2 stops disables the gradient
+uniform int uGradientAxis;
+uniform int uGradientInterpolation;
+uniform float uGradientOffsets[MAX_GRADIENT_STOPS];
+uniform vec3 uGradientColors[MAX_GRADIENT_STOPS];
+
+// Where along the gradient a local position is, from 0 to 1
+float gradientParameter(vec3 localPos) {
+    // Parts span -0.5..0.5 on every axis
+    if (uGradientAxis == GRADIENT_AXIS_X) {
+        return localPos.x + 0.5;
+    }
+    if (uGradientAxis == GRADIENT_AXIS_Y) {
+        return localPos.y + 0.5;
+    }
+    if (uGradientAxis == GRADIENT_AXIS_Z) {
+        return localPos.z + 0.5;
+    }
+    // 0 at the center, 1 at the middle of each face
+    return length(localPos) * 2.0;
+}
+
+// The gradient's color at `localPos`, or `fallback` when the part has no gradient
+vec3 gradientColor(vec3 localPos, vec3 fallback) {
+    if (uGradientStopCount < 2) {
+        return fallback;
+    }
+
+    float t = clamp(gradientParameter(localPos), 0.0, 1.0);
+    if (t <= uGradientOffsets[0]) {
+        return uGradientColors[0];
+    }
+    for (int i = 1; i < MAX_GRADIENT_STOPS; i++) {
+        if (i >= uGradientStopCount) {
+            break;
+        }
+        if (t <= uGradientOffsets[i]) {
+            if (uGradientInterpolation == GRADIENT_STEP) {
+                return uGradientColors[i - 1];
+            }
+            float span = uGradientOffsets[i] - uGradientOffsets[i - 1];
+            float f = span > 0.0 ? (t - uGradientOffsets[i - 1]) / span : 1.0;
+            if (uGradientInterpolation == GRADIENT_SMOOTH) {
+                f = smoothstep(0.0, 1.0, f);
+            }
+            return mix(uGradientColors[i - 1], uGradientColors[i], f);
+        }
+    }
+    return uGradientColors[uGradientStopCount - 1];
+}
@@ -0,0 +1,79 @@
+#include "common/camera.glsl"
+
+// Shared by every program, updated once per frame (binding 1)
+layout (std140) uniform LightBlock {
+    vec3 lightPos;     // point light
+    vec3 lightColor;   // zero without a point light
+    vec3 sunDirection; // direction the sunlight travels
+    vec3 sunColor;     // zero without a sun
+    vec4 ambientLight; // rgb: color, a: intensity. Set by the sky
+};
+
+#include "common/shadows.glsl"
+
+// Lighting constants
+const float AMBIENT_COLOR_TINT = 0.1;
+
+const float WRAP_AMOUNT = 0.3;
+const float WRAP_SCALE = 1.3;
+
+// Used by surfaces without a material, matches Material::default()
+const vec3 DEFAULT_SPECULAR = vec3(0.9);
+const float DEFAULT_SHININESS = 16.0;
+
+const float FRESNEL_POWER = 3.0;
+const float RIM_STRENGTH = 0.15;
+
+const float ATTENUATION_LINEAR = 0.09;
+const float ATTENUATION_QUADRATIC = 0.032;
+
+const float SUBSURFACE_STRENGTH = 0.2;
+
+// Diffuse, specular and subsurface light from one light, before attenuation and shadowing
+vec3 lightContribution(vec3 norm, vec3 viewDir, vec3 lightDir, vec3 radiance, vec3 albedo, vec3 specularColor, float shininess) {
+    // Diffuse with wrap lighting for softer shadows
+    float NdotL = dot(norm, lightDir);
+    float diffuseWrap = max((NdotL + WRAP_AMOUNT) / WRAP_SCALE, 0.0);
+    vec3 diffuse = diffuseWrap * radiance;
+
+    // Enhanced Specular with Fresnel effect
+    vec3 halfwayDir = normalize(lightDir + viewDir);
+    float NdotH = max(dot(norm, halfwayDir), 0.0);
+    float spec = pow(NdotH, shininess);
+
+    // Fresnel effect (Schlick approximation)
+    float fresnel = pow(1.0 - max(dot(viewDir, norm), 0.0), FRESNEL_POWER);
+    vec3 specular = specularColor * spec * radiance;
+    specular += fresnel * RIM_STRENGTH * radiance;
+
+    // Subsurface scattering approximation
+    float backlight = max(dot(norm, -lightDir), 0.0);
+    vec3 subsurface = SUBSURFACE_STRENGTH * backlight * radiance * albedo;
+
+    return diffuse + specular + subsurface;
+}
+
+// Lit color of a surface point, in linear HDR. Tone mapping happens in post
+vec3 calculateLighting(vec3 fragPos, vec3 normal, vec3 albedo, vec3 specularColor, float shininess) {
+    vec3 norm = normalize(normal);
+    vec3 viewDir = normalize(viewPos - fragPos);
+
+    // Ambient with subtle color variation
+    vec3 ambient = ambientLight.rgb * ambientLight.a * (1.0 + AMBIENT_COLOR_TINT * albedo);
+
+    // Point light, with improved attenuation
+    vec3 toLight = lightPos - fragPos;
+    float distance = length(toLight);
+    float attenuation = 1.0 / (1.0 + ATTENUATION_LINEAR * distance + ATTENUATION_QUADRATIC * (distance * distance));
+    vec3 lit = lightContribution(norm, viewDir, toLight / distance, lightColor, albedo, specularColor, shininess)
+        * attenuation * pointShadow(fragPos, norm);
+
+    // Sun, which doesn't fall off with distance
+    if (dot(sunColor, sunColor) > 0.0) {
+        lit += lightContribution(norm, viewDir, -normalize(sunDirection), sunColor, albedo, specularColor, shininess)
+            * sunShadow(fragPos, norm);
+    }
+
+    // Final composition
+    return (ambient + lit) * albedo;
+}
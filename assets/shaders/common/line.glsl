@@ -0,0 +1,6 @@
+// Shared by line.vert and line.frag. Values match graphics::line::LineCap
+#define CAP_BUTT 0
+#define CAP_SQUARE 1
+#define CAP_ROUND 2
+
+uniform int uCap;
@@ -0,0 +1,47 @@
+// Mirrors graphics::material::Material
+struct Material {
+    vec3 diffuse;
+    vec3 specular;
+    float shininess;
+    float opacity;
+    bool hasDiffuseMap;
+    bool hasNormalMap;
+};
+
+uniform Material uMaterial;
+uniform sampler2D uDiffuseMap; // texture unit 0
+uniform sampler2D uNormalMap;  // texture unit 1
+
+// Surface color and alpha, with the diffuse map (stored as sRGB) converted to linear
+vec4 materialAlbedo(vec3 tint, vec2 uv) {
+    vec4 albedo = vec4(tint * uMaterial.diffuse, uMaterial.opacity);
+    if (uMaterial.hasDiffuseMap) {
+        vec4 texel = texture(uDiffuseMap, uv);
+        albedo *= vec4(pow(texel.rgb, vec3(2.2)), texel.a);
+    }
+    return albedo;
+}
+
+// Applies the normal map using a tangent frame built from screen-space derivatives,
+// so meshes don't need to carry tangents
+vec3 materialNormal(vec3 normal, vec3 fragPos, vec2 uv) {
+    vec3 n = normalize(normal);
+    if (!uMaterial.hasNormalMap) {
+        return n;
+    }
+
+    vec3 dp1 = dFdx(fragPos);
+    vec3 dp2 = dFdy(fragPos);
+    vec2 duv1 = dFdx(uv);
+    vec2 duv2 = dFdy(uv);
+
+    vec3 dp2perp = cross(dp2, n);
+    vec3 dp1perp = cross(n, dp1);
+    vec3 t = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 b = dp2perp * duv1.y + dp1perp * duv2.y;
+    float invmax = inversesqrt(max(dot(t, t), dot(b, b)));
+    mat3 tbn = mat3(t * invmax, b * invmax, n);
+
+    vec3 mapped = texture(uNormalMap, uv).rgb * 2.0 - 1.0;
+    return normalize(tbn * mapped);
+}
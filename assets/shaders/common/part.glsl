@@ -0,0 +1,8 @@
+// Texture coordinates for part meshes, tiled once per unit of part size along the two axes
+// the face spans. The size is read back from the model matrix's column lengths
+vec2 tiledTexCoord(mat4 model, vec3 normal, vec2 uv) {
+    vec3 size = vec3(length(model[0].xyz), length(model[1].xyz), length(model[2].xyz));
+    vec3 n = abs(normal);
+    vec2 faceSize = n.x > 0.5 ? size.zy : (n.y > 0.5 ? size.xz : size.xy);
+    return uv * faceSize;
+}
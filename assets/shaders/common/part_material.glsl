@@ -0,0 +1,50 @@
+#include "common/lighting.glsl"
+
+// Mirrors object::part::material::PartMaterial
+struct PartMaterial {
+    int layer; // layer in uMaterialArray, -1 for no material
+    float roughness;
+    float reflectance;
+    float transparency;
+};
+
+uniform PartMaterial uPartMaterial;
+uniform sampler2DArray uMaterialArray; // texture unit 2
+
+const float MAX_MATERIAL_SHININESS = 256.0;
+
+bool hasPartMaterial() {
+    return uPartMaterial.layer >= 0;
+}
+
+// Surface color with the material texture (stored as sRGB) converted to linear and tinted
+// by the part color, so white parts show the texture as is
+vec3 partMaterialAlbedo(vec3 tint, vec2 uv) {
+    if (!hasPartMaterial()) {
+        return tint;
+    }
+    vec3 texel = texture(uMaterialArray, vec3(uv, float(uPartMaterial.layer))).rgb;
+    return pow(texel, vec3(2.2)) * tint;
+}
+
+vec3 partMaterialSpecular() {
+    if (!hasPartMaterial()) {
+        return DEFAULT_SPECULAR;
+    }
+    return vec3(mix(0.05, 1.0, uPartMaterial.reflectance));
+}
+
+// Rough surfaces get wide, dim highlights and smooth ones tight, sharp highlights
+float partMaterialShininess() {
+    if (!hasPartMaterial()) {
+        return DEFAULT_SHININESS;
+    }
+    return max(pow(MAX_MATERIAL_SHININESS, 1.0 - uPartMaterial.roughness), 1.0);
+}
+
+float partMaterialAlpha() {
+    if (!hasPartMaterial()) {
+        return 1.0;
+    }
+    return 1.0 - uPartMaterial.transparency;
+}
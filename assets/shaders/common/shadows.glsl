@@ -0,0 +1,88 @@
+// Shared by every program, updated once per frame (binding 2).
+// Needs CameraBlock and LightBlock declared first
+#define SHADOW_CASCADES 3
+
+layout (std140) uniform ShadowBlock {
+    mat4 sunMatrices[SHADOW_CASCADES];
+    vec4 cascadeSplits; // view-space distance where each cascade ends
+    vec4 shadowParams;  // x: sun shadows on, y: point shadows on, z: point shadow far plane
+};
+
+uniform sampler2DArrayShadow uSunShadowMap; // texture unit 3
+uniform samplerCube uPointShadowMap;        // texture unit 4
+uniform bool uReceiveShadow;
+
+// Pushing the lookup out along the normal hides acne on surfaces facing away from the light
+const float SUN_NORMAL_OFFSET = 0.02;
+const float SUN_SHADOW_BIAS = 0.0005;
+const float POINT_NORMAL_OFFSET = 0.03;
+const float POINT_SHADOW_BIAS = 0.005;
+const float POINT_PCF_RADIUS = 0.02;
+
+const vec3 POINT_PCF_OFFSETS[20] = vec3[](
+    vec3( 1,  1,  1), vec3( 1, -1,  1), vec3(-1, -1,  1), vec3(-1,  1,  1),
+    vec3( 1,  1, -1), vec3( 1, -1, -1), vec3(-1, -1, -1), vec3(-1,  1, -1),
+    vec3( 1,  1,  0), vec3( 1, -1,  0), vec3(-1, -1,  0), vec3(-1,  1,  0),
+    vec3( 1,  0,  1), vec3(-1,  0,  1), vec3( 1,  0, -1), vec3(-1,  0, -1),
+    vec3( 0,  1,  1), vec3( 0, -1,  1), vec3( 0, -1, -1), vec3( 0,  1, -1)
+);
+
+// 1 where the sun reaches the point, 0 where it's fully shadowed
+float sunShadow(vec3 fragPos, vec3 normal) {
+    if (!uReceiveShadow || shadowParams.x < 0.5) {
+        return 1.0;
+    }
+
+    float depth = -(view * vec4(fragPos, 1.0)).z;
+    if (depth > cascadeSplits[SHADOW_CASCADES - 1]) {
+        return 1.0;
+    }
+    int cascade = SHADOW_CASCADES - 1;
+    for (int i = 0; i < SHADOW_CASCADES; i++) {
+        if (depth < cascadeSplits[i]) {
+            cascade = i;
+            break;
+        }
+    }
+
+    // Later cascades cover more ground per texel, so they need a larger offset
+    vec3 offsetPos = fragPos + normal * SUN_NORMAL_OFFSET * float(cascade + 1);
+    vec4 lightSpace = sunMatrices[cascade] * vec4(offsetPos, 1.0);
+    vec3 coords = lightSpace.xyz / lightSpace.w * 0.5 + 0.5;
+    if (coords.z > 1.0) {
+        return 1.0;
+    }
+
+    // 3x3 PCF on top of the hardware's 2x2 comparison filtering
+    vec2 texel = 1.0 / vec2(textureSize(uSunShadowMap, 0).xy);
+    float lit = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec2 uv = coords.xy + vec2(x, y) * texel;
+            lit += texture(uSunShadowMap, vec4(uv, float(cascade), coords.z - SUN_SHADOW_BIAS));
+        }
+    }
+    return lit / 9.0;
+}
+
+// 1 where the point light reaches the point, 0 where it's fully shadowed
+float pointShadow(vec3 fragPos, vec3 normal) {
+    if (!uReceiveShadow || shadowParams.y < 0.5) {
+        return 1.0;
+    }
+
+    vec3 fromLight = fragPos + normal * POINT_NORMAL_OFFSET - lightPos;
+    float current = length(fromLight) / shadowParams.z;
+    if (current > 1.0) {
+        return 1.0;
+    }
+
+    // The cube map stores distance to the light over the far plane. Softer further from the camera
+    float radius = POINT_PCF_RADIUS * (1.0 + length(viewPos - fragPos) / shadowParams.z);
+    float lit = 0.0;
+    for (int i = 0; i < 20; i++) {
+        float closest = texture(uPointShadowMap, fromLight + POINT_PCF_OFFSETS[i] * radius).r;
+        lit += current - POINT_SHADOW_BIAS > closest ? 0.0 : 1.0;
+    }
+    return lit / 20.0;
+}
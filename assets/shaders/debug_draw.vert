@@ -0,0 +1,14 @@
+#version 330 core
+
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aColor;
+#include "common/camera.glsl"
+
+out vec3 Color;
+
+// Vertices are already in world space, batched by graphics::debug_draw
+void main()
+{
+    Color = aColor;
+    gl_Position = projection * view * vec4(aPos, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,52 @@
+#version 330 core
+
+#include "common/material.glsl"
+
+// Values match graphics::debug_view::DebugView
+#define VIEW_WIREFRAME 1
+#define VIEW_NORMALS 2
+#define VIEW_UV_CHECKER 3
+#define VIEW_UNLIT 4
+#define VIEW_DEPTH 5
+#define VIEW_OVERDRAW 6
+
+in vec3 Normal;
+in vec2 TexCoord;
+in float ViewDepth;
+
+out vec4 FragColor;
+
+uniform int uDebugView;
+uniform vec3 uColor;
+uniform bool uTextured;
+uniform sampler2D uTexture;
+uniform float uNear;
+uniform float uFar;
+
+// Colors here are shown as they are, post-processing is skipped in debug views
+void main()
+{
+    vec3 color;
+    if (uDebugView == VIEW_NORMALS) {
+        color = normalize(Normal) * 0.5 + 0.5;
+    } else if (uDebugView == VIEW_UV_CHECKER) {
+        // 8 squares per UV unit, tinted by the UV itself so flipped or rotated UVs stand out
+        vec2 cell = floor(TexCoord * 8.0);
+        float checker = mod(cell.x + cell.y, 2.0);
+        color = mix(vec3(fract(TexCoord), 0.0), vec3(1.0), 0.5) * (0.35 + 0.65 * checker);
+    } else if (uDebugView == VIEW_DEPTH) {
+        // Logarithmic, so the near range isn't all one shade. White is near
+        float depth = log(max(ViewDepth, uNear) / uNear) / log(uFar / uNear);
+        color = vec3(1.0 - clamp(depth, 0.0, 1.0));
+    } else if (uDebugView == VIEW_OVERDRAW) {
+        // Added up with additive blending. The composite pass turns the count into a heatmap
+        color = vec3(1.0, 0.0, 0.0);
+    } else {
+        vec4 albedo = materialAlbedo(uColor, TexCoord);
+        if (uTextured) {
+            albedo.rgb *= pow(texture(uTexture, TexCoord).rgb, vec3(2.2));
+        }
+        color = pow(albedo.rgb, vec3(1.0 / 2.2));
+    }
+    FragColor = vec4(color, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,23 @@
+#version 330 core
+
+// Shared by every debug view, see graphics::debug_view
+layout (location = 0) in vec3 aPos;
+layout (location = 2) in vec2 aTexCoord;
+layout (location = 3) in vec3 aNormal;
+#include "common/camera.glsl"
+
+uniform mat4 model;
+
+out vec3 Normal;
+out vec2 TexCoord;
+out float ViewDepth;
+
+void main()
+{
+    vec4 viewPos = view * model * vec4(aPos, 1.0);
+    Normal = mat3(transpose(inverse(model))) * aNormal;
+    // The mesh's own UVs, not tiled by part size, since those are what the checker is checking
+    TexCoord = aTexCoord;
+    ViewDepth = -viewPos.z;
+    gl_Position = projection * viewPos;
+}
\ No newline at end of file
@@ -0,0 +1,11 @@
+#version 330 core
+
+// Ids are offset by one, so 0 is left for pixels nothing was drawn to
+uniform int uEntityId;
+
+out uint EntityId;
+
+void main()
+{
+    EntityId = uint(uEntityId + 1);
+}
\ No newline at end of file
@@ -0,0 +1,12 @@
+#version 330 core
+
+layout (location = 0) in vec3 aPos;
+
+uniform mat4 model;
+// Set by the picking pass itself, which can run before the frame's CameraBlock is uploaded
+uniform mat4 uViewProjection;
+
+void main()
+{
+    gl_Position = uViewProjection * model * vec4(aPos, 1.0);
+}
\ No newline at end of file
@@ -1,10 +1,32 @@
 #version 330 core
 
+#include "common/line.glsl"
+
+noperspective in float Along;
+noperspective in float Side;
+noperspective in float SegmentLength;
+noperspective in float HalfWidth;
+in float Distance;
+
 uniform vec3 uColor;
+// Dashes are off when uDashLength is 0
+uniform float uDashLength;
+uniform float uGapLength;
 
 out vec4 FragColor;
 
 void main()
 {
+    if (uDashLength > 0.0 && mod(Distance, uDashLength + uGapLength) > uDashLength) {
+        discard;
+    }
+    if (uCap == CAP_ROUND) {
+        // Round off the corners of the square extension past each end
+        float past = max(-Along, Along - SegmentLength);
+        float across = Side * HalfWidth;
+        if (past > 0.0 && past * past + across * across > HalfWidth * HalfWidth) {
+            discard;
+        }
+    }
     FragColor = vec4(uColor, 1.0);
 }
\ No newline at end of file
@@ -1,11 +1,62 @@
 #version 330 core
 
+// One quad per segment, expanded to the line's width in screen space.
+// Every corner knows both ends of its segment so it can work out the direction
 layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aOther;
+layout (location = 2) in vec2 aCorner; // side (-1 or 1), end (0 at the segment's start, 1 at its end)
+layout (location = 3) in float aDistance;
 uniform mat4 model;
-uniform mat4 view;
-uniform mat4 projection;
+#include "common/camera.glsl"
+#include "common/line.glsl"
+
+uniform vec2 uViewport;
+uniform float uWidth;
+uniform bool uWorldWidth;
+
+// In pixels, measured from the segment's start along it and out from its center
+noperspective out float Along;
+noperspective out float Side;
+noperspective out float SegmentLength;
+noperspective out float HalfWidth;
+// In world units along the whole line, for dashes
+out float Distance;
+
+vec2 toScreen(vec4 clip) {
+    return (clip.xy / clip.w * 0.5 + 0.5) * uViewport;
+}
 
 void main()
 {
-    gl_Position = projection * view * model * vec4(aPos, 1.0);
+    mat4 mvp = projection * view * model;
+    vec4 clip = mvp * vec4(aPos, 1.0);
+    vec4 otherClip = mvp * vec4(aOther, 1.0);
+    // Points behind the camera would flip to the other side of the screen
+    clip.w = max(clip.w, 1e-4);
+    otherClip.w = max(otherClip.w, 1e-4);
+
+    vec2 screen = toScreen(clip);
+    vec2 toOther = toScreen(otherClip) - screen;
+    float segmentLength = length(toOther);
+    vec2 dir = segmentLength > 1e-4 ? toOther / segmentLength : vec2(1.0, 0.0);
+    // The direction flips at the segment's end, so the side has to as well
+    float side = aCorner.y > 0.5 ? -aCorner.x : aCorner.x;
+
+    float halfWidth = uWidth * 0.5;
+    if (uWorldWidth) {
+        halfWidth *= projection[1][1] * uViewport.y * 0.5 / clip.w;
+    }
+    // Thinner than a pixel breaks up into dots
+    halfWidth = max(halfWidth, 0.5);
+    float cap = uCap == CAP_BUTT ? 0.0 : halfWidth;
+
+    vec2 offset = vec2(-dir.y, dir.x) * side * halfWidth - dir * cap;
+    clip.xy += offset / uViewport * 2.0 * clip.w;
+    gl_Position = clip;
+
+    Along = aCorner.y > 0.5 ? segmentLength + cap : -cap;
+    Side = aCorner.x;
+    SegmentLength = segmentLength;
+    HalfWidth = halfWidth;
+    Distance = aDistance;
 }
\ No newline at end of file
@@ -10,8 +10,7 @@ out vec3 Normal;
 out vec2 TexCoord;
 
 uniform mat4 model;
-uniform mat4 view;
-uniform mat4 projection;
+#include "common/camera.glsl"
 
 void main() {
     // Calculate world position for lighting
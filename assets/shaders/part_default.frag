@@ -1,24 +1,22 @@
 #version 330 core
 
-in float fragDistance;
+#include "common/lighting.glsl"
+#include "common/gradient.glsl"
+#include "common/part_material.glsl"
 
-out vec4 FragColor;
+in vec3 FragPos;
+in vec3 LocalPos;
+in vec3 Normal;
+in vec2 TexCoord;
 
-/* Fog constants */
-const vec3  FOG_COLOR = vec3(0.0, 0.0, 0.0);
-const float FOG_START = 0.0;
-const float FOG_END   = 10.0;
+out vec4 FragColor;
 
 uniform vec3 uColor;
+uniform float uTransparency;
 
 void main()
 {
-    float fogFactor = clamp(
-        (FOG_END - fragDistance) / (FOG_END - FOG_START),
-        0.0,
-        1.0
-    );
-
-    vec3 color = mix(FOG_COLOR, uColor, fogFactor);
-    FragColor = vec4(uColor, 1.0);
-}
\ No newline at end of file
+    vec3 albedo = partMaterialAlbedo(gradientColor(LocalPos, uColor), TexCoord);
+    vec3 result = calculateLighting(FragPos, Normal, albedo, partMaterialSpecular(), partMaterialShininess());
+    FragColor = vec4(result, partMaterialAlpha() * (1.0 - uTransparency));
+}
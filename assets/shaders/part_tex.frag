@@ -1,17 +1,27 @@
 #version 330 core
 
+#include "common/lighting.glsl"
+#include "common/gradient.glsl"
+#include "common/part_material.glsl"
+
+in vec3 FragPos;
+in vec3 LocalPos;
+in vec3 Normal;
 in vec2 TexCoord;
 
 out vec4 FragColor;
 
 uniform sampler2D uTexture;
 uniform vec3 uColor;
+uniform float uTransparency;
 
 void main()
 {
-    vec3 texColor = texture(uTexture, TexCoord).rgb;
-    
-    vec3 n = mix(texColor, uColor, 0.5);
+    // Textures are stored as sRGB, lighting happens in linear space
+    vec3 texColor = pow(texture(uTexture, TexCoord).rgb, vec3(2.2));
+    vec3 albedo = mix(texColor, gradientColor(LocalPos, uColor), 0.5);
+    albedo = partMaterialAlbedo(albedo, TexCoord);
 
-    FragColor = vec4(n, 1.0);
+    vec3 result = calculateLighting(FragPos, Normal, albedo, partMaterialSpecular(), partMaterialShininess());
+    FragColor = vec4(result, partMaterialAlpha() * (1.0 - uTransparency));
 }
@@ -1,20 +1,25 @@
 #version 330 core
 
+#include "common/camera.glsl"
+#include "common/part.glsl"
+
 layout (location = 0) in vec3 aPos;
-layout (location = 1) in vec2 aTexCoord;
+layout (location = 2) in vec2 aTexCoord;
+layout (location = 3) in vec3 aNormal;
 
+out vec3 FragPos;
+out vec3 LocalPos;
+out vec3 Normal;
 out vec2 TexCoord;
 
 uniform mat4 model;
-uniform mat4 view;
-uniform mat4 projection;
 
 void main()
 {
     vec4 worldPos = model * vec4(aPos, 1.0);
-    vec4 viewPos  = view * worldPos;
-
-    TexCoord = aTexCoord;
-
-    gl_Position = projection * viewPos;
+    FragPos = worldPos.xyz;
+    LocalPos = aPos;
+    Normal = mat3(transpose(inverse(model))) * aNormal;
+    TexCoord = tiledTexCoord(model, aNormal, aTexCoord);
+    gl_Position = projection * view * worldPos;
 }
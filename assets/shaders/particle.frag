@@ -0,0 +1,30 @@
+#version 330 core
+
+in vec2 TexCoord;
+in vec4 Color;
+
+out vec4 FragColor;
+
+uniform bool uTextured;
+uniform sampler2D uTexture;
+// Counts layers for the overdraw debug view instead of shading
+uniform bool uOverdraw;
+
+void main()
+{
+    if (uOverdraw) {
+        FragColor = vec4(1.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    vec4 color = Color;
+    if (uTextured) {
+        // Textures are stored as sRGB, the scene is linear
+        vec4 texel = texture(uTexture, TexCoord);
+        color *= vec4(pow(texel.rgb, vec3(2.2)), texel.a);
+    } else {
+        // A soft round dot
+        float distance = length(TexCoord - 0.5) * 2.0;
+        color.a *= 1.0 - smoothstep(0.3, 1.0, distance);
+    }
+    FragColor = color;
+}
\ No newline at end of file
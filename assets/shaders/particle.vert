@@ -0,0 +1,22 @@
+#version 330 core
+
+// One unit quad, instanced once per particle, see graphics::particles
+layout (location = 0) in vec2 aCorner;
+layout (location = 1) in vec3 aCenter;
+layout (location = 2) in float aSize;
+layout (location = 3) in vec4 aColor;
+#include "common/camera.glsl"
+
+out vec2 TexCoord;
+out vec4 Color;
+
+void main()
+{
+    // The camera's right and up, so the particle always faces it
+    vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+    vec3 pos = aCenter + (right * aCorner.x + up * aCorner.y) * aSize;
+    gl_Position = projection * view * vec4(pos, 1.0);
+    TexCoord = aCorner + 0.5;
+    Color = aColor;
+}
\ No newline at end of file
@@ -0,0 +1,17 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+out vec4 FragColor;
+
+uniform sampler2D uInput; // HDR scene
+uniform float uThreshold;
+
+void main()
+{
+    vec3 color = texture(uInput, TexCoord).rgb;
+    // Keep only what's brighter than the threshold, fading in rather than cutting off
+    float brightness = max(color.r, max(color.g, color.b));
+    float contribution = max(brightness - uThreshold, 0.0) / max(brightness, 0.0001);
+    FragColor = vec4(color * contribution, 1.0);
+}
@@ -0,0 +1,25 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+out vec4 FragColor;
+
+uniform sampler2D uInput;
+uniform bool uHorizontal;
+
+// 9-tap gaussian folded into 5 taps with bilinear filtering
+const float OFFSETS[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float WEIGHTS[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
+void main()
+{
+    vec2 texel = 1.0 / vec2(textureSize(uInput, 0));
+    vec2 step = uHorizontal ? vec2(texel.x, 0.0) : vec2(0.0, texel.y);
+
+    vec3 result = texture(uInput, TexCoord).rgb * WEIGHTS[0];
+    for (int i = 1; i < 3; i++) {
+        result += texture(uInput, TexCoord + step * OFFSETS[i]).rgb * WEIGHTS[i];
+        result += texture(uInput, TexCoord - step * OFFSETS[i]).rgb * WEIGHTS[i];
+    }
+    FragColor = vec4(result, 1.0);
+}
@@ -0,0 +1,98 @@
+#version 330 core
+
+// Turns the linear HDR scene into the final gamma-corrected image:
+// bloom, exposure, tone mapping, color grading and vignette. Mirrors graphics::post::PostSettings
+
+#define TONEMAP_REINHARD 0
+#define TONEMAP_ACES 1
+
+// Values match graphics::debug_view::DebugView
+#define VIEW_LIT 0
+#define VIEW_OVERDRAW 6
+
+in vec2 TexCoord;
+
+out vec4 FragColor;
+
+uniform sampler2D uInput; // HDR scene
+uniform sampler2D uBloom; // blurred bright parts, half resolution
+
+uniform bool uBloomEnabled;
+uniform float uBloomIntensity;
+
+uniform float uExposure;
+uniform int uTonemapper;
+
+uniform bool uGradingEnabled;
+uniform float uContrast;
+uniform float uSaturation;
+uniform vec3 uTint;
+
+uniform bool uVignetteEnabled;
+uniform float uVignetteStrength;
+uniform float uVignetteRadius;
+uniform float uVignetteSoftness;
+
+uniform int uDebugView;
+
+vec3 reinhard(vec3 color) {
+    return color / (color + vec3(1.0));
+}
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 aces(vec3 color) {
+    return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+}
+
+// Black for nothing drawn, then blue, green, yellow, orange and red at 5 or more layers
+vec3 heatmap(float count) {
+    const vec3 stops[6] = vec3[](
+        vec3(0.0), vec3(0.0, 0.2, 1.0), vec3(0.0, 1.0, 0.2),
+        vec3(1.0, 1.0, 0.0), vec3(1.0, 0.5, 0.0), vec3(1.0, 0.0, 0.0)
+    );
+    float t = clamp(count, 0.0, 5.0);
+    int i = int(t);
+    return mix(stops[i], stops[min(i + 1, 5)], t - float(i));
+}
+
+void main()
+{
+    vec3 color = texture(uInput, TexCoord).rgb;
+    // Debug views are already display colors, apart from overdraw's layer count
+    if (uDebugView == VIEW_OVERDRAW) {
+        FragColor = vec4(heatmap(color.r), 1.0);
+        return;
+    }
+    if (uDebugView != VIEW_LIT) {
+        FragColor = vec4(color, 1.0);
+        return;
+    }
+
+    if (uBloomEnabled) {
+        color += texture(uBloom, TexCoord).rgb * uBloomIntensity;
+    }
+
+    color *= uExposure;
+    color = uTonemapper == TONEMAP_ACES ? aces(color) : reinhard(color);
+
+    if (uGradingEnabled) {
+        color *= uTint;
+        float luma = dot(color, vec3(0.2126, 0.7152, 0.0722));
+        color = mix(vec3(luma), color, uSaturation);
+    }
+
+    color = pow(clamp(color, 0.0, 1.0), vec3(1.0 / 2.2));
+
+    if (uGradingEnabled) {
+        // Contrast pivots around middle grey, which only sits at 0.5 after gamma correction
+        color = clamp((color - 0.5) * uContrast + 0.5, 0.0, 1.0);
+    }
+
+    if (uVignetteEnabled) {
+        float distance = length(TexCoord - 0.5) * 1.41421356;
+        float falloff = smoothstep(uVignetteRadius, uVignetteRadius + uVignetteSoftness, distance);
+        color *= 1.0 - falloff * uVignetteStrength;
+    }
+
+    FragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,48 @@
+#version 330 core
+
+// Fast approximate anti-aliasing, run on the final gamma-corrected image.
+// Blurs along edges found from the luma of the four diagonal neighbours
+
+in vec2 TexCoord;
+
+out vec4 FragColor;
+
+uniform sampler2D uInput;
+
+const float FXAA_REDUCE_MIN = 1.0 / 128.0;
+const float FXAA_REDUCE_MUL = 1.0 / 8.0;
+const float FXAA_SPAN_MAX = 8.0;
+const vec3 LUMA = vec3(0.299, 0.587, 0.114);
+
+void main()
+{
+    vec2 texel = 1.0 / vec2(textureSize(uInput, 0));
+
+    float lumaNW = dot(texture(uInput, TexCoord + vec2(-1.0, -1.0) * texel).rgb, LUMA);
+    float lumaNE = dot(texture(uInput, TexCoord + vec2(1.0, -1.0) * texel).rgb, LUMA);
+    float lumaSW = dot(texture(uInput, TexCoord + vec2(-1.0, 1.0) * texel).rgb, LUMA);
+    float lumaSE = dot(texture(uInput, TexCoord + vec2(1.0, 1.0) * texel).rgb, LUMA);
+    vec3 rgbM = texture(uInput, TexCoord).rgb;
+    float lumaM = dot(rgbM, LUMA);
+
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    vec2 dir = vec2(
+        -((lumaNW + lumaNE) - (lumaSW + lumaSE)),
+        (lumaNW + lumaSW) - (lumaNE + lumaSE)
+    );
+    float dirReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+    float rcpDirMin = 1.0 / (min(abs(dir.x), abs(dir.y)) + dirReduce);
+    dir = clamp(dir * rcpDirMin, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX)) * texel;
+
+    vec3 rgbA = 0.5 * (
+        texture(uInput, TexCoord + dir * (1.0 / 3.0 - 0.5)).rgb +
+        texture(uInput, TexCoord + dir * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 rgbB = rgbA * 0.5 + 0.25 * (
+        texture(uInput, TexCoord - dir * 0.5).rgb +
+        texture(uInput, TexCoord + dir * 0.5).rgb);
+
+    float lumaB = dot(rgbB, LUMA);
+    FragColor = vec4((lumaB < lumaMin || lumaB > lumaMax) ? rgbA : rgbB, 1.0);
+}
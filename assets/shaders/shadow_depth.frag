@@ -0,0 +1,18 @@
+#version 330 core
+
+// Directional shadows only need the depth buffer. Point shadows store the distance to the
+// light over the far plane instead, so every face of the cube map uses the same scale
+
+in vec3 FragPos;
+
+#ifdef POINT_SHADOW
+uniform vec3 uLightPos;
+uniform float uFarPlane;
+#endif
+
+void main()
+{
+#ifdef POINT_SHADOW
+    gl_FragDepth = length(FragPos - uLightPos) / uFarPlane;
+#endif
+}
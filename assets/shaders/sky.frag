@@ -0,0 +1,44 @@
+#version 330 core
+
+#include "common/lighting.glsl"
+
+in vec3 ViewDir;
+
+out vec4 FragColor;
+
+uniform bool uProcedural;
+uniform samplerCube uSkybox; // texture unit 5
+
+// Procedural sky, colors are linear
+uniform vec3 uZenithColor;
+uniform vec3 uHorizonColor;
+uniform vec3 uGroundColor;
+uniform float uSunSize; // angular radius of the sun disk, in radians
+
+const float SUN_DISK_INTENSITY = 20.0;
+const float SUN_GLOW_POWER = 64.0;
+const float SUN_GLOW_STRENGTH = 0.4;
+
+vec3 proceduralSky(vec3 dir) {
+    vec3 color = dir.y > 0.0
+        ? mix(uHorizonColor, uZenithColor, sqrt(dir.y))
+        : mix(uHorizonColor, uGroundColor, sqrt(-dir.y));
+
+    if (dot(sunColor, sunColor) > 0.0) {
+        float cosAngle = dot(dir, -normalize(sunDirection));
+        float disk = smoothstep(cos(uSunSize), cos(uSunSize * 0.8), cosAngle);
+        float glow = pow(max(cosAngle, 0.0), SUN_GLOW_POWER) * SUN_GLOW_STRENGTH;
+        color += sunColor * (disk * SUN_DISK_INTENSITY + glow);
+    }
+    return color;
+}
+
+void main()
+{
+    vec3 dir = normalize(ViewDir);
+    vec3 color = uProcedural
+        ? proceduralSky(dir)
+        // Skybox images are stored as sRGB
+        : pow(texture(uSkybox, dir).rgb, vec3(2.2));
+    FragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,17 @@
+#version 330 core
+
+#include "common/camera.glsl"
+
+out vec3 ViewDir;
+
+// One triangle covering the whole screen, no vertex buffer needed
+void main()
+{
+    vec2 pos = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    gl_Position = vec4(pos, 1.0, 1.0);
+
+    // Only the camera's rotation matters, the sky is infinitely far away
+    mat4 rotation = mat4(mat3(view));
+    vec4 farPoint = inverse(projection * rotation) * vec4(pos, 1.0, 1.0);
+    ViewDir = farPoint.xyz / farPoint.w;
+}
@@ -0,0 +1,21 @@
+#version 330 core
+
+in vec2 TexCoord;
+
+uniform sampler2D uFontAtlas;
+uniform vec3 uColor;
+
+out vec4 FragColor;
+
+void main()
+{
+    // The atlas holds distance to the glyph's edge, 0.5 being on it.
+    // Smoothing over about a pixel keeps edges sharp but not aliased at any size
+    float distance = texture(uFontAtlas, TexCoord).r;
+    float width = max(fwidth(distance), 1e-4) * 0.7;
+    float alpha = smoothstep(0.5 - width, 0.5 + width, distance);
+    if (alpha <= 0.0) {
+        discard;
+    }
+    FragColor = vec4(uColor, alpha);
+}
\ No newline at end of file
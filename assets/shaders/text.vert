@@ -0,0 +1,37 @@
+#version 330 core
+
+layout (location = 0) in vec2 aPos;
+layout (location = 2) in vec2 aTexCoord;
+#include "common/camera.glsl"
+
+// Values match graphics::text::TextSpace
+#define SPACE_SCREEN 0
+#define SPACE_BILLBOARD 1
+#define SPACE_FIXED_BILLBOARD 2
+
+uniform int uTextSpace;
+uniform vec3 uAnchor;    // world position, for billboards
+uniform vec2 uScreenPos; // pixels from the top left, for screen text
+uniform float uScale;    // layout units to pixels or world units
+uniform vec2 uViewport;
+
+out vec2 TexCoord;
+
+void main()
+{
+    vec2 local = aPos * uScale;
+    if (uTextSpace == SPACE_SCREEN) {
+        vec2 pixel = vec2(uScreenPos.x, uViewport.y - uScreenPos.y) + local;
+        gl_Position = vec4(pixel / uViewport * 2.0 - 1.0, 0.0, 1.0);
+    } else if (uTextSpace == SPACE_BILLBOARD) {
+        // The camera's right and up, so the text always faces it
+        vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+        vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+        gl_Position = projection * view * vec4(uAnchor + right * local.x + up * local.y, 1.0);
+    } else {
+        vec4 clip = projection * view * vec4(uAnchor, 1.0);
+        clip.xy += local / uViewport * 2.0 * clip.w;
+        gl_Position = clip;
+    }
+    TexCoord = aTexCoord;
+}
\ No newline at end of file
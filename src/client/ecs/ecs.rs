@@ -1,8 +1,17 @@
 use std::collections::HashMap;
+use std::rc::Rc;
 
 use nalgebra_glm::{self as glm, Vec3};
 
-use crate::graphics::{shader, texture::Texture};
+use crate::graphics::{
+    debug_draw::DebugDraw, debug_view::DebugView, frustum::Aabb, gradient::Gradient,
+    line::LineStyle, material::Material, particles::ParticleEmitter, render_stats::RenderStats,
+    shader, sky::Sky, text::Text, texture::Texture,
+};
+use crate::object::part::{
+    material::PartMaterial,
+    shape::{PartMeshCache, PartShape},
+};
 
 // Components
 #[derive(Debug, Clone, Copy)]
@@ -11,6 +20,7 @@ pub struct Position(pub glm::Vec3);
 pub struct Rotation(pub glm::Vec3);
 #[derive(Debug, Clone, Copy)]
 pub struct Scale(pub glm::Vec3);
+#[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
 pub struct Velocity(pub glm::Vec3);
 #[derive(Debug, Clone, Copy)]
@@ -21,13 +31,47 @@ pub struct PartRenderData {
     pub vao_id: u32,
     pub index_count: i32,
 }
+/// 0 is opaque, 1 is invisible
 #[derive(Debug, Clone, Copy)]
+pub struct Transparency(pub f32);
+#[derive(Debug, Clone)]
 pub struct Shader(pub shader::Shader);
+#[derive(Debug, Clone, Copy)]
+pub enum LightKind {
+    /// Shines in every direction from the entity's position
+    Point,
+    /// Parallel light travelling in the given direction, like the sun
+    Directional(glm::Vec3),
+}
+#[derive(Debug, Clone, Copy)]
+pub struct Light {
+    pub color: glm::Vec3,
+    pub intensity: f32,
+    pub kind: LightKind,
+    pub cast_shadows: bool,
+}
+/// Local-space bounds of an entity's mesh, used for culling. Entities without one are always drawn
+#[derive(Debug, Clone, Copy)]
+pub struct Bounds(pub Aabb);
+/// Local-space triangles to pick the entity by. Entities without one are picked by their
+/// `Bounds`, rotated with them
+#[derive(Debug, Clone)]
+pub struct PickMesh(pub Rc<Vec<[glm::Vec3; 3]>>);
+/// Local-space vertex positions and normals, drawn as lines by the normals debug view
+#[derive(Debug, Clone)]
+pub struct MeshNormals(pub Rc<Vec<(glm::Vec3, glm::Vec3)>>);
+/// Whether the entity shows up in shadow maps. Entities without one cast shadows
+#[derive(Debug, Clone, Copy)]
+pub struct CastShadow(pub bool);
+/// Whether shadows darken the entity. Entities without one receive shadows
+#[derive(Debug, Clone, Copy)]
+pub struct ReceiveShadow(pub bool);
 
 pub enum EntityType {
     Part,
+    #[allow(dead_code)]
     Special,
-    Line(Vec3), // start, end, color
+    Line(Vec3), // color
 }
 
 pub struct World {
@@ -35,12 +79,34 @@ pub struct World {
     pub positions: HashMap<usize, Position>,
     pub rotations: HashMap<usize, Rotation>,
     pub scales: HashMap<usize, Scale>,
+    #[allow(dead_code)]
     pub velocities: HashMap<usize, Velocity>,
     pub colors: HashMap<usize, Color>,
     pub part_render_data: HashMap<usize, PartRenderData>,
     pub textures: HashMap<usize, Texture>,
+    pub materials: HashMap<usize, Material>,
+    pub gradients: HashMap<usize, Gradient>,
+    pub transparencies: HashMap<usize, Transparency>,
     pub shaders: HashMap<usize, Shader>,
+    pub lights: HashMap<usize, Light>,
+    pub cast_shadows: HashMap<usize, CastShadow>,
+    pub receive_shadows: HashMap<usize, ReceiveShadow>,
     pub entity_types: HashMap<usize, EntityType>,
+    pub shapes: HashMap<usize, PartShape>,
+    pub part_materials: HashMap<usize, PartMaterial>,
+    pub bounds: HashMap<usize, Bounds>,
+    pub pick_meshes: HashMap<usize, PickMesh>,
+    pub mesh_normals: HashMap<usize, MeshNormals>,
+    pub line_styles: HashMap<usize, LineStyle>,
+    pub texts: HashMap<usize, Text>,
+    pub particle_emitters: HashMap<usize, ParticleEmitter>,
+
+    // Resources
+    pub part_meshes: PartMeshCache,
+    pub sky: Sky,
+    pub debug_draw: DebugDraw,
+    pub render_stats: RenderStats,
+    pub debug_view: DebugView,
 }
 
 impl World {
@@ -54,8 +120,27 @@ impl World {
             colors: HashMap::new(),
             part_render_data: HashMap::new(),
             textures: HashMap::new(),
+            materials: HashMap::new(),
+            gradients: HashMap::new(),
+            transparencies: HashMap::new(),
             shaders: HashMap::new(),
+            lights: HashMap::new(),
+            cast_shadows: HashMap::new(),
+            receive_shadows: HashMap::new(),
             entity_types: HashMap::new(),
+            shapes: HashMap::new(),
+            part_materials: HashMap::new(),
+            bounds: HashMap::new(),
+            pick_meshes: HashMap::new(),
+            mesh_normals: HashMap::new(),
+            line_styles: HashMap::new(),
+            texts: HashMap::new(),
+            particle_emitters: HashMap::new(),
+            part_meshes: PartMeshCache::new(),
+            sky: Sky::default(),
+            debug_draw: DebugDraw::new(),
+            render_stats: RenderStats::new(),
+            debug_view: DebugView::default(),
         }
     }
 
@@ -65,6 +150,7 @@ impl World {
         entity_id
     }
 
+    #[allow(dead_code)]
     pub fn destroy_entity(&mut self, entity: usize) {
         self.positions.remove(&entity);
         self.rotations.remove(&entity);
@@ -73,7 +159,35 @@ impl World {
         self.colors.remove(&entity);
         self.part_render_data.remove(&entity);
         self.textures.remove(&entity);
+        self.materials.remove(&entity);
+        self.gradients.remove(&entity);
+        self.transparencies.remove(&entity);
         self.shaders.remove(&entity);
+        self.lights.remove(&entity);
+        self.cast_shadows.remove(&entity);
+        self.receive_shadows.remove(&entity);
         self.entity_types.remove(&entity);
+        self.shapes.remove(&entity);
+        self.part_materials.remove(&entity);
+        self.bounds.remove(&entity);
+        self.pick_meshes.remove(&entity);
+        self.mesh_normals.remove(&entity);
+        self.line_styles.remove(&entity);
+        self.texts.remove(&entity);
+        self.particle_emitters.remove(&entity);
+    }
+
+    /// Points every entity using `old_program` at `shader` instead
+    pub fn replace_shader(&mut self, old_program: u32, shader: &shader::Shader) {
+        for current in self.shaders.values_mut() {
+            if current.0.program == old_program {
+                current.0 = shader.clone();
+            }
+        }
+        for render_data in self.part_render_data.values_mut() {
+            if render_data.program_id == old_program {
+                render_data.program_id = shader.program;
+            }
+        }
     }
 }
@@ -1,10 +1,20 @@
 use crate::ecs::ecs::{self as ECS, PartRenderData};
-use crate::graphics::shader::{Shader, VertexArrayObject};
+use crate::graphics::line::{LINE_SHADER, LineMesh, LineStyle};
+use crate::graphics::shader::{Shader, ShaderCache};
 use crate::graphics::texture::Texture;
 use crate::object::part::RenderData;
-use crate::object::part::consts::{PART_INDICES_COLOR, PART_VERTICES};
+use crate::object::part::shape::PartShape;
 use nalgebra_glm as glm;
 
+pub const PART_SHADER: (&str, &str) = (
+    "assets/shaders/part_default.vert",
+    "assets/shaders/part_default.frag",
+);
+pub const PART_TEX_SHADER: (&str, &str) = (
+    "assets/shaders/part_tex.vert",
+    "assets/shaders/part_tex.frag",
+);
+
 // pub fn spawn_part(
 //     world: &mut ECS::World,
 //     position: glm::Vec3,
@@ -42,54 +52,27 @@ use nalgebra_glm as glm;
 //     entity
 // }
 
+/// Spawns a part of the given shape. Textured parts are drawn with `part_tex`, which tiles
+/// the texture by the part's size; everything else uses the default part shader.
+/// Parts of the same shape share one mesh from `world.part_meshes`
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_part(
     world: &mut ECS::World,
+    shader_cache: &mut ShaderCache,
+    shape: PartShape,
     position: glm::Vec3,
     rotation: glm::Vec3,
     scale: glm::Vec3,
     color: glm::Vec3,
-    shader: &Shader,
     texture: Option<Texture>,
-) -> usize {
-    let entity = world.create_entity();
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let shader = match texture {
+        Some(_) => shader_cache.get(PART_TEX_SHADER.0, PART_TEX_SHADER.1, &[])?,
+        None => shader_cache.get(PART_SHADER.0, PART_SHADER.1, &[])?,
+    };
+    let mesh = world.part_meshes.get(shape);
 
-    let (mut vbo, mut ebo) = (0, 0);
-    let mut vao = VertexArrayObject::new();
-
-    unsafe {
-        gl::GenVertexArrays(1, &mut vao.id);
-        gl::GenBuffers(1, &mut vbo);
-        gl::GenBuffers(1, &mut ebo);
-
-        gl::BindVertexArray(vao.id);
-
-        // -------- VBO --------
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (PART_VERTICES.len() * std::mem::size_of::<f32>()) as _,
-            PART_VERTICES.as_ptr() as *const _,
-            gl::STATIC_DRAW,
-        );
-
-        // -------- EBO --------
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-        gl::BufferData(
-            gl::ELEMENT_ARRAY_BUFFER,
-            (PART_INDICES_COLOR.len() * std::mem::size_of::<u32>()) as _,
-            PART_INDICES_COLOR.as_ptr() as *const _,
-            gl::STATIC_DRAW,
-        );
-
-        // -------- Vertex Attributes --------
-        let stride = 3 * std::mem::size_of::<f32>() as i32;
-
-        // Position (location = 0)
-        gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
-        gl::EnableVertexAttribArray(0);
-
-        gl::BindVertexArray(0);
-    }
+    let entity = world.create_entity();
 
     world.positions.insert(entity, ECS::Position(position));
     world.rotations.insert(entity, ECS::Rotation(rotation));
@@ -99,20 +82,26 @@ pub fn spawn_part(
         entity,
         ECS::PartRenderData {
             program_id: shader.program,
-            vao_id: vao.id,
-            index_count: PART_INDICES_COLOR.len() as i32,
+            vao_id: mesh.vao_id,
+            index_count: mesh.index_count,
         },
     );
-    world.shaders.insert(entity, ECS::Shader(*shader));
+    world.shaders.insert(entity, ECS::Shader(shader));
     world.entity_types.insert(entity, ECS::EntityType::Part);
+    world.shapes.insert(entity, shape);
+    world.bounds.insert(entity, ECS::Bounds(mesh.bounds));
+    world
+        .mesh_normals
+        .insert(entity, ECS::MeshNormals(mesh.normals));
 
     if let Some(tex) = texture {
         world.textures.insert(entity, tex);
     }
 
-    entity
+    Ok(entity)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn add_render_data_to_world(
     world: &mut ECS::World,
     position: glm::Vec3,
@@ -129,10 +118,13 @@ pub fn add_render_data_to_world(
     world.rotations.insert(entity, ECS::Rotation(rotation));
     world.scales.insert(entity, ECS::Scale(scale));
     world.colors.insert(entity, ECS::Color(color));
-    world.shaders.insert(entity, ECS::Shader(*shader));
+    world.shaders.insert(entity, ECS::Shader(shader.clone()));
     if let Some(tex) = texture {
         world.textures.insert(entity, tex);
     }
+    world
+        .materials
+        .insert(entity, render_data.material.clone().unwrap_or_default());
     world.part_render_data.insert(
         entity,
         PartRenderData {
@@ -142,47 +134,50 @@ pub fn add_render_data_to_world(
         },
     );
     world.entity_types.insert(entity, ECS::EntityType::Part);
+    world.bounds.insert(entity, ECS::Bounds(render_data.bounds));
+    world
+        .pick_meshes
+        .insert(entity, ECS::PickMesh(render_data.triangles.clone()));
+    if !render_data.normals.is_empty() {
+        world
+            .mesh_normals
+            .insert(entity, ECS::MeshNormals(render_data.normals.clone()));
+    }
 }
 
+/// Spawns a straight line from `start` to `end`. See `spawn_polyline`
 pub fn spawn_line(
     world: &mut ECS::World,
+    shader_cache: &mut ShaderCache,
     start: glm::Vec3,
     end: glm::Vec3,
     color: glm::Vec3,
-    shader: &Shader,
-) -> usize {
-    let entity = world.create_entity();
+    style: LineStyle,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    spawn_polyline(world, shader_cache, &[start, end], false, color, style)
+}
 
-    let mut vao = 0;
-    let mut vbo = 0;
-    let line_vertices: [f32; 6] = [start.x, start.y, start.z, end.x, end.y, end.z];
-
-    unsafe {
-        gl::GenVertexArrays(1, &mut vao);
-        gl::GenBuffers(1, &mut vbo);
-
-        gl::BindVertexArray(vao);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (line_vertices.len() * std::mem::size_of::<f32>()) as isize,
-            line_vertices.as_ptr() as *const _,
-            gl::STATIC_DRAW,
-        );
-
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            3 * std::mem::size_of::<f32>() as i32,
-            std::ptr::null(),
-        );
-        gl::EnableVertexAttribArray(0);
-        gl::BindVertexArray(0);
+/// Spawns a line through `points`, closed back to the first point if `closed`.
+/// The entity sits at the first point, so moving its `Position` moves the whole line
+pub fn spawn_polyline(
+    world: &mut ECS::World,
+    shader_cache: &mut ShaderCache,
+    points: &[glm::Vec3],
+    closed: bool,
+    color: glm::Vec3,
+    style: LineStyle,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    if points.len() < 2 {
+        return Err("A line needs at least two points".into());
     }
+    let shader = shader_cache.get(LINE_SHADER.0, LINE_SHADER.1, &[])?;
+    let origin = points[0];
+    let local: Vec<glm::Vec3> = points.iter().map(|p| p - origin).collect();
+    let mesh = LineMesh::new(&local, closed);
+
+    let entity = world.create_entity();
 
-    world.positions.insert(entity, ECS::Position(start));
+    world.positions.insert(entity, ECS::Position(origin));
     world
         .rotations
         .insert(entity, ECS::Rotation(glm::vec3(0., 0., 0.)));
@@ -193,15 +188,17 @@ pub fn spawn_line(
     world
         .entity_types
         .insert(entity, ECS::EntityType::Line( color));
-    world.shaders.insert(entity, ECS::Shader(*shader));
+    world.shaders.insert(entity, ECS::Shader(shader.clone()));
     world.part_render_data.insert(
         entity,
         ECS::PartRenderData {
             program_id: shader.program,
-            vao_id: vao,
-            index_count: 2,
+            vao_id: mesh.vao_id,
+            index_count: mesh.index_count,
         },
     );
+    world.bounds.insert(entity, ECS::Bounds(mesh.bounds));
+    world.line_styles.insert(entity, style);
 
-    entity
+    Ok(entity)
 }
@@ -1,2 +1,5 @@
+#[allow(clippy::module_inception)]
 pub mod ecs;
-pub mod funcs;
\ No newline at end of file
+pub mod funcs;
+pub mod picking;
+pub mod render;
\ No newline at end of file
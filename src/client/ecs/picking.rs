@@ -0,0 +1,261 @@
+use nalgebra_glm as glm;
+
+use crate::ecs::ecs::{EntityType, World};
+use crate::ecs::render;
+use crate::graphics::camera::Camera3d;
+use crate::graphics::frustum::Ray;
+use crate::graphics::id_buffer::IdBuffer;
+use crate::graphics::shader::ShaderCache;
+
+/// Where a picking ray met an entity
+#[derive(Debug, Clone, Copy)]
+pub struct PickHit {
+    pub entity: usize,
+    pub point: glm::Vec3,
+    /// Surface normal at `point`, facing out of the entity
+    pub normal: glm::Vec3,
+    /// From the ray's origin to `point`
+    pub distance: f32,
+}
+
+/// The nearest entity `ray` hits within `max_distance`. `ray.direction` should be normalized,
+/// so distances are in world units. Lines, text and anything without a transform can't be
+/// picked
+pub fn raycast(world: &World, ray: &Ray, max_distance: f32) -> Option<PickHit> {
+    world
+        .entity_types
+        .keys()
+        .filter_map(|&entity| raycast_entity(world, ray, entity))
+        .filter(|hit| hit.distance <= max_distance)
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+/// Where `ray` hits one entity. Entities with a `PickMesh` are tested triangle by triangle,
+/// everything else against its `Bounds` rotated and scaled with it, which for parts is the
+/// part's oriented box
+pub fn raycast_entity(world: &World, ray: &Ray, entity: usize) -> Option<PickHit> {
+    if matches!(world.entity_types.get(&entity), Some(EntityType::Line(_)))
+        || !world.positions.contains_key(&entity)
+        || !world.rotations.contains_key(&entity)
+        || !world.scales.contains_key(&entity)
+    {
+        return None;
+    }
+    let bounds = world.bounds.get(&entity)?.0;
+    // A zero scale leaves nothing to hit
+    let model = render::model_matrix(world, entity);
+    let inverse = model.try_inverse()?;
+    let local = ray.transformed(&inverse);
+
+    let mut hit = bounds.intersect_ray(&local)?;
+    if let Some(mesh) = world.pick_meshes.get(&entity) {
+        hit = mesh
+            .0
+            .iter()
+            .filter_map(|triangle| intersect_triangle(&local, triangle))
+            .min_by(|a, b| a.0.total_cmp(&b.0))?;
+    }
+
+    let (distance, normal) = hit;
+    // Normals go through the inverse transpose, so they stay perpendicular under uneven scale
+    let normal = inverse.fixed_view::<3, 3>(0, 0).transpose() * normal;
+    Some(PickHit {
+        entity,
+        point: ray.at(distance),
+        normal: normal.normalize(),
+        distance,
+    })
+}
+
+/// Picks through the id buffer instead of raycasting: the entity is whatever was drawn at
+/// `screen` (window coordinates from the top left), and the point comes from its depth.
+/// The normal is the entity's raycast normal there, or faces the camera if that misses
+pub fn pick_with_id_buffer(
+    world: &World,
+    camera: &Camera3d,
+    id_buffer: &IdBuffer,
+    shader_cache: &mut ShaderCache,
+    screen: glm::Vec2,
+    window_size: glm::Vec2,
+    framebuffer_size: glm::Vec2,
+) -> Result<Option<PickHit>, Box<dyn std::error::Error>> {
+    let pixel = screen
+        .component_mul(&framebuffer_size)
+        .component_div(&window_size);
+    let Some((entity, depth)) = id_buffer.pick(
+        world,
+        camera,
+        shader_cache,
+        (pixel.x as i32, pixel.y as i32),
+    )?
+    else {
+        return Ok(None);
+    };
+
+    let ndc = glm::vec4(
+        screen.x / window_size.x * 2.0 - 1.0,
+        1.0 - screen.y / window_size.y * 2.0,
+        depth * 2.0 - 1.0,
+        1.0,
+    );
+    let inverse = glm::inverse(&(camera.get_projection_matrix() * camera.get_view_matrix()));
+    let point = inverse * ndc;
+    let point = point.xyz() / point.w;
+
+    let ray = camera.screen_ray(screen, window_size);
+    let normal = raycast_entity(world, &ray, entity)
+        .map(|hit| hit.normal)
+        .unwrap_or(-ray.direction);
+    Ok(Some(PickHit {
+        entity,
+        point,
+        normal,
+        distance: glm::distance(&camera.position, &point),
+    }))
+}
+
+/// Möller-Trumbore. Hits from either side, with the normal facing back along the ray
+fn intersect_triangle(ray: &Ray, [a, b, c]: &[glm::Vec3; 3]) -> Option<(f32, glm::Vec3)> {
+    let (ab, ac) = (b - a, c - a);
+    let p = ray.direction.cross(&ac);
+    let determinant = ab.dot(&p);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let to_origin = ray.origin - a;
+    let u = to_origin.dot(&p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = to_origin.cross(&ab);
+    let v = ray.direction.dot(&q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = ac.dot(&q) * inverse;
+    if t < 0.0 {
+        return None;
+    }
+    let normal = ab.cross(&ac);
+    let normal = if normal.dot(&ray.direction) > 0.0 {
+        -normal
+    } else {
+        normal
+    };
+    Some((t, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::ecs::ecs::{Bounds, PickMesh, Position, Rotation, Scale};
+    use crate::graphics::frustum::Aabb;
+
+    const TRIANGLE: [glm::Vec3; 3] = [
+        glm::Vec3::new(0., 0., 0.),
+        glm::Vec3::new(1., 0., 0.),
+        glm::Vec3::new(0., 1., 0.),
+    ];
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    /// A part at the origin with the unit cube as its bounds, scaled by `scale`
+    fn scaled_part(world: &mut World, scale: glm::Vec3) -> usize {
+        let entity = world.create_entity();
+        world.entity_types.insert(entity, EntityType::Part);
+        world.positions.insert(entity, Position(glm::Vec3::zeros()));
+        world.rotations.insert(entity, Rotation(glm::Vec3::zeros()));
+        world.scales.insert(entity, Scale(scale));
+        let half = glm::vec3(0.5, 0.5, 0.5);
+        world.bounds.insert(entity, Bounds(Aabb::new(-half, half)));
+        entity
+    }
+
+    #[test]
+    fn triangle_hit() {
+        let ray = Ray::new(glm::vec3(0.25, 0.25, 1.), glm::vec3(0., 0., -1.));
+        let (distance, normal) = intersect_triangle(&ray, &TRIANGLE).unwrap();
+        assert_near(distance, 1.);
+        assert_eq!(normal, glm::vec3(0., 0., 1.));
+    }
+
+    #[test]
+    fn triangle_miss() {
+        let down = glm::vec3(0., 0., -1.);
+        // Beside the hypotenuse, past the triangle's plane and parallel to it
+        let beside = Ray::new(glm::vec3(0.75, 0.75, 1.), down);
+        let behind = Ray::new(glm::vec3(0.25, 0.25, 1.), -down);
+        let parallel = Ray::new(glm::vec3(-1., 0.25, 0.), glm::vec3(1., 0., 0.));
+        assert_eq!(intersect_triangle(&beside, &TRIANGLE), None);
+        assert_eq!(intersect_triangle(&behind, &TRIANGLE), None);
+        assert_eq!(intersect_triangle(&parallel, &TRIANGLE), None);
+    }
+
+    #[test]
+    fn triangle_edges_and_corners_hit() {
+        let down = glm::vec3(0., 0., -1.);
+        for point in [
+            glm::vec3(0.5, 0., 1.),
+            glm::vec3(0., 0.5, 1.),
+            glm::vec3(0.5, 0.5, 1.),
+            glm::vec3(0., 0., 1.),
+        ] {
+            let hit = intersect_triangle(&Ray::new(point, down), &TRIANGLE);
+            assert_near(hit.expect("edge should hit").0, 1.);
+        }
+    }
+
+    #[test]
+    fn triangle_back_face_hit_faces_the_ray() {
+        let ray = Ray::new(glm::vec3(0.25, 0.25, -1.), glm::vec3(0., 0., 1.));
+        let (distance, normal) = intersect_triangle(&ray, &TRIANGLE).unwrap();
+        assert_near(distance, 1.);
+        assert_eq!(normal, glm::vec3(0., 0., -1.));
+    }
+
+    #[test]
+    fn raycast_distance_is_in_world_units_under_uneven_scale() {
+        let mut world = World::new();
+        let entity = scaled_part(&mut world, glm::vec3(4., 1., 1.));
+
+        // Along the stretched axis the box reaches x = -2
+        let along = Ray::new(glm::vec3(-10., 0., 0.), glm::vec3(1., 0., 0.));
+        let hit = raycast_entity(&world, &along, entity).unwrap();
+        assert_near(hit.distance, 8.);
+        assert_eq!(hit.normal, glm::vec3(-1., 0., 0.));
+
+        // Diagonally, where the local ray's direction has a different length than the world one
+        let direction = glm::vec3(1., 1., 0.).normalize();
+        let diagonal = Ray::new(glm::vec3(-10., -10., 0.), direction);
+        let hit = raycast_entity(&world, &diagonal, entity).unwrap();
+        assert_near(hit.distance, 9.5 * std::f32::consts::SQRT_2);
+        assert_near(hit.distance, glm::distance(&diagonal.origin, &hit.point));
+        assert_near(hit.point.y, -0.5);
+        assert_eq!(hit.normal, glm::vec3(0., -1., 0.));
+    }
+
+    #[test]
+    fn raycast_pick_mesh_under_uneven_scale() {
+        let mut world = World::new();
+        let entity = scaled_part(&mut world, glm::vec3(4., 1., 2.));
+        let triangle = [
+            glm::vec3(-0.5, -0.5, 0.),
+            glm::vec3(0.5, -0.5, 0.),
+            glm::vec3(0., 0.5, 0.),
+        ];
+        world
+            .pick_meshes
+            .insert(entity, PickMesh(Rc::new(vec![triangle])));
+
+        let ray = Ray::new(glm::vec3(1., 0., 10.), glm::vec3(0., 0., -1.));
+        let hit = raycast_entity(&world, &ray, entity).unwrap();
+        assert_near(hit.distance, 10.);
+        assert_near(hit.distance, glm::distance(&ray.origin, &hit.point));
+        assert_eq!(hit.normal, glm::vec3(0., 0., 1.));
+    }
+}
@@ -0,0 +1,245 @@
+use nalgebra_glm as glm;
+
+use crate::ecs::ecs::{self as ECS, World};
+use crate::graphics::camera::Camera3d;
+use crate::graphics::frustum::{Aabb, Frustum};
+use crate::graphics::gradient::Gradient;
+use crate::graphics::render_stats;
+use crate::graphics::uniform_buffer::LightBlock;
+use crate::object::part::material::PartMaterial;
+
+pub fn model_matrix(world: &World, entity: usize) -> glm::Mat4 {
+    let pos = world.positions.get(&entity).unwrap();
+    let rot = world.rotations.get(&entity).unwrap();
+    let scale = world.scales.get(&entity).unwrap();
+
+    let rotation_matrix = glm::rotation(rot.0.y, &glm::vec3(0., 1., 0.))
+        * glm::rotation(rot.0.x, &glm::vec3(1., 0., 0.))
+        * glm::rotation(rot.0.z, &glm::vec3(0., 0., 1.));
+
+    glm::translate(&glm::identity(), &pos.0) * rotation_matrix * glm::scaling(&scale.0)
+}
+
+/// The point light that lights the scene, the one with the lowest entity id
+pub fn point_light(world: &World) -> Option<(glm::Vec3, &ECS::Light)> {
+    world
+        .lights
+        .iter()
+        .filter(|(_, light)| matches!(light.kind, ECS::LightKind::Point))
+        .min_by_key(|(entity, _)| **entity)
+        .map(|(entity, light)| (world.positions.get(entity).unwrap().0, light))
+}
+
+/// The directional light that lights the scene, the one with the lowest entity id
+pub fn sun_light(world: &World) -> Option<(glm::Vec3, &ECS::Light)> {
+    world
+        .lights
+        .iter()
+        .filter_map(|(entity, light)| match light.kind {
+            ECS::LightKind::Directional(direction) => Some((entity, direction, light)),
+            ECS::LightKind::Point => None,
+        })
+        .min_by_key(|(entity, _, _)| **entity)
+        .map(|(_, direction, light)| (direction.normalize(), light))
+}
+
+pub fn light_block(world: &World) -> LightBlock {
+    let zero = glm::vec4(0., 0., 0., 0.);
+    let (light_pos, light_color) = point_light(world).map_or((zero, zero), |(pos, light)| {
+        (
+            glm::vec3_to_vec4(&pos),
+            glm::vec3_to_vec4(&(light.color * light.intensity)),
+        )
+    });
+    let (sun_direction, sun_color) = sun_light(world).map_or((zero, zero), |(dir, light)| {
+        (
+            glm::vec3_to_vec4(&dir),
+            glm::vec3_to_vec4(&(light.color * light.intensity)),
+        )
+    });
+
+    LightBlock {
+        light_pos,
+        light_color,
+        sun_direction,
+        sun_color,
+        ambient_light: world.sky.ambient(),
+    }
+}
+
+/// World-space bounds of an entity, if it has a `Bounds`
+pub fn world_bounds(world: &World, entity: usize) -> Option<Aabb> {
+    let bounds = world.bounds.get(&entity)?;
+    Some(bounds.0.transformed(&model_matrix(world, entity)))
+}
+
+pub fn in_frustum(world: &World, entity: usize, frustum: &Frustum) -> bool {
+    world_bounds(world, entity).is_none_or(|bounds| frustum.intersects_aabb(&bounds))
+}
+
+pub fn casts_shadow(world: &World, entity: usize) -> bool {
+    world.cast_shadows.get(&entity).is_none_or(|c| c.0)
+}
+
+pub fn receives_shadow(world: &World, entity: usize) -> bool {
+    world.receive_shadows.get(&entity).is_none_or(|r| r.0)
+}
+
+/// Overall opacity of an entity, combining its `Transparency`, its mesh material's
+/// opacity (MTL `d`/`Tr`) and its part material. Anything below 1 goes in the translucent pass
+pub fn opacity(world: &World, entity: usize) -> f32 {
+    let mut opacity = 1.0 - world.transparencies.get(&entity).map_or(0.0, |t| t.0);
+    if let Some(material) = world.materials.get(&entity) {
+        opacity *= material.opacity;
+    }
+    if let Some(material) = world.part_materials.get(&entity) {
+        opacity *= 1.0 - material.properties().transparency;
+    }
+    opacity.clamp(0.0, 1.0)
+}
+
+/// What `render_world` did with the entities it was given
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameStats {
+    pub drawn: usize,
+    /// Skipped for being outside the camera's frustum
+    pub culled: usize,
+}
+
+/// Renderable entities the camera can see, split into opaque ones and translucent ones sorted
+/// back-to-front from the camera
+pub fn visible_entities(world: &World, camera: &Camera3d) -> (Vec<usize>, Vec<usize>, FrameStats) {
+    let frustum = camera.frustum();
+    let camera_pos = &camera.position;
+    let mut stats = FrameStats::default();
+    let mut opaque = Vec::new();
+    let mut translucent = Vec::new();
+    for &entity in world.part_render_data.keys() {
+        if !world.shaders.contains_key(&entity) {
+            continue;
+        }
+        if !in_frustum(world, entity, &frustum) {
+            stats.culled += 1;
+            continue;
+        }
+        match world.entity_types.get(&entity) {
+            Some(ECS::EntityType::Part) if opacity(world, entity) < 1.0 => {
+                let distance = glm::distance2(&world.positions.get(&entity).unwrap().0, camera_pos);
+                translucent.push((entity, distance));
+            }
+            _ => opaque.push(entity),
+        }
+    }
+
+    stats.drawn = opaque.len() + translucent.len();
+    translucent.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    let translucent = translucent.into_iter().map(|(entity, _)| entity).collect();
+    (opaque, translucent, stats)
+}
+
+/// Draws every renderable entity the camera can see. Opaque entities go first, then
+/// translucent ones with blending on and depth writes off, so they blend over everything
+/// behind them without hiding each other
+pub fn render_world(world: &World, camera: &Camera3d) -> FrameStats {
+    let (opaque, translucent, stats) = visible_entities(world, camera);
+    for entity in opaque {
+        draw_entity(world, entity);
+    }
+
+    unsafe {
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        gl::DepthMask(gl::FALSE);
+    }
+    for entity in translucent {
+        draw_entity(world, entity);
+    }
+    unsafe {
+        gl::DepthMask(gl::TRUE);
+        gl::Disable(gl::BLEND);
+    }
+
+    stats
+}
+
+pub fn draw_entity(world: &World, entity: usize) {
+    let render_data = world.part_render_data.get(&entity).unwrap();
+    let shader = &world.shaders.get(&entity).unwrap().0;
+
+    shader.use_program();
+
+    match world.entity_types.get(&entity) {
+        Some(ECS::EntityType::Line(color)) => {
+            shader
+                .set_mat4("model", &model_matrix(world, entity))
+                .unwrap();
+            shader.set_vec3("uColor", color).unwrap();
+            world
+                .line_styles
+                .get(&entity)
+                .copied()
+                .unwrap_or_default()
+                .apply(shader)
+                .unwrap();
+            unsafe {
+                // A segment's quad faces either way depending on its direction on screen
+                gl::Disable(gl::CULL_FACE);
+            }
+            draw_elements(render_data.vao_id, render_data.index_count);
+            unsafe {
+                render_stats::bind_vertex_array(0);
+                gl::Enable(gl::CULL_FACE);
+            }
+        }
+        Some(ECS::EntityType::Part) => {
+            shader
+                .set_mat4("model", &model_matrix(world, entity))
+                .unwrap();
+            shader
+                .set_vec3("uColor", &world.colors.get(&entity).unwrap().0)
+                .unwrap();
+            shader
+                .set_float(
+                    "uTransparency",
+                    world.transparencies.get(&entity).map_or(0.0, |t| t.0),
+                )
+                .unwrap();
+            shader
+                .set_bool("uReceiveShadow", receives_shadow(world, entity))
+                .unwrap();
+
+            if let Some(tex) = world.textures.get(&entity) {
+                tex.bind(0);
+                shader.set_int("uTexture", 0).unwrap();
+            }
+            if let Some(material) = world.materials.get(&entity) {
+                material.apply(shader).unwrap();
+            }
+            match world.gradients.get(&entity) {
+                Some(gradient) => gradient.apply(shader).unwrap(),
+                None => Gradient::clear(shader).unwrap(),
+            }
+            match world.part_materials.get(&entity) {
+                Some(material) => material.apply(shader).unwrap(),
+                None => PartMaterial::clear(shader).unwrap(),
+            }
+
+            draw_elements(render_data.vao_id, render_data.index_count);
+        }
+        _ => {}
+    }
+}
+
+/// Draws an indexed triangle mesh with whatever program and uniforms are already set
+pub fn draw_elements(vao_id: u32, index_count: i32) {
+    unsafe {
+        render_stats::bind_vertex_array(vao_id);
+        gl::DrawElements(
+            gl::TRIANGLES,
+            index_count,
+            gl::UNSIGNED_INT,
+            std::ptr::null(),
+        );
+    }
+    render_stats::record_draw(gl::TRIANGLES, index_count);
+}
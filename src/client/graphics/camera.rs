@@ -9,8 +9,9 @@ const MAX_ZOOM: f32 = 145.0;
 const MIN_ZOOM: f32 = 0.01;
 
 use glfw::Key;
-use nalgebra_glm::{Mat4, Vec3};
+use nalgebra_glm::{Mat4, Vec2, Vec3};
 
+use crate::graphics::frustum::{Frustum, Ray};
 use crate::graphics::windowing::GameWindow;
 
 pub struct Camera3d {
@@ -31,6 +32,7 @@ pub struct Camera3d {
 }
 
 impl Camera3d {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         position: Vec3,
         world_up: Vec3,
@@ -82,6 +84,30 @@ impl Camera3d {
         )
     }
 
+    /// What the camera currently sees, for culling
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(&(self.get_projection_matrix() * self.get_view_matrix()))
+    }
+
+    /// Ray from the camera through a point in the window, given in window coordinates with
+    /// the origin at the top left
+    pub fn screen_ray(&self, screen: Vec2, window_size: Vec2) -> Ray {
+        let ndc = nalgebra_glm::vec2(
+            screen.x / window_size.x * 2.0 - 1.0,
+            1.0 - screen.y / window_size.y * 2.0,
+        );
+        let inverse =
+            nalgebra_glm::inverse(&(self.get_projection_matrix() * self.get_view_matrix()));
+        let far = inverse * nalgebra_glm::vec4(ndc.x, ndc.y, 1.0, 1.0);
+        let direction = (far.xyz() / far.w - self.position).normalize();
+        Ray::new(self.position, direction)
+    }
+
+    /// Ray through the middle of the screen, where the crosshair is
+    pub fn crosshair_ray(&self) -> Ray {
+        Ray::new(self.position, self.front)
+    }
+
     pub fn process_mouse(&mut self, xoff: f32, yoff: f32) {
         let xf = xoff * self.mouse_sensitivity;
         let yf = yoff * self.mouse_sensitivity;
@@ -91,14 +117,14 @@ impl Camera3d {
 
         if self.constrain_pitch {
             // Clamps pitch between -89 and 89 deg
-            self.pitch = self.pitch.max(-89f32).min(89f32);
+            self.pitch = self.pitch.clamp(-89f32, 89f32);
         }
 
         self.update_vectors();
     }
 
     pub fn process_scroll(&mut self, y_offset: f32) {
-        self.zoom = f32::max(f32::min(self.zoom - y_offset, MAX_ZOOM), MIN_ZOOM);
+        self.zoom = (self.zoom - y_offset).clamp(MIN_ZOOM, MAX_ZOOM);
     }
 
     pub fn update_vectors(&mut self) {
@@ -114,6 +140,7 @@ impl Camera3d {
         self.up = self.right.cross(&self.front).normalize();
     }
 
+    #[allow(dead_code)]
     pub fn set_aspect_ratio(&mut self, w: f32, h: f32) {
         self.aspect_ratio = w / h;
     }
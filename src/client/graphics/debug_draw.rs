@@ -0,0 +1,410 @@
+use std::f32::consts::PI;
+
+use gl::types::GLuint;
+use nalgebra_glm as glm;
+
+use crate::graphics::camera::Camera3d;
+use crate::graphics::frustum::Aabb;
+use crate::graphics::render_stats;
+use crate::graphics::shader::ShaderCache;
+
+pub const DEBUG_DRAW_SHADER: (&str, &str) = (
+    "assets/shaders/debug_draw.vert",
+    "assets/shaders/debug_draw.frag",
+);
+
+/// Position then color
+const DEBUG_VERTEX_FLOATS: usize = 6;
+const CIRCLE_SEGMENTS: usize = 24;
+
+/// Stroke font on a 4 wide, 6 tall grid, as (x0, y0, x1, y1) segments with y up
+const GLYPH_WIDTH: f32 = 4.0;
+const GLYPH_HEIGHT: f32 = 6.0;
+const GLYPH_ADVANCE: f32 = 6.0;
+const GLYPH_LINE_HEIGHT: f32 = 9.0;
+
+struct DebugLine {
+    start: glm::Vec3,
+    end: glm::Vec3,
+    color: glm::Vec3,
+    /// Seconds left to draw, counting down after each frame
+    remaining: f32,
+}
+
+struct DebugText {
+    position: glm::Vec3,
+    text: String,
+    size: f32,
+    color: glm::Vec3,
+    remaining: f32,
+}
+
+/// Immediate-mode lines for debugging, drawn from anywhere with access to the world.
+/// Everything queued is batched into one vertex buffer and drawn with a single call.
+/// Every call takes a `duration` in seconds; 0 draws for the current frame only
+pub struct DebugDraw {
+    pub enabled: bool,
+    lines: Vec<DebugLine>,
+    texts: Vec<DebugText>,
+    vertices: Vec<f32>,
+    /// Created on the first draw, like the sky's
+    vao: GLuint,
+    vbo: GLuint,
+    /// Size of the vertex buffer in floats, grown as needed
+    capacity: usize,
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        DebugDraw::new()
+    }
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        DebugDraw {
+            enabled: true,
+            lines: Vec::new(),
+            texts: Vec::new(),
+            vertices: Vec::new(),
+            vao: 0,
+            vbo: 0,
+            capacity: 0,
+        }
+    }
+
+    pub fn line(&mut self, start: glm::Vec3, end: glm::Vec3, color: glm::Vec3, duration: f32) {
+        self.lines.push(DebugLine {
+            start,
+            end,
+            color,
+            remaining: duration,
+        });
+    }
+
+    pub fn ray(
+        &mut self,
+        origin: glm::Vec3,
+        direction: glm::Vec3,
+        length: f32,
+        color: glm::Vec3,
+        duration: f32,
+    ) {
+        self.line(
+            origin,
+            origin + direction.normalize() * length,
+            color,
+            duration,
+        );
+    }
+
+    /// A line with a four-pronged head at `end`
+    pub fn arrow(&mut self, start: glm::Vec3, end: glm::Vec3, color: glm::Vec3, duration: f32) {
+        self.line(start, end, color, duration);
+
+        let direction = end - start;
+        let length = direction.magnitude();
+        if length < 1e-6 {
+            return;
+        }
+        let direction = direction / length;
+        let (side, up) = perpendiculars(&direction);
+        let head = length * 0.15;
+        let back = end - direction * head;
+        for offset in [side, -side, up, -up] {
+            self.line(end, back + offset * head * 0.5, color, duration);
+        }
+    }
+
+    pub fn aabb(&mut self, aabb: &Aabb, color: glm::Vec3, duration: f32) {
+        let (min, max) = (aabb.min, aabb.max);
+        let corner = |i: usize| {
+            glm::vec3(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        // Corners one bit apart share an edge
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color, duration);
+                }
+            }
+        }
+    }
+
+    pub fn circle(
+        &mut self,
+        center: glm::Vec3,
+        normal: glm::Vec3,
+        radius: f32,
+        color: glm::Vec3,
+        duration: f32,
+    ) {
+        let (u, v) = perpendiculars(&normal.normalize());
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * 2.0 * PI;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color, duration);
+        }
+    }
+
+    /// Three circles, one around each axis
+    pub fn sphere(&mut self, center: glm::Vec3, radius: f32, color: glm::Vec3, duration: f32) {
+        for axis in [glm::Vec3::x(), glm::Vec3::y(), glm::Vec3::z()] {
+            self.circle(center, axis, radius, color, duration);
+        }
+    }
+
+    /// A square grid on the XZ plane, `size` wide with `divisions` cells along each side
+    pub fn grid(
+        &mut self,
+        center: glm::Vec3,
+        size: f32,
+        divisions: u32,
+        color: glm::Vec3,
+        duration: f32,
+    ) {
+        let divisions = divisions.max(1);
+        let half = size * 0.5;
+        for i in 0..=divisions {
+            let t = i as f32 / divisions as f32 * size - half;
+            self.line(
+                center + glm::vec3(t, 0., -half),
+                center + glm::vec3(t, 0., half),
+                color,
+                duration,
+            );
+            self.line(
+                center + glm::vec3(-half, 0., t),
+                center + glm::vec3(half, 0., t),
+                color,
+                duration,
+            );
+        }
+    }
+
+    /// Text that always faces the camera, centered above `position`. `size` is the height of
+    /// a capital letter in world units. Lowercase is drawn as uppercase, and characters the
+    /// stroke font doesn't have as '?'
+    pub fn text3d(
+        &mut self,
+        position: glm::Vec3,
+        text: &str,
+        size: f32,
+        color: glm::Vec3,
+        duration: f32,
+    ) {
+        self.texts.push(DebugText {
+            position,
+            text: text.to_owned(),
+            size,
+            color,
+            remaining: duration,
+        });
+    }
+
+    /// Drops everything queued, including anything with time left
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.texts.clear();
+    }
+
+    /// Draws everything queued, then ages it by `delta_time` and drops what has run out.
+    /// Call it once per frame after the scene so the lines are depth tested against it
+    pub fn render(
+        &mut self,
+        camera: &Camera3d,
+        delta_time: f32,
+        shader_cache: &mut ShaderCache,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.enabled {
+            self.build_vertices(camera);
+            self.draw(shader_cache)?;
+        }
+
+        for line in &mut self.lines {
+            line.remaining -= delta_time;
+        }
+        for text in &mut self.texts {
+            text.remaining -= delta_time;
+        }
+        self.lines.retain(|line| line.remaining > 0.0);
+        self.texts.retain(|text| text.remaining > 0.0);
+        Ok(())
+    }
+
+    fn build_vertices(&mut self, camera: &Camera3d) {
+        self.vertices.clear();
+        let mut push = |start: &glm::Vec3, end: &glm::Vec3, color: &glm::Vec3| {
+            self.vertices
+                .extend_from_slice(&[start.x, start.y, start.z, color.x, color.y, color.z]);
+            self.vertices
+                .extend_from_slice(&[end.x, end.y, end.z, color.x, color.y, color.z]);
+        };
+
+        for line in &self.lines {
+            push(&line.start, &line.end, &line.color);
+        }
+
+        for text in &self.texts {
+            let scale = text.size / GLYPH_HEIGHT;
+            let (right, up) = (camera.right * scale, camera.up * scale);
+            for (row, line) in text.text.lines().enumerate() {
+                let width =
+                    line.chars().count() as f32 * GLYPH_ADVANCE - (GLYPH_ADVANCE - GLYPH_WIDTH);
+                let origin =
+                    text.position - right * (width * 0.5) - up * (row as f32 * GLYPH_LINE_HEIGHT);
+                for (column, c) in line.chars().enumerate() {
+                    let glyph_origin = origin + right * (column as f32 * GLYPH_ADVANCE);
+                    for &(x0, y0, x1, y1) in glyph(c) {
+                        let start = glyph_origin + right * x0 as f32 + up * y0 as f32;
+                        let end = glyph_origin + right * x1 as f32 + up * y1 as f32;
+                        push(&start, &end, &text.color);
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw(&mut self, shader_cache: &mut ShaderCache) -> Result<(), Box<dyn std::error::Error>> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+
+        let shader = shader_cache.get(DEBUG_DRAW_SHADER.0, DEBUG_DRAW_SHADER.1, &[])?;
+        shader.use_program();
+
+        unsafe {
+            if self.vao == 0 {
+                gl::GenVertexArrays(1, &mut self.vao);
+                gl::GenBuffers(1, &mut self.vbo);
+                render_stats::bind_vertex_array(self.vao);
+                gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+                let stride = (DEBUG_VERTEX_FLOATS * std::mem::size_of::<f32>()) as i32;
+                gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+                gl::EnableVertexAttribArray(0);
+                gl::VertexAttribPointer(
+                    1,
+                    3,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    stride,
+                    (3 * std::mem::size_of::<f32>()) as *const _,
+                );
+                gl::EnableVertexAttribArray(1);
+            }
+
+            render_stats::bind_vertex_array(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            // Grow to the next power of two, otherwise orphan the old storage and refill it
+            if self.vertices.len() > self.capacity {
+                self.capacity = self.vertices.len().next_power_of_two();
+            }
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (self.capacity * std::mem::size_of::<f32>()) as isize,
+                std::ptr::null(),
+                gl::STREAM_DRAW,
+            );
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                std::mem::size_of_val(self.vertices.as_slice()) as isize,
+                self.vertices.as_ptr() as *const _,
+            );
+            gl::DrawArrays(
+                gl::LINES,
+                0,
+                (self.vertices.len() / DEBUG_VERTEX_FLOATS) as i32,
+            );
+            render_stats::bind_vertex_array(0);
+        }
+        render_stats::record_draw(
+            gl::LINES,
+            (self.vertices.len() / DEBUG_VERTEX_FLOATS) as i32,
+        );
+
+        Ok(())
+    }
+}
+
+/// Two unit vectors perpendicular to `direction` and to each other
+fn perpendiculars(direction: &glm::Vec3) -> (glm::Vec3, glm::Vec3) {
+    let reference = if direction.y.abs() < 0.99 {
+        glm::Vec3::y()
+    } else {
+        glm::Vec3::x()
+    };
+    let side = direction.cross(&reference).normalize();
+    let up = side.cross(direction).normalize();
+    (side, up)
+}
+
+type Segment = (i8, i8, i8, i8);
+
+#[rustfmt::skip]
+fn glyph(c: char) -> &'static [Segment] {
+    const O: &[Segment] = &[(0, 0, 0, 6), (0, 6, 4, 6), (4, 6, 4, 0), (4, 0, 0, 0)];
+    const S: &[Segment] = &[(4, 6, 0, 6), (0, 6, 0, 3), (0, 3, 4, 3), (4, 3, 4, 0), (4, 0, 0, 0)];
+    match c.to_ascii_uppercase() {
+        ' ' => &[],
+        'A' => &[(0, 0, 0, 4), (0, 4, 2, 6), (2, 6, 4, 4), (4, 4, 4, 0), (0, 3, 4, 3)],
+        'B' => &[(0, 0, 0, 6), (0, 6, 3, 6), (3, 6, 4, 5), (4, 5, 4, 4), (4, 4, 3, 3), (0, 3, 3, 3),
+                 (3, 3, 4, 2), (4, 2, 4, 1), (4, 1, 3, 0), (3, 0, 0, 0)],
+        'C' => &[(4, 6, 0, 6), (0, 6, 0, 0), (0, 0, 4, 0)],
+        'D' => &[(0, 0, 0, 6), (0, 6, 2, 6), (2, 6, 4, 4), (4, 4, 4, 2), (4, 2, 2, 0), (2, 0, 0, 0)],
+        'E' => &[(4, 6, 0, 6), (0, 6, 0, 0), (0, 0, 4, 0), (0, 3, 3, 3)],
+        'F' => &[(4, 6, 0, 6), (0, 6, 0, 0), (0, 3, 3, 3)],
+        'G' => &[(4, 6, 0, 6), (0, 6, 0, 0), (0, 0, 4, 0), (4, 0, 4, 3), (4, 3, 2, 3)],
+        'H' => &[(0, 0, 0, 6), (4, 0, 4, 6), (0, 3, 4, 3)],
+        'I' => &[(0, 6, 4, 6), (2, 6, 2, 0), (0, 0, 4, 0)],
+        'J' => &[(4, 6, 4, 0), (4, 0, 0, 0), (0, 0, 0, 2)],
+        'K' => &[(0, 0, 0, 6), (0, 3, 4, 6), (0, 3, 4, 0)],
+        'L' => &[(0, 6, 0, 0), (0, 0, 4, 0)],
+        'M' => &[(0, 0, 0, 6), (0, 6, 2, 3), (2, 3, 4, 6), (4, 6, 4, 0)],
+        'N' => &[(0, 0, 0, 6), (0, 6, 4, 0), (4, 0, 4, 6)],
+        'O' => O,
+        'P' => &[(0, 0, 0, 6), (0, 6, 4, 6), (4, 6, 4, 3), (4, 3, 0, 3)],
+        'Q' => &[(0, 0, 0, 6), (0, 6, 4, 6), (4, 6, 4, 0), (4, 0, 0, 0), (2, 2, 4, 0)],
+        'R' => &[(0, 0, 0, 6), (0, 6, 4, 6), (4, 6, 4, 3), (4, 3, 0, 3), (2, 3, 4, 0)],
+        'S' | '5' => S,
+        'T' => &[(0, 6, 4, 6), (2, 6, 2, 0)],
+        'U' => &[(0, 6, 0, 0), (0, 0, 4, 0), (4, 0, 4, 6)],
+        'V' => &[(0, 6, 2, 0), (2, 0, 4, 6)],
+        'W' => &[(0, 6, 1, 0), (1, 0, 2, 3), (2, 3, 3, 0), (3, 0, 4, 6)],
+        'X' => &[(0, 0, 4, 6), (0, 6, 4, 0)],
+        'Y' => &[(0, 6, 2, 3), (4, 6, 2, 3), (2, 3, 2, 0)],
+        'Z' => &[(0, 6, 4, 6), (4, 6, 0, 0), (0, 0, 4, 0)],
+        '0' => &[(0, 0, 0, 6), (0, 6, 4, 6), (4, 6, 4, 0), (4, 0, 0, 0), (0, 0, 4, 6)],
+        '1' => &[(1, 5, 2, 6), (2, 6, 2, 0), (0, 0, 4, 0)],
+        '2' => &[(0, 6, 4, 6), (4, 6, 4, 3), (4, 3, 0, 3), (0, 3, 0, 0), (0, 0, 4, 0)],
+        '3' => &[(0, 6, 4, 6), (4, 6, 4, 0), (4, 0, 0, 0), (1, 3, 4, 3)],
+        '4' => &[(0, 6, 0, 3), (0, 3, 4, 3), (4, 6, 4, 0)],
+        '6' => &[(4, 6, 0, 6), (0, 6, 0, 0), (0, 0, 4, 0), (4, 0, 4, 3), (4, 3, 0, 3)],
+        '7' => &[(0, 6, 4, 6), (4, 6, 1, 0)],
+        '8' => &[(0, 0, 0, 6), (0, 6, 4, 6), (4, 6, 4, 0), (4, 0, 0, 0), (0, 3, 4, 3)],
+        '9' => &[(4, 3, 0, 3), (0, 3, 0, 6), (0, 6, 4, 6), (4, 6, 4, 0), (4, 0, 0, 0)],
+        '.' => &[(2, 0, 2, 1)],
+        ',' => &[(2, 1, 1, -1)],
+        ':' => &[(2, 1, 2, 2), (2, 4, 2, 5)],
+        '-' => &[(0, 3, 4, 3)],
+        '+' => &[(0, 3, 4, 3), (2, 1, 2, 5)],
+        '=' => &[(0, 2, 4, 2), (0, 4, 4, 4)],
+        '*' => &[(0, 1, 4, 5), (0, 5, 4, 1), (2, 0, 2, 6)],
+        '/' => &[(0, 0, 4, 6)],
+        '_' => &[(0, -1, 4, -1)],
+        '(' => &[(3, 6, 1, 4), (1, 4, 1, 2), (1, 2, 3, 0)],
+        ')' => &[(1, 6, 3, 4), (3, 4, 3, 2), (3, 2, 1, 0)],
+        '<' => &[(4, 6, 0, 3), (0, 3, 4, 0)],
+        '>' => &[(0, 6, 4, 3), (4, 3, 0, 0)],
+        '!' => &[(2, 6, 2, 2), (2, 1, 2, 0)],
+        '\'' => &[(2, 6, 2, 4)],
+        '"' => &[(1, 6, 1, 4), (3, 6, 3, 4)],
+        _ => &[(0, 6, 4, 6), (4, 6, 4, 3), (4, 3, 2, 3), (2, 3, 2, 2), (2, 1, 2, 0)],
+    }
+}
@@ -0,0 +1,520 @@
+use glfw::{Action, Modifiers, MouseButton, WindowEvent};
+use imgui::{Condition, Drag, FocusedWidget, Key, Ui};
+use log::{debug, warn};
+
+use crate::ecs::ecs::{EntityType, World};
+use crate::graphics::camera::Camera3d;
+use crate::graphics::debug_view::DebugView;
+use crate::graphics::post::{PostSettings, Tonemapper};
+use crate::graphics::render_stats::RenderStats;
+use crate::graphics::windowing::GameWindow;
+
+/// Frames kept for the frame time graph
+const FRAME_HISTORY: usize = 240;
+/// How long the capture button logs render stats for, in seconds
+const STATS_CAPTURE_TIME: f32 = 5.0;
+/// Console lines kept, oldest dropped first
+const CONSOLE_HISTORY: usize = 100;
+/// Particles the inspector's burst button emits
+const PARTICLE_BURST: usize = 50;
+
+/// ImGui windows for poking at the running game: the entities in the world, their components,
+/// the camera, frame times and a command console. Hidden until toggled, and only takes input
+/// while shown
+pub struct DebugUi {
+    pub visible: bool,
+    context: imgui::Context,
+    renderer: imgui_opengl_renderer::Renderer,
+    /// Frame times in milliseconds, a ring buffer starting at `history_start`
+    frame_times: Vec<f32>,
+    history_start: usize,
+    /// Entity shown in the inspector
+    pub selected: Option<usize>,
+    console_input: String,
+    console_log: Vec<String>,
+}
+
+impl DebugUi {
+    pub fn new(game_window: &mut GameWindow) -> Self {
+        let mut context = imgui::Context::create();
+        // Window layouts aren't worth a file in the working directory
+        context.set_ini_filename(None);
+        let renderer = imgui_opengl_renderer::Renderer::new(&mut context, |s| {
+            game_window
+                .win
+                .get_proc_address(s)
+                .map(|p| p as *const _)
+                .unwrap_or(std::ptr::null())
+        });
+        debug!("Created debug UI");
+
+        DebugUi {
+            visible: false,
+            context,
+            renderer,
+            frame_times: Vec::with_capacity(FRAME_HISTORY),
+            history_start: 0,
+            selected: None,
+            console_input: String::new(),
+            console_log: Vec::new(),
+        }
+    }
+
+    /// Whether ImGui wants the mouse, so clicks over a window don't reach the game
+    pub fn wants_mouse(&self) -> bool {
+        self.visible && self.context.io().want_capture_mouse
+    }
+
+    /// Passes a window event on to ImGui. Events are dropped while hidden, so nothing is left
+    /// held down when it's shown again
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        if !self.visible {
+            return;
+        }
+        let io = self.context.io_mut();
+        match *event {
+            WindowEvent::CursorPos(x, y) => io.add_mouse_pos_event([x as f32, y as f32]),
+            WindowEvent::MouseButton(button, action, _) => {
+                let button = match button {
+                    MouseButton::Button1 => imgui::MouseButton::Left,
+                    MouseButton::Button2 => imgui::MouseButton::Right,
+                    MouseButton::Button3 => imgui::MouseButton::Middle,
+                    MouseButton::Button4 => imgui::MouseButton::Extra1,
+                    MouseButton::Button5 => imgui::MouseButton::Extra2,
+                    _ => return,
+                };
+                io.add_mouse_button_event(button, action != Action::Release);
+            }
+            WindowEvent::Scroll(x, y) => io.add_mouse_wheel_event([x as f32, y as f32]),
+            WindowEvent::Char(c) => io.add_input_character(c),
+            WindowEvent::Key(key, _, action, modifiers) => {
+                io.add_key_event(Key::ModCtrl, modifiers.contains(Modifiers::Control));
+                io.add_key_event(Key::ModShift, modifiers.contains(Modifiers::Shift));
+                io.add_key_event(Key::ModAlt, modifiers.contains(Modifiers::Alt));
+                io.add_key_event(Key::ModSuper, modifiers.contains(Modifiers::Super));
+                if let Some(key) = imgui_key(key) {
+                    io.add_key_event(key, action != Action::Release);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Records this frame's time and, when shown, builds and draws the UI over whatever is in
+    /// the current framebuffer. Edits go straight into `world` and `camera`
+    pub fn render(
+        &mut self,
+        game_window: &GameWindow,
+        world: &mut World,
+        camera: &mut Camera3d,
+        post_settings: &mut PostSettings,
+        delta_time: f32,
+    ) {
+        if self.frame_times.len() < FRAME_HISTORY {
+            self.frame_times.push(delta_time * 1000.0);
+        } else {
+            self.frame_times[self.history_start] = delta_time * 1000.0;
+            self.history_start = (self.history_start + 1) % FRAME_HISTORY;
+        }
+        if !self.visible {
+            return;
+        }
+
+        let (width, height) = game_window.win.get_size();
+        let (fb_width, fb_height) = game_window.win.get_framebuffer_size();
+        let io = self.context.io_mut();
+        io.display_size = [width as f32, height as f32];
+        if width > 0 && height > 0 {
+            io.display_framebuffer_scale = [
+                fb_width as f32 / width as f32,
+                fb_height as f32 / height as f32,
+            ];
+        }
+        // ImGui asserts on a zero time step
+        io.delta_time = delta_time.max(1e-5);
+
+        let ui = self.context.new_frame();
+        entity_window(ui, world, &mut self.selected);
+        inspector_window(ui, world, self.selected);
+        camera_window(ui, camera);
+        post_window(ui, post_settings);
+        frame_window(
+            ui,
+            &self.frame_times,
+            self.history_start,
+            &mut world.render_stats,
+        );
+        console_window(ui, world, &mut self.console_input, &mut self.console_log);
+        self.renderer.render(&mut self.context);
+    }
+}
+
+fn entity_window(ui: &Ui, world: &World, selected: &mut Option<usize>) {
+    ui.window("Entities")
+        .position([10., 40.], Condition::FirstUseEver)
+        .size([220., 320.], Condition::FirstUseEver)
+        .build(|| {
+            for entity in 0..world.next_entity_id {
+                let Some(label) = entity_label(world, entity) else {
+                    continue;
+                };
+                if ui
+                    .selectable_config(format!("#{} {}", entity, label))
+                    .selected(*selected == Some(entity))
+                    .build()
+                {
+                    *selected = Some(entity);
+                }
+            }
+        });
+}
+
+/// A short description of what the entity is, or `None` if it has nothing worth listing
+fn entity_label(world: &World, entity: usize) -> Option<String> {
+    if let Some(light) = world.lights.get(&entity) {
+        return Some(format!("{:?} light", light.kind));
+    }
+    if let Some(shape) = world.shapes.get(&entity) {
+        return Some(format!("{:?} part", shape));
+    }
+    if let Some(emitter) = world.particle_emitters.get(&entity) {
+        return Some(format!("{:?} particles", emitter.blend));
+    }
+    if let Some(text) = world.texts.get(&entity)
+        && !world.entity_types.contains_key(&entity)
+    {
+        return Some(format!("Text \"{}\"", text.content));
+    }
+    match world.entity_types.get(&entity)? {
+        EntityType::Part => Some("Part".to_owned()),
+        EntityType::Special => Some("Mesh".to_owned()),
+        EntityType::Line(_) => Some("Line".to_owned()),
+    }
+}
+
+fn inspector_window(ui: &Ui, world: &mut World, selected: Option<usize>) {
+    ui.window("Inspector")
+        .position([240., 40.], Condition::FirstUseEver)
+        .size([300., 220.], Condition::FirstUseEver)
+        .build(|| {
+            let Some(entity) = selected else {
+                ui.text("Select an entity");
+                return;
+            };
+            ui.text(format!("Entity #{}", entity));
+            ui.separator();
+            if let Some(position) = world.positions.get_mut(&entity) {
+                Drag::new("Position")
+                    .speed(0.05)
+                    .build_array(ui, position.0.as_mut_slice());
+            }
+            if let Some(rotation) = world.rotations.get_mut(&entity) {
+                Drag::new("Rotation (rad)")
+                    .speed(0.01)
+                    .build_array(ui, rotation.0.as_mut_slice());
+            }
+            if let Some(scale) = world.scales.get_mut(&entity) {
+                Drag::new("Scale")
+                    .speed(0.05)
+                    .build_array(ui, scale.0.as_mut_slice());
+            }
+            if let Some(color) = world.colors.get_mut(&entity) {
+                let mut rgb = [color.0.x, color.0.y, color.0.z];
+                if ui.color_edit3("Color", &mut rgb) {
+                    color.0 = rgb.into();
+                }
+            }
+            if let Some(light) = world.lights.get_mut(&entity) {
+                let mut rgb = [light.color.x, light.color.y, light.color.z];
+                if ui.color_edit3("Light color", &mut rgb) {
+                    light.color = rgb.into();
+                }
+                Drag::new("Intensity")
+                    .speed(0.01)
+                    .range(0.0, 100.0)
+                    .build(ui, &mut light.intensity);
+            }
+            if let Some(emitter) = world.particle_emitters.get_mut(&entity) {
+                ui.checkbox("Emitting", &mut emitter.emitting);
+                Drag::new("Rate")
+                    .speed(0.5)
+                    .range(0.0, 1000.0)
+                    .build(ui, &mut emitter.rate);
+                Drag::new("Lifetime")
+                    .speed(0.05)
+                    .range(0.0, 60.0)
+                    .build(ui, &mut emitter.lifetime);
+                Drag::new("Speed").speed(0.05).build(ui, &mut emitter.speed);
+                Drag::new("Spread (rad)")
+                    .speed(0.01)
+                    .range(0.0, std::f32::consts::PI)
+                    .build(ui, &mut emitter.spread);
+                Drag::new("Gravity")
+                    .speed(0.05)
+                    .build_array(ui, emitter.gravity.as_mut_slice());
+                if ui.button("Burst") {
+                    let origin = world.positions.get(&entity).map(|p| p.0);
+                    emitter.burst(origin.unwrap_or_default(), PARTICLE_BURST);
+                }
+                ui.same_line();
+                ui.text(format!("{} particles", emitter.particle_count()));
+            }
+        });
+}
+
+fn camera_window(ui: &Ui, camera: &mut Camera3d) {
+    ui.window("Camera")
+        .position([10., 370.], Condition::FirstUseEver)
+        .size([300., 200.], Condition::FirstUseEver)
+        .build(|| {
+            Drag::new("Position")
+                .speed(0.05)
+                .build_array(ui, camera.position.as_mut_slice());
+            let mut turned = Drag::new("Yaw").speed(0.5).build(ui, &mut camera.yaw);
+            turned |= Drag::new("Pitch")
+                .speed(0.5)
+                .range(-89.0, 89.0)
+                .build(ui, &mut camera.pitch);
+            if turned {
+                camera.update_vectors();
+            }
+            Drag::new("FOV")
+                .speed(0.1)
+                .range(1.0, 120.0)
+                .build(ui, &mut camera.zoom);
+            Drag::new("Speed")
+                .speed(0.05)
+                .range(0.0, 100.0)
+                .build(ui, &mut camera.move_speed);
+            Drag::new("Near")
+                .speed(0.01)
+                .range(0.001, camera.far)
+                .build(ui, &mut camera.near);
+            Drag::new("Far")
+                .speed(1.0)
+                .range(camera.near, 10000.0)
+                .build(ui, &mut camera.far);
+        });
+}
+
+fn post_window(ui: &Ui, settings: &mut PostSettings) {
+    ui.window("Post")
+        .position([750., 470.], Condition::FirstUseEver)
+        .size([320., 240.], Condition::FirstUseEver)
+        .build(|| {
+            Drag::new("Exposure")
+                .speed(0.01)
+                .range(0.0, 10.0)
+                .build(ui, &mut settings.exposure);
+            ui.radio_button("Reinhard", &mut settings.tonemapper, Tonemapper::Reinhard);
+            ui.same_line();
+            ui.radio_button("ACES", &mut settings.tonemapper, Tonemapper::Aces);
+
+            let bloom = &mut settings.bloom;
+            ui.checkbox("Bloom", &mut bloom.enabled);
+            Drag::new("Threshold")
+                .speed(0.01)
+                .range(0.0, 10.0)
+                .build(ui, &mut bloom.threshold);
+            Drag::new("Bloom intensity")
+                .speed(0.01)
+                .range(0.0, 10.0)
+                .build(ui, &mut bloom.intensity);
+            Drag::new("Blur passes")
+                .range(0, 16)
+                .build(ui, &mut bloom.blur_passes);
+
+            let grading = &mut settings.color_grading;
+            ui.checkbox("Color grading", &mut grading.enabled);
+            Drag::new("Contrast")
+                .speed(0.01)
+                .range(0.0, 3.0)
+                .build(ui, &mut grading.contrast);
+            Drag::new("Saturation")
+                .speed(0.01)
+                .range(0.0, 3.0)
+                .build(ui, &mut grading.saturation);
+            let mut tint = [grading.tint.x, grading.tint.y, grading.tint.z];
+            if ui.color_edit3("Tint", &mut tint) {
+                grading.tint = tint.into();
+            }
+
+            let vignette = &mut settings.vignette;
+            ui.checkbox("Vignette", &mut vignette.enabled);
+            Drag::new("Strength")
+                .speed(0.01)
+                .range(0.0, 1.0)
+                .build(ui, &mut vignette.strength);
+            Drag::new("Radius")
+                .speed(0.01)
+                .range(0.0, 2.0)
+                .build(ui, &mut vignette.radius);
+            Drag::new("Softness")
+                .speed(0.01)
+                .range(0.0, 2.0)
+                .build(ui, &mut vignette.softness);
+
+            ui.checkbox("FXAA", &mut settings.fxaa);
+        });
+}
+
+fn frame_window(ui: &Ui, frame_times: &[f32], history_start: usize, stats: &mut RenderStats) {
+    ui.window("Frame")
+        .position([550., 40.], Condition::FirstUseEver)
+        .size([320., 420.], Condition::FirstUseEver)
+        .build(|| {
+            let average = frame_times.iter().sum::<f32>() / frame_times.len().max(1) as f32;
+            let worst = frame_times.iter().copied().fold(0.0, f32::max);
+            ui.text(format!(
+                "{:.2} ms avg ({:.0} fps), {:.2} ms worst",
+                average,
+                1000.0 / average.max(1e-3),
+                worst
+            ));
+            ui.plot_lines("##frame_times", frame_times)
+                .values_offset(history_start)
+                .scale_min(0.0)
+                .scale_max(worst.max(1000.0 / 30.0))
+                .graph_size([0., 80.])
+                .build();
+
+            let frame = &stats.frame;
+            ui.separator();
+            ui.text(format!(
+                "{} draw calls, {} triangles",
+                frame.draw_calls, frame.triangles
+            ));
+            ui.text(format!("{} state changes", frame.state_changes));
+            ui.text(format!("{} drawn, {} culled", frame.drawn, frame.culled));
+            ui.text(format!(
+                "Textures {:.1} MB, buffers {:.1} MB",
+                stats.texture_memory as f32 / 1_048_576.0,
+                stats.buffer_memory as f32 / 1_048_576.0
+            ));
+
+            ui.separator();
+            ui.text("GPU ms");
+            for (pass, time) in &stats.gpu_times {
+                ui.text(format!("  {:<14}{:>7.3}", pass, time));
+            }
+            ui.text("CPU ms");
+            for (system, time) in &stats.cpu_times {
+                ui.text(format!("  {:<14}{:>7.3}", system, time));
+            }
+
+            ui.separator();
+            if stats.capturing() {
+                ui.text("Capturing to CSV...");
+            } else if ui.button(format!("Capture {}s to CSV", STATS_CAPTURE_TIME))
+                && let Err(e) = stats.start_capture(STATS_CAPTURE_TIME)
+            {
+                warn!("{}", e);
+            }
+        });
+}
+
+fn console_window(ui: &Ui, world: &mut World, input: &mut String, log: &mut Vec<String>) {
+    ui.window("Console")
+        .position([320., 370.], Condition::FirstUseEver)
+        .size([420., 200.], Condition::FirstUseEver)
+        .build(|| {
+            ui.child_window("##console_log").size([0., -28.]).build(|| {
+                for line in log.iter() {
+                    ui.text(line);
+                }
+                // Follow new lines unless scrolled back up
+                if ui.scroll_y() >= ui.scroll_max_y() {
+                    ui.set_scroll_here_y_with_ratio(1.0);
+                }
+            });
+            if ui
+                .input_text("##console_input", input)
+                .hint("help")
+                .enter_returns_true(true)
+                .build()
+            {
+                let command = std::mem::take(input);
+                log.push(format!("> {}", command));
+                let output = run_command(command.trim(), world);
+                if !output.is_empty() {
+                    log.push(output);
+                }
+                if log.len() > CONSOLE_HISTORY {
+                    log.drain(..log.len() - CONSOLE_HISTORY);
+                }
+                // Enter takes focus away from the field, keep typing into it
+                ui.set_keyboard_focus_here_with_offset(FocusedWidget::Previous);
+            }
+        });
+}
+
+/// Runs a console command, returning what to print back
+fn run_command(command: &str, world: &mut World) -> String {
+    let mut words = command.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("view"), name) => {
+            let view = match name {
+                Some(name) => match DebugView::from_name(name) {
+                    Some(view) => view,
+                    None => return format!("Unknown view \"{}\"", name),
+                },
+                None => world.debug_view.next(),
+            };
+            world.debug_view = view;
+            debug!("Debug view: {}", view.name());
+            format!("Debug view: {}", view.name())
+        }
+        (Some("clear"), _) => {
+            world.debug_draw.clear();
+            "Cleared debug drawing".to_owned()
+        }
+        (Some("help"), _) => {
+            let views: Vec<_> = DebugView::ALL.iter().map(|view| view.name()).collect();
+            format!(
+                "view [{}]  switch debug view, or cycle with no name (F4)\n\
+                 clear  drop pick markers and other debug drawing",
+                views.join("|")
+            )
+        }
+        (Some(other), _) => format!("Unknown command \"{}\", try help", other),
+        (None, _) => String::new(),
+    }
+}
+
+/// The keys ImGui needs for navigating and editing text fields
+fn imgui_key(key: glfw::Key) -> Option<Key> {
+    use glfw::Key as G;
+    Some(match key {
+        G::Tab => Key::Tab,
+        G::Left => Key::LeftArrow,
+        G::Right => Key::RightArrow,
+        G::Up => Key::UpArrow,
+        G::Down => Key::DownArrow,
+        G::PageUp => Key::PageUp,
+        G::PageDown => Key::PageDown,
+        G::Home => Key::Home,
+        G::End => Key::End,
+        G::Insert => Key::Insert,
+        G::Delete => Key::Delete,
+        G::Backspace => Key::Backspace,
+        G::Space => Key::Space,
+        G::Enter => Key::Enter,
+        G::KpEnter => Key::KeypadEnter,
+        G::Escape => Key::Escape,
+        G::LeftControl => Key::LeftCtrl,
+        G::RightControl => Key::RightCtrl,
+        G::LeftShift => Key::LeftShift,
+        G::RightShift => Key::RightShift,
+        G::LeftAlt => Key::LeftAlt,
+        G::RightAlt => Key::RightAlt,
+        G::LeftSuper => Key::LeftSuper,
+        G::RightSuper => Key::RightSuper,
+        G::A => Key::A,
+        G::C => Key::C,
+        G::V => Key::V,
+        G::X => Key::X,
+        G::Y => Key::Y,
+        G::Z => Key::Z,
+        _ => return None,
+    })
+}
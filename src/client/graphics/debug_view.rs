@@ -0,0 +1,184 @@
+use nalgebra_glm as glm;
+
+use crate::ecs::ecs::{EntityType, World};
+use crate::ecs::render::{self, FrameStats};
+use crate::graphics::camera::Camera3d;
+use crate::graphics::material::{DIFFUSE_TEXTURE_UNIT, Material};
+use crate::graphics::shader::ShaderCache;
+
+pub const DEBUG_VIEW_SHADER: (&str, &str) = (
+    "assets/shaders/debug_view.vert",
+    "assets/shaders/debug_view.frag",
+);
+
+/// Length of the lines the normals view draws, in world units
+const NORMAL_LINE_LENGTH: f32 = 0.15;
+const NORMAL_LINE_COLOR: glm::Vec3 = glm::Vec3::new(1.0, 0.85, 0.2);
+
+/// How the world is drawn. Everything but `Lit` replaces every entity's shader with the debug
+/// view shader and skips the sky and post-processing, so what's on screen is the geometry.
+/// Values match `VIEW_*` in `debug_view.frag` and `post/composite.frag`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebugView {
+    #[default]
+    Lit,
+    /// Triangle edges in each entity's flat color
+    Wireframe,
+    /// World-space normals as colors, with each vertex's normal drawn as a line
+    Normals,
+    /// A checkerboard over the mesh's UVs
+    UvChecker,
+    /// Color, material and texture without any lighting
+    Unlit,
+    /// Distance from the camera, white up close
+    Depth,
+    /// How many times each pixel was drawn over, depth testing off
+    Overdraw,
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 7] = [
+        DebugView::Lit,
+        DebugView::Wireframe,
+        DebugView::Normals,
+        DebugView::UvChecker,
+        DebugView::Unlit,
+        DebugView::Depth,
+        DebugView::Overdraw,
+    ];
+
+    /// The view after this one, wrapping back around to `Lit`
+    pub fn next(self) -> Self {
+        let index = DebugView::ALL
+            .iter()
+            .position(|&view| view == self)
+            .unwrap();
+        DebugView::ALL[(index + 1) % DebugView::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::Lit => "lit",
+            DebugView::Wireframe => "wireframe",
+            DebugView::Normals => "normals",
+            DebugView::UvChecker => "uv",
+            DebugView::Unlit => "unlit",
+            DebugView::Depth => "depth",
+            DebugView::Overdraw => "overdraw",
+        }
+    }
+
+    /// The view called `name`, as in `DebugView::name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        DebugView::ALL
+            .into_iter()
+            .find(|view| view.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn id(self) -> i32 {
+        self as i32
+    }
+}
+
+/// Draws what `render::render_world` would, through the debug view shader instead of each
+/// entity's own. Lines keep their shader, except in the overdraw view where they're skipped
+pub fn render_debug_view(
+    world: &World,
+    camera: &Camera3d,
+    shader_cache: &mut ShaderCache,
+) -> Result<FrameStats, Box<dyn std::error::Error>> {
+    let view = world.debug_view;
+    let (opaque, translucent, stats) = render::visible_entities(world, camera);
+    let shader = shader_cache.get(DEBUG_VIEW_SHADER.0, DEBUG_VIEW_SHADER.1, &[])?;
+    shader.use_program();
+    shader.set_int("uDebugView", view.id())?;
+    shader.set_float("uNear", camera.near)?;
+    shader.set_float("uFar", camera.far)?;
+
+    unsafe {
+        match view {
+            DebugView::Wireframe => gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE),
+            DebugView::Overdraw => {
+                gl::Disable(gl::DEPTH_TEST);
+                gl::DepthMask(gl::FALSE);
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::ONE, gl::ONE);
+            }
+            _ => {}
+        }
+    }
+
+    let mut lines = Vec::new();
+    for entity in opaque.into_iter().chain(translucent) {
+        if matches!(world.entity_types.get(&entity), Some(EntityType::Line(_))) {
+            lines.push(entity);
+            continue;
+        }
+        let render_data = world.part_render_data.get(&entity).unwrap();
+        shader.set_mat4("model", &render::model_matrix(world, entity))?;
+        shader.set_vec3(
+            "uColor",
+            &world
+                .colors
+                .get(&entity)
+                .map_or(glm::vec3(1., 1., 1.), |c| c.0),
+        )?;
+        match world.materials.get(&entity) {
+            Some(material) => material.apply(&shader)?,
+            None => Material::default().apply(&shader)?,
+        }
+        shader.set_bool("uTextured", world.textures.contains_key(&entity))?;
+        if let Some(texture) = world.textures.get(&entity) {
+            texture.bind(DIFFUSE_TEXTURE_UNIT);
+        }
+        render::draw_elements(render_data.vao_id, render_data.index_count);
+    }
+
+    unsafe {
+        match view {
+            DebugView::Wireframe => gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL),
+            DebugView::Overdraw => {
+                gl::Disable(gl::BLEND);
+                gl::DepthMask(gl::TRUE);
+                gl::Enable(gl::DEPTH_TEST);
+            }
+            _ => {}
+        }
+    }
+
+    if view != DebugView::Overdraw {
+        for entity in lines {
+            render::draw_entity(world, entity);
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Queues a line along every vertex normal of the entities the camera can see, for the
+/// normals view. Entities without `MeshNormals` are skipped
+pub fn draw_normals(world: &mut World, camera: &Camera3d) {
+    let frustum = camera.frustum();
+    let mut lines = Vec::new();
+    for (&entity, normals) in &world.mesh_normals {
+        if !render::in_frustum(world, entity, &frustum) {
+            continue;
+        }
+        let model = render::model_matrix(world, entity);
+        let Some(inverse) = glm::mat4_to_mat3(&model).try_inverse() else {
+            continue;
+        };
+        let normal_matrix = inverse.transpose();
+        for (position, normal) in normals.0.iter() {
+            let start = (model * glm::vec4(position.x, position.y, position.z, 1.)).xyz();
+            // A zero normal in the mesh data points nowhere
+            let Some(direction) = (normal_matrix * normal).try_normalize(1e-6) else {
+                continue;
+            };
+            lines.push((start, start + direction * NORMAL_LINE_LENGTH));
+        }
+    }
+    for (start, end) in lines {
+        world.debug_draw.line(start, end, NORMAL_LINE_COLOR, 0.);
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use gl::types::{GLint, GLuint};
+use log::{debug, warn};
+use nalgebra_glm as glm;
+
+use crate::graphics::render_stats;
+
+pub const DEFAULT_FONT: &str = "assets/fonts/DejaVuSans.ttf";
+
+/// Glyphs are rasterized at this size, in pixels per em. Layout is done in these units too,
+/// and scaled to whatever size the text is drawn at
+pub const FONT_BASE_SIZE: f32 = 48.0;
+/// How far the distance field reaches past a glyph's edge, in pixels at `FONT_BASE_SIZE`
+const SDF_SPREAD: usize = 6;
+const ATLAS_SIZE: i32 = 1024;
+
+/// Characters rasterized up front; anything else is added to the atlas on first use
+const PRELOADED_CHARS: std::ops::RangeInclusive<char> = ' '..='~';
+
+/// Where a glyph sits in the atlas, and how to place it. Lengths are in pixels at
+/// `FONT_BASE_SIZE`, y up from the baseline
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph {
+    pub uv_min: glm::Vec2,
+    pub uv_max: glm::Vec2,
+    /// Bottom left of the quad relative to the pen position, padding included
+    pub offset: glm::Vec2,
+    pub size: glm::Vec2,
+    pub advance: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    #[allow(dead_code)]
+    Right,
+}
+
+/// A glyph placed by `Font::layout`, in layout units: pixels at `FONT_BASE_SIZE`, with the
+/// top of the first line at y 0 and y going up
+#[derive(Debug, Clone, Copy)]
+pub struct GlyphQuad {
+    pub min: glm::Vec2,
+    pub max: glm::Vec2,
+    pub uv_min: glm::Vec2,
+    pub uv_max: glm::Vec2,
+}
+
+pub struct TextLayout {
+    pub quads: Vec<GlyphQuad>,
+    pub height: f32,
+}
+
+/// A TTF font with a signed distance field atlas of its glyphs, so one atlas renders
+/// sharp at any size
+pub struct Font {
+    font: fontdue::Font,
+    glyphs: HashMap<char, Option<Glyph>>,
+    /// Advances of the characters `glyphs` has no `Glyph` for, like spaces
+    blank_advances: HashMap<char, f32>,
+    pub atlas: GLuint,
+    pub line_height: f32,
+    pub ascent: f32,
+    /// Shelf packing state: where the next glyph goes and how tall the current row is
+    cursor: (i32, i32),
+    row_height: i32,
+}
+
+impl Font {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("Failed to read font {}: {}", path, e))?;
+        let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+            .map_err(|e| format!("Failed to parse font {}: {}", path, e))?;
+        let line_metrics = font
+            .horizontal_line_metrics(FONT_BASE_SIZE)
+            .ok_or_else(|| format!("Font {} has no horizontal metrics", path))?;
+
+        let mut atlas = 0;
+        unsafe {
+            gl::GenTextures(1, &mut atlas);
+            gl::BindTexture(gl::TEXTURE_2D, atlas);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::R8 as GLint,
+                ATLAS_SIZE,
+                ATLAS_SIZE,
+                0,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+            let params = [
+                (gl::TEXTURE_MIN_FILTER, gl::LINEAR),
+                (gl::TEXTURE_MAG_FILTER, gl::LINEAR),
+                (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE),
+            ];
+            for (name, value) in params {
+                gl::TexParameteri(gl::TEXTURE_2D, name, value as GLint);
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        let mut font = Font {
+            font,
+            glyphs: HashMap::new(),
+            blank_advances: HashMap::new(),
+            atlas,
+            line_height: line_metrics.new_line_size,
+            ascent: line_metrics.ascent,
+            cursor: (0, 0),
+            row_height: 0,
+        };
+        for c in PRELOADED_CHARS {
+            font.glyph(c);
+        }
+        render_stats::track_texture_memory((ATLAS_SIZE * ATLAS_SIZE) as isize);
+        debug!("Loaded font {} into atlas #{}", path, atlas);
+        Ok(font)
+    }
+
+    /// The glyph for `c`, rasterizing it into the atlas the first time. `None` for glyphs
+    /// with nothing to draw, like spaces, or when the atlas is full
+    pub fn glyph(&mut self, c: char) -> Option<Glyph> {
+        if let Some(glyph) = self.glyphs.get(&c) {
+            return *glyph;
+        }
+        let glyph = self.rasterize(c);
+        self.glyphs.insert(c, glyph);
+        glyph
+    }
+
+    pub fn advance(&mut self, c: char) -> f32 {
+        match self.glyph(c) {
+            Some(glyph) => glyph.advance,
+            None => self.blank_advances[&c],
+        }
+    }
+
+    pub fn kerning(&self, left: char, right: char) -> f32 {
+        self.font
+            .horizontal_kern(left, right, FONT_BASE_SIZE)
+            .unwrap_or(0.0)
+    }
+
+    /// Width of a single line of text, kerning included
+    pub fn measure(&mut self, line: &str) -> f32 {
+        let mut width = 0.0;
+        let mut previous = None;
+        for c in line.chars() {
+            if let Some(previous) = previous {
+                width += self.kerning(previous, c);
+            }
+            width += self.advance(c);
+            previous = Some(c);
+        }
+        width
+    }
+
+    /// Lays `text` out into quads. Lines break at newlines and, when `max_width` is set, between
+    /// words that would run past it. Words longer than `max_width` get a line to themselves.
+    /// Each line is aligned around x 0: starting there, centered on it or ending there
+    pub fn layout(&mut self, text: &str, align: TextAlign, max_width: Option<f32>) -> TextLayout {
+        let lines = self.wrap(text, max_width);
+
+        let mut quads = Vec::new();
+        for (row, line) in lines.iter().enumerate() {
+            let line_width = self.measure(line);
+            let mut pen = match align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => -line_width * 0.5,
+                TextAlign::Right => -line_width,
+            };
+            let baseline = -self.ascent - row as f32 * self.line_height;
+
+            let mut previous = None;
+            for c in line.chars() {
+                if let Some(previous) = previous {
+                    pen += self.kerning(previous, c);
+                }
+                if let Some(glyph) = self.glyph(c) {
+                    let min = glm::vec2(pen, baseline) + glyph.offset;
+                    quads.push(GlyphQuad {
+                        min,
+                        max: min + glyph.size,
+                        uv_min: glyph.uv_min,
+                        uv_max: glyph.uv_max,
+                    });
+                }
+                pen += self.advance(c);
+                previous = Some(c);
+            }
+        }
+
+        TextLayout {
+            quads,
+            height: lines.len() as f32 * self.line_height,
+        }
+    }
+
+    fn wrap(&mut self, text: &str, max_width: Option<f32>) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let Some(max_width) = max_width else {
+                lines.push(paragraph.to_owned());
+                continue;
+            };
+
+            let mut line = String::new();
+            for word in paragraph.split(' ') {
+                let candidate = if line.is_empty() {
+                    word.to_owned()
+                } else {
+                    format!("{} {}", line, word)
+                };
+                if !line.is_empty() && self.measure(&candidate) > max_width {
+                    lines.push(std::mem::replace(&mut line, word.to_owned()));
+                } else {
+                    line = candidate;
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn rasterize(&mut self, c: char) -> Option<Glyph> {
+        let (metrics, coverage) = self.font.rasterize(c, FONT_BASE_SIZE);
+        let width = metrics.width + SDF_SPREAD * 2;
+        let height = metrics.height + SDF_SPREAD * 2;
+        let position = if metrics.width == 0 || metrics.height == 0 {
+            None
+        } else {
+            self.allocate(width as i32, height as i32)
+        };
+        let Some((x, y)) = position else {
+            self.blank_advances.insert(c, metrics.advance_width);
+            return None;
+        };
+        let field = distance_field(&coverage, metrics.width, metrics.height);
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.atlas);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                x,
+                y,
+                width as i32,
+                height as i32,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                field.as_ptr() as *const _,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        // Bitmap rows go top to bottom, so the top of the glyph is at the lower v
+        let atlas_size = ATLAS_SIZE as f32;
+        Some(Glyph {
+            uv_min: glm::vec2(x as f32, y as f32) / atlas_size,
+            uv_max: glm::vec2((x as usize + width) as f32, (y as usize + height) as f32)
+                / atlas_size,
+            offset: glm::vec2(
+                metrics.xmin as f32 - SDF_SPREAD as f32,
+                metrics.ymin as f32 - SDF_SPREAD as f32,
+            ),
+            size: glm::vec2(width as f32, height as f32),
+            advance: metrics.advance_width,
+        })
+    }
+
+    /// Finds room in the atlas, filling it in rows
+    fn allocate(&mut self, width: i32, height: i32) -> Option<(i32, i32)> {
+        if self.cursor.0 + width > ATLAS_SIZE {
+            self.cursor = (0, self.cursor.1 + self.row_height);
+            self.row_height = 0;
+        }
+        if self.cursor.1 + height > ATLAS_SIZE {
+            warn!("Font atlas is full, glyphs will be missing");
+            return None;
+        }
+        let position = self.cursor;
+        self.cursor.0 += width;
+        self.row_height = self.row_height.max(height);
+        Some(position)
+    }
+}
+
+/// Turns a coverage bitmap into a distance field padded by `SDF_SPREAD` on every side.
+/// 0.5 (127) is the glyph's edge, rising inside it and falling outside
+fn distance_field(coverage: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let inside = |x: isize, y: isize| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < width
+            && (y as usize) < height
+            && coverage[y as usize * width + x as usize] >= 128
+    };
+
+    let spread = SDF_SPREAD as isize;
+    let (out_width, out_height) = (width + SDF_SPREAD * 2, height + SDF_SPREAD * 2);
+    let mut field = vec![0u8; out_width * out_height];
+    for oy in 0..out_height as isize {
+        for ox in 0..out_width as isize {
+            let (x, y) = (ox - spread, oy - spread);
+            let is_inside = inside(x, y);
+
+            // Nearest pixel on the other side of the edge
+            let mut nearest = (spread * spread * 2) as f32;
+            for dy in -spread..=spread {
+                for dx in -spread..=spread {
+                    if inside(x + dx, y + dy) != is_inside {
+                        nearest = nearest.min((dx * dx + dy * dy) as f32);
+                    }
+                }
+            }
+            // Pixel centers are half a pixel from the edge between them
+            let distance = (nearest.sqrt() - 0.5).min(spread as f32);
+            let signed = if is_inside { distance } else { -distance };
+            let value = 0.5 + signed / (spread as f32 * 2.0);
+            field[oy as usize * out_width + ox as usize] = (value.clamp(0.0, 1.0) * 255.0) as u8;
+        }
+    }
+    field
+}
@@ -0,0 +1,242 @@
+use nalgebra_glm as glm;
+
+/// Axis-aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: glm::Vec3,
+    pub max: glm::Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: glm::Vec3, max: glm::Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    /// Bounds of the positions in an interleaved vertex buffer, where each vertex is `stride`
+    /// floats starting with x, y, z. An empty buffer gives a box around the origin
+    pub fn from_vertices(vertices: &[f32], stride: usize) -> Self {
+        let mut chunks = vertices.chunks_exact(stride);
+        let Some(first) = chunks.next() else {
+            return Aabb::new(glm::Vec3::zeros(), glm::Vec3::zeros());
+        };
+        let first = glm::vec3(first[0], first[1], first[2]);
+        chunks.fold(Aabb::new(first, first), |bounds, v| {
+            let p = glm::vec3(v[0], v[1], v[2]);
+            Aabb::new(glm::min2(&bounds.min, &p), glm::max2(&bounds.max, &p))
+        })
+    }
+
+    pub fn center(&self) -> glm::Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn extents(&self) -> glm::Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// The box around this one after `transform`, still axis aligned so it grows with rotation
+    pub fn transformed(&self, transform: &glm::Mat4) -> Self {
+        let center = transform * glm::vec4(self.center().x, self.center().y, self.center().z, 1.);
+        let linear = transform.fixed_view::<3, 3>(0, 0).abs();
+        let extents = linear * self.extents();
+        let center = center.xyz();
+        Aabb::new(center - extents, center + extents)
+    }
+
+    /// Where `ray` first meets the box, as the distance along it and the outward normal of
+    /// the face it crosses. A ray starting inside meets the face it leaves through
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<(f32, glm::Vec3)> {
+        let (mut near, mut far) = (f32::NEG_INFINITY, f32::INFINITY);
+        let (mut near_normal, mut far_normal) = (glm::Vec3::zeros(), glm::Vec3::zeros());
+        for axis in 0..3 {
+            let (origin, direction) = (ray.origin[axis], ray.direction[axis]);
+            if direction.abs() < 1e-8 {
+                // Parallel to this pair of faces, so it has to start between them
+                if origin < self.min[axis] || origin > self.max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let mut normal = glm::Vec3::zeros();
+            normal[axis] = -direction.signum();
+            let t1 = (self.min[axis] - origin) / direction;
+            let t2 = (self.max[axis] - origin) / direction;
+            let (enter, exit) = (t1.min(t2), t1.max(t2));
+            if enter > near {
+                near = enter;
+                near_normal = normal;
+            }
+            if exit < far {
+                far = exit;
+                far_normal = -normal;
+            }
+        }
+        if near > far || far < 0.0 {
+            None
+        } else if near >= 0.0 {
+            Some((near, near_normal))
+        } else {
+            Some((far, far_normal))
+        }
+    }
+}
+
+/// A half line from `origin` along `direction`, in whatever space both are given in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: glm::Vec3,
+    pub direction: glm::Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: glm::Vec3, direction: glm::Vec3) -> Self {
+        Ray { origin, direction }
+    }
+
+    pub fn at(&self, distance: f32) -> glm::Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// The same ray in the space `transform` maps to. `direction` isn't renormalized, so
+    /// distances along the new ray are the same as along this one
+    pub fn transformed(&self, transform: &glm::Mat4) -> Self {
+        let origin = transform * glm::vec4(self.origin.x, self.origin.y, self.origin.z, 1.);
+        let direction = transform.fixed_view::<3, 3>(0, 0) * self.direction;
+        Ray::new(origin.xyz(), direction)
+    }
+}
+
+/// The six planes bounding what a camera sees, as (normal, distance) with normals pointing
+/// inwards
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    pub planes: [glm::Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a projection * view matrix
+    pub fn from_matrix(m: &glm::Mat4) -> Self {
+        let row = |i: usize| m.row(i).transpose();
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+        let planes =
+            [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r3 + r2, r3 - r2].map(|p| p / p.xyz().magnitude());
+        Frustum { planes }
+    }
+
+    /// Whether any part of `aabb` might be visible. Boxes crossing a frustum corner can pass
+    /// without being visible, which only costs a draw call
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let (center, extents) = (aabb.center(), aabb.extents());
+        self.planes.iter().all(|plane| {
+            let normal = plane.xyz();
+            let radius = extents.dot(&normal.abs());
+            normal.dot(&center) + plane.w >= -radius
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(glm::vec3(-1., -1., -1.), glm::vec3(1., 1., 1.))
+    }
+
+    /// A camera at the origin looking down -z with a 90 degree field of view, seeing from 0.1
+    /// to 100 units away
+    fn camera_frustum() -> Frustum {
+        let projection = glm::perspective(1., std::f32::consts::FRAC_PI_2, 0.1, 100.);
+        let view = glm::look_at(
+            &glm::Vec3::zeros(),
+            &glm::vec3(0., 0., -1.),
+            &glm::Vec3::y(),
+        );
+        Frustum::from_matrix(&(projection * view))
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn ray_from_outside_hits_the_near_face() {
+        let ray = Ray::new(glm::vec3(-5., 0.5, 0.), glm::vec3(1., 0., 0.));
+        let (distance, normal) = unit_box().intersect_ray(&ray).unwrap();
+        assert_near(distance, 4.);
+        assert_eq!(normal, glm::vec3(-1., 0., 0.));
+    }
+
+    #[test]
+    fn ray_from_inside_hits_the_face_it_leaves_through() {
+        let ray = Ray::new(glm::vec3(0., 0., 0.5), glm::vec3(0., 0., 1.));
+        let (distance, normal) = unit_box().intersect_ray(&ray).unwrap();
+        assert_near(distance, 0.5);
+        assert_eq!(normal, glm::vec3(0., 0., 1.));
+    }
+
+    #[test]
+    fn rays_that_miss() {
+        let aabb = unit_box();
+        // Passes beside the box
+        let beside = Ray::new(glm::vec3(-5., 2., 0.), glm::vec3(1., 0.1, 0.).normalize());
+        assert_eq!(aabb.intersect_ray(&beside), None);
+        // Points away from it
+        let away = Ray::new(glm::vec3(-5., 0., 0.), glm::vec3(-1., 0., 0.));
+        assert_eq!(aabb.intersect_ray(&away), None);
+    }
+
+    #[test]
+    fn ray_parallel_to_a_face() {
+        let aabb = unit_box();
+        // Between the y faces it hits the x face, outside them it can't hit anything
+        let between = Ray::new(glm::vec3(-5., 0.5, 0.5), glm::vec3(1., 0., 0.));
+        assert_near(aabb.intersect_ray(&between).unwrap().0, 4.);
+        let outside = Ray::new(glm::vec3(-5., 1.5, 0.), glm::vec3(1., 0., 0.));
+        assert_eq!(aabb.intersect_ray(&outside), None);
+    }
+
+    #[test]
+    fn transformed_box_covers_the_transformed_corners() {
+        let transform = glm::translate(&glm::Mat4::identity(), &glm::vec3(10., 0., 0.))
+            * glm::rotate_y(&glm::Mat4::identity(), std::f32::consts::FRAC_PI_4)
+            * glm::scale(&glm::Mat4::identity(), &glm::vec3(2., 1., 1.));
+        let bounds = unit_box().transformed(&transform);
+
+        let half_diagonal = 3. * std::f32::consts::FRAC_1_SQRT_2;
+        assert_near(bounds.min.x, 10. - half_diagonal);
+        assert_near(bounds.max.x, 10. + half_diagonal);
+        assert_near(bounds.min.y, -1.);
+        assert_near(bounds.max.y, 1.);
+        assert_near(bounds.min.z, -half_diagonal);
+        assert_near(bounds.max.z, half_diagonal);
+    }
+
+    #[test]
+    fn frustum_keeps_boxes_in_view() {
+        let frustum = camera_frustum();
+        let ahead = Aabb::new(glm::vec3(-1., -1., -11.), glm::vec3(1., 1., -9.));
+        assert!(frustum.intersects_aabb(&ahead));
+    }
+
+    #[test]
+    fn frustum_culls_boxes_out_of_view() {
+        let frustum = camera_frustum();
+        let behind = Aabb::new(glm::vec3(-1., -1., 9.), glm::vec3(1., 1., 11.));
+        let beside = Aabb::new(glm::vec3(20., -1., -11.), glm::vec3(22., 1., -9.));
+        let too_far = Aabb::new(glm::vec3(-1., -1., -200.), glm::vec3(1., 1., -150.));
+        assert!(!frustum.intersects_aabb(&behind));
+        assert!(!frustum.intersects_aabb(&beside));
+        assert!(!frustum.intersects_aabb(&too_far));
+    }
+
+    #[test]
+    fn frustum_keeps_boxes_straddling_a_plane() {
+        let frustum = camera_frustum();
+        // The right plane passes through x = 10 at z = -10
+        let right_edge = Aabb::new(glm::vec3(9., -1., -11.), glm::vec3(12., 1., -9.));
+        let far_plane = Aabb::new(glm::vec3(-1., -1., -101.), glm::vec3(1., 1., -99.));
+        assert!(frustum.intersects_aabb(&right_edge));
+        assert!(frustum.intersects_aabb(&far_plane));
+    }
+}
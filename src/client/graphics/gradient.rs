@@ -0,0 +1,111 @@
+use nalgebra_glm as glm;
+
+use crate::graphics::shader::Shader;
+
+/// Must match `MAX_GRADIENT_STOPS` in `common/gradient.glsl`
+pub const MAX_GRADIENT_STOPS: usize = 8;
+
+/// Which local-space direction a gradient runs along. Values match `GRADIENT_AXIS_*`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientAxis {
+    X = 0,
+    Y = 1,
+    Z = 2,
+    /// Outwards from the part's center
+    Radial = 3,
+}
+
+/// How colors are blended between stops. Values match `GRADIENT_LINEAR` and friends
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientInterpolation {
+    Linear = 0,
+    /// Eases in and out of each stop
+    Smooth = 1,
+    /// Hard bands, each stop's color holds until the next stop
+    Step = 2,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GradientStop {
+    /// Where the stop sits along the axis, from 0 to 1
+    pub offset: f32,
+    pub color: glm::Vec3,
+}
+
+/// Colors a part by position instead of with a single solid color.
+/// Evaluated per fragment, so any number of stops costs no extra vertices
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    stops: Vec<GradientStop>,
+    pub axis: GradientAxis,
+    pub interpolation: GradientInterpolation,
+}
+
+impl Gradient {
+    /// Stops are sorted by offset. Needs between 2 and `MAX_GRADIENT_STOPS` stops
+    pub fn new(
+        mut stops: Vec<GradientStop>,
+        axis: GradientAxis,
+        interpolation: GradientInterpolation,
+    ) -> Result<Self, String> {
+        if stops.len() < 2 || stops.len() > MAX_GRADIENT_STOPS {
+            return Err(format!(
+                "A gradient needs between 2 and {} stops, got {}",
+                MAX_GRADIENT_STOPS,
+                stops.len()
+            ));
+        }
+        for stop in &mut stops {
+            stop.offset = stop.offset.clamp(0.0, 1.0);
+        }
+        stops.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+
+        Ok(Gradient {
+            stops,
+            axis,
+            interpolation,
+        })
+    }
+
+    /// Evenly spaced stops from the first color to the last
+    pub fn even(
+        colors: &[glm::Vec3],
+        axis: GradientAxis,
+        interpolation: GradientInterpolation,
+    ) -> Result<Self, String> {
+        let last = colors.len().saturating_sub(1).max(1) as f32;
+        let stops = colors
+            .iter()
+            .enumerate()
+            .map(|(i, color)| GradientStop {
+                offset: i as f32 / last,
+                color: *color,
+            })
+            .collect();
+        Gradient::new(stops, axis, interpolation)
+    }
+
+    #[allow(dead_code)]
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops
+    }
+
+    /// Uploads the gradient to the `uGradient*` uniforms
+    pub fn apply(&self, shader: &Shader) -> Result<(), String> {
+        let offsets: Vec<f32> = self.stops.iter().map(|s| s.offset).collect();
+        let colors: Vec<glm::Vec3> = self.stops.iter().map(|s| s.color).collect();
+
+        shader.set_int("uGradientStopCount", self.stops.len() as i32)?;
+        shader.set_int("uGradientAxis", self.axis as i32)?;
+        shader.set_int("uGradientInterpolation", self.interpolation as i32)?;
+        shader.set_float_array("uGradientOffsets", &offsets)?;
+        shader.set_vec3_array("uGradientColors", &colors)
+    }
+
+    /// Turns gradients off for the next draw with `shader`
+    pub fn clear(shader: &Shader) -> Result<(), String> {
+        shader.set_int("uGradientStopCount", 0)
+    }
+}
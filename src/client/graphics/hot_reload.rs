@@ -0,0 +1,97 @@
+use std::fs;
+use std::time::SystemTime;
+
+use gl::types::GLuint;
+use log::{debug, error};
+
+use crate::graphics::shader::{Shader, ShaderCache};
+
+struct WatchedShader {
+    shader: Shader,
+    modified: Vec<Option<SystemTime>>,
+}
+
+/// Polls the source files of every watched shader and rebuilds the ones that changed.
+/// Everything in the shader cache is watched, including programs compiled after startup
+pub struct ShaderWatcher {
+    watched: Vec<WatchedShader>,
+}
+
+/// Modification times of both stages and everything they include
+fn modified_times(shader: &Shader) -> Vec<Option<SystemTime>> {
+    match &shader.sources {
+        Some(sources) => sources
+            .files
+            .iter()
+            .map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+impl ShaderWatcher {
+    pub fn new() -> Self {
+        ShaderWatcher {
+            watched: Vec::new(),
+        }
+    }
+
+    /// Starts watching a shader. Shaders that weren't loaded with `Shader::from_files` are ignored
+    pub fn watch(&mut self, shader: &Shader) {
+        if shader.sources.is_none() {
+            return;
+        }
+        if self
+            .watched
+            .iter()
+            .any(|w| w.shader.program == shader.program)
+        {
+            return;
+        }
+        debug!("Watching program #{} for changes", shader.program);
+        self.watched.push(WatchedShader {
+            shader: shader.clone(),
+            modified: modified_times(shader),
+        });
+    }
+
+    /// Starts watching anything new in `shader_cache`, then rebuilds every watched shader whose
+    /// files changed since the last poll. Returns `(old program, new shader)` for each
+    /// successful rebuild; the caller is expected to swap the new shader in everywhere the old
+    /// program was used and then delete it. Failed rebuilds are logged and the old program is
+    /// kept.
+    pub fn poll(&mut self, shader_cache: &ShaderCache) -> Vec<(GLuint, Shader)> {
+        for shader in shader_cache.shaders() {
+            self.watch(shader);
+        }
+
+        let mut reloaded = Vec::new();
+
+        for watched in &mut self.watched {
+            let modified = modified_times(&watched.shader);
+            if modified == watched.modified {
+                continue;
+            }
+            watched.modified = modified;
+
+            match watched.shader.rebuild() {
+                Ok(shader) => {
+                    debug!(
+                        "Reloaded program #{} as #{}",
+                        watched.shader.program, shader.program
+                    );
+                    reloaded.push((watched.shader.program, shader.clone()));
+                    // The new source may include a different set of files
+                    watched.modified = modified_times(&shader);
+                    watched.shader = shader;
+                }
+                Err(e) => error!(
+                    "Failed to reload program #{}, keeping the old one: {}",
+                    watched.shader.program, e
+                ),
+            }
+        }
+
+        reloaded
+    }
+}
@@ -0,0 +1,194 @@
+use gl::types::{GLint, GLuint};
+use log::{debug, error};
+
+use crate::ecs::ecs::{self as ECS, World};
+use crate::ecs::render;
+use crate::graphics::camera::Camera3d;
+use crate::graphics::render_stats;
+use crate::graphics::shader::ShaderCache;
+
+pub const ENTITY_ID_SHADER: (&str, &str) = (
+    "assets/shaders/entity_id.vert",
+    "assets/shaders/entity_id.frag",
+);
+
+/// Entity ids rasterized into an integer texture, for picking in scenes too dense to raycast
+/// against. Only drawn when asked to pick, and then only the pixel being picked
+pub struct IdBuffer {
+    fbo: GLuint,
+    ids: GLuint,
+    depth: GLuint,
+    width: i32,
+    height: i32,
+}
+
+impl IdBuffer {
+    pub fn new(width: i32, height: i32) -> Self {
+        let (fbo, ids, depth) = create_target(width, height);
+        debug!("Created entity id buffer #{} at {}x{}", fbo, width, height);
+        IdBuffer {
+            fbo,
+            ids,
+            depth,
+            width,
+            height,
+        }
+    }
+
+    /// Recreates the buffer at the new framebuffer size
+    pub fn resize(&mut self, width: i32, height: i32) {
+        if width <= 0 || height <= 0 || (width, height) == (self.width, self.height) {
+            return;
+        }
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(1, &self.ids);
+            gl::DeleteRenderbuffers(1, &self.depth);
+        }
+        render_stats::track_texture_memory(-target_bytes(self.width, self.height));
+        (self.fbo, self.ids, self.depth) = create_target(width, height);
+        (self.width, self.height) = (width, height);
+    }
+
+    /// The entity drawn at `pixel`, in framebuffer pixels from the top left, with its depth
+    /// buffer value there. Draws the opaque parts `camera` sees, clipped to that one pixel,
+    /// then restores the viewport and default framebuffer. Doesn't depend on `CameraBlock`, so
+    /// it can run before the frame's uniforms are uploaded
+    pub fn pick(
+        &self,
+        world: &World,
+        camera: &Camera3d,
+        shader_cache: &mut ShaderCache,
+        pixel: (i32, i32),
+    ) -> Result<Option<(usize, f32)>, Box<dyn std::error::Error>> {
+        let (x, y) = (pixel.0, self.height - 1 - pixel.1);
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return Ok(None);
+        }
+
+        let shader = shader_cache.get(ENTITY_ID_SHADER.0, ENTITY_ID_SHADER.1, &[])?;
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::Viewport(0, 0, self.width, self.height);
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::Scissor(x, y, 1, 1);
+            gl::ClearBufferuiv(gl::COLOR, 0, [0u32; 4].as_ptr());
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+
+        shader.use_program();
+        shader.set_mat4(
+            "uViewProjection",
+            &(camera.get_projection_matrix() * camera.get_view_matrix()),
+        )?;
+        let frustum = camera.frustum();
+        for (&entity, render_data) in &world.part_render_data {
+            if !matches!(world.entity_types.get(&entity), Some(ECS::EntityType::Part))
+                || render::opacity(world, entity) < 1.0
+                || !render::in_frustum(world, entity, &frustum)
+            {
+                continue;
+            }
+            shader.set_mat4("model", &render::model_matrix(world, entity))?;
+            shader.set_int("uEntityId", entity as i32)?;
+            unsafe {
+                render_stats::bind_vertex_array(render_data.vao_id);
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    render_data.index_count,
+                    gl::UNSIGNED_INT,
+                    std::ptr::null(),
+                );
+            }
+            render_stats::record_draw(gl::TRIANGLES, render_data.index_count);
+        }
+
+        let (mut id, mut depth) = (0u32, 1.0f32);
+        unsafe {
+            render_stats::bind_vertex_array(0);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+            gl::ReadPixels(
+                x,
+                y,
+                1,
+                1,
+                gl::RED_INTEGER,
+                gl::UNSIGNED_INT,
+                &mut id as *mut u32 as *mut _,
+            );
+            gl::ReadPixels(
+                x,
+                y,
+                1,
+                1,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                &mut depth as *mut f32 as *mut _,
+            );
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+
+        Ok(id.checked_sub(1).map(|entity| (entity as usize, depth)))
+    }
+}
+
+fn create_target(width: i32, height: i32) -> (GLuint, GLuint, GLuint) {
+    let (mut fbo, mut ids, mut depth) = (0, 0, 0);
+    unsafe {
+        gl::GenFramebuffers(1, &mut fbo);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+
+        gl::GenTextures(1, &mut ids);
+        gl::BindTexture(gl::TEXTURE_2D, ids);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::R32UI as GLint,
+            width,
+            height,
+            0,
+            gl::RED_INTEGER,
+            gl::UNSIGNED_INT,
+            std::ptr::null(),
+        );
+        // Integer textures can't be filtered
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+        gl::FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            ids,
+            0,
+        );
+
+        gl::GenRenderbuffers(1, &mut depth);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, depth);
+        gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, width, height);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        gl::FramebufferRenderbuffer(
+            gl::FRAMEBUFFER,
+            gl::DEPTH_ATTACHMENT,
+            gl::RENDERBUFFER,
+            depth,
+        );
+
+        let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            error!("Framebuffer #{} is incomplete: 0x{:X}", fbo, status);
+        }
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+    }
+    render_stats::track_texture_memory(target_bytes(width, height));
+    (fbo, ids, depth)
+}
+
+/// A 32 bit id and a 24 bit depth value, padded to 32, per pixel
+fn target_bytes(width: i32, height: i32) -> isize {
+    width as isize * height as isize * 8
+}
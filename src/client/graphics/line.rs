@@ -0,0 +1,166 @@
+use gl::types::GLint;
+use log::debug;
+use nalgebra_glm as glm;
+
+use crate::graphics::frustum::Aabb;
+use crate::graphics::shader::{Shader, VertexArrayObject};
+
+pub const LINE_SHADER: (&str, &str) = ("assets/shaders/line.vert", "assets/shaders/line.frag");
+
+/// Both ends of the segment, the corner (side, end) and the distance along the line
+const LINE_ATTRIBUTES: &[(u32, i32)] = &[(0, 3), (1, 3), (2, 2), (3, 1)];
+const LINE_VERTEX_FLOATS: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineWidth {
+    /// Same thickness on screen at any distance
+    Pixels(f32),
+    /// Thins out with distance like any other geometry
+    World(f32),
+}
+
+/// Values match `CAP_*` in `common/line.glsl`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineCap {
+    /// Ends exactly at the end points
+    #[default]
+    Butt = 0,
+    /// Extends half the width past the end points
+    #[allow(dead_code)]
+    Square = 1,
+    /// A half circle past each end point. Also fills the gaps at polyline corners
+    Round = 2,
+}
+
+/// Lengths in world units along the line, whatever the width is measured in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineDash {
+    pub length: f32,
+    pub gap: f32,
+}
+
+/// How a `Line` entity is drawn. Lines without one are a pixel wide, solid and butt capped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineStyle {
+    pub width: LineWidth,
+    pub cap: LineCap,
+    pub dash: Option<LineDash>,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        LineStyle {
+            width: LineWidth::Pixels(1.0),
+            cap: LineCap::Butt,
+            dash: None,
+        }
+    }
+}
+
+impl LineStyle {
+    pub fn pixels(width: f32) -> Self {
+        LineStyle {
+            width: LineWidth::Pixels(width),
+            ..Default::default()
+        }
+    }
+
+    pub fn world(width: f32) -> Self {
+        LineStyle {
+            width: LineWidth::World(width),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    pub fn dashed(mut self, length: f32, gap: f32) -> Self {
+        self.dash = Some(LineDash { length, gap });
+        self
+    }
+
+    /// Sets the style's uniforms, and the viewport size the shader expands lines in
+    pub fn apply(&self, shader: &Shader) -> Result<(), String> {
+        let (width, world) = match self.width {
+            LineWidth::Pixels(width) => (width, false),
+            LineWidth::World(width) => (width, true),
+        };
+        shader.set_float("uWidth", width)?;
+        shader.set_bool("uWorldWidth", world)?;
+        shader.set_int("uCap", self.cap as i32)?;
+        let dash = self.dash.unwrap_or(LineDash {
+            length: 0.0,
+            gap: 0.0,
+        });
+        shader.set_float("uDashLength", dash.length)?;
+        shader.set_float("uGapLength", dash.gap)?;
+        shader.set_vec2("uViewport", &viewport_size())?;
+        Ok(())
+    }
+}
+
+/// A polyline on the GPU, as one quad per segment
+#[derive(Debug, Clone, Copy)]
+pub struct LineMesh {
+    pub vao_id: u32,
+    pub index_count: i32,
+    pub bounds: Aabb,
+}
+
+impl LineMesh {
+    /// Uploads a line through `points`, which are relative to the entity's position.
+    /// `closed` adds a segment from the last point back to the first
+    pub fn new(points: &[glm::Vec3], closed: bool) -> Self {
+        let mut path = points.to_vec();
+        if closed && points.len() > 2 {
+            path.push(points[0]);
+        }
+
+        let mut vertices = Vec::with_capacity(path.len() * 4 * LINE_VERTEX_FLOATS);
+        let mut indices = Vec::with_capacity(path.len() * 6);
+        let mut distance = 0.0;
+        for segment in path.windows(2) {
+            let (start, end) = (segment[0], segment[1]);
+            let length = glm::distance(&start, &end);
+            let base = (vertices.len() / LINE_VERTEX_FLOATS) as u32;
+            for (pos, other, end_flag, at) in [
+                (start, end, 0.0, distance),
+                (end, start, 1.0, distance + length),
+            ] {
+                for side in [-1.0, 1.0] {
+                    vertices.extend_from_slice(&[
+                        pos.x, pos.y, pos.z, other.x, other.y, other.z, side, end_flag, at,
+                    ]);
+                }
+            }
+            // Corners are start -1, start 1, end -1, end 1
+            indices.extend_from_slice(&[base, base + 1, base + 3, base, base + 3, base + 2]);
+            distance += length;
+        }
+
+        let vao = VertexArrayObject::from_interleaved(&vertices, &indices, LINE_ATTRIBUTES);
+        debug!("Uploaded {}-point line as VAO #{}", points.len(), vao.id);
+        let bounds = Aabb::from_vertices(
+            &points
+                .iter()
+                .flat_map(|p| [p.x, p.y, p.z])
+                .collect::<Vec<_>>(),
+            3,
+        );
+        LineMesh {
+            vao_id: vao.id,
+            index_count: indices.len() as i32,
+            bounds,
+        }
+    }
+}
+
+/// Size of the current viewport, in pixels
+pub fn viewport_size() -> glm::Vec2 {
+    let mut viewport: [GLint; 4] = [0; 4];
+    unsafe { gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()) };
+    glm::vec2(viewport[2] as f32, viewport[3] as f32)
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use log::warn;
+use nalgebra_glm as glm;
+
+use crate::graphics::shader::Shader;
+use crate::graphics::texture::{Texture, load_texture_from_file};
+
+pub const DIFFUSE_TEXTURE_UNIT: u32 = 0;
+pub const NORMAL_TEXTURE_UNIT: u32 = 1;
+
+/// Surface properties of a mesh, usually built from an MTL entry.
+/// Mirrors `struct Material` in `common/material.glsl`
+#[derive(Debug, Clone)]
+pub struct Material {
+    #[allow(dead_code)]
+    pub name: String,
+    pub diffuse: glm::Vec3,
+    pub diffuse_texture: Option<Texture>,
+    pub specular: glm::Vec3,
+    pub shininess: f32,
+    pub opacity: f32,
+    pub normal_texture: Option<Texture>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            name: String::from("Default"),
+            diffuse: glm::vec3(1., 1., 1.),
+            diffuse_texture: None,
+            specular: glm::vec3(0.9, 0.9, 0.9),
+            shininess: 16.0,
+            opacity: 1.0,
+            normal_texture: None,
+        }
+    }
+}
+
+impl Material {
+    /// Builds a material from a parsed MTL entry. Texture paths are resolved relative to
+    /// `base_dir` (the OBJ's directory) and loaded through `textures`, so materials sharing
+    /// an image share one GL texture. Textures that fail to load are logged and skipped
+    pub fn from_mtl(
+        mtl: &tobj::Material,
+        base_dir: &Path,
+        textures: &mut HashMap<String, Texture>,
+    ) -> Material {
+        let default = Material::default();
+        let mut load = |file: &Option<String>| -> Option<Texture> {
+            let path = base_dir.join(file.as_ref()?).to_string_lossy().into_owned();
+            if let Some(texture) = textures.get(&path) {
+                return Some(*texture);
+            }
+            match load_texture_from_file(&path, Default::default()) {
+                Ok(texture) => {
+                    textures.insert(path, texture);
+                    Some(texture)
+                }
+                Err(e) => {
+                    warn!("Material '{}': {}", mtl.name, e);
+                    None
+                }
+            }
+        };
+
+        Material {
+            name: mtl.name.clone(),
+            diffuse: mtl.diffuse.map_or(default.diffuse, glm::Vec3::from),
+            diffuse_texture: load(&mtl.diffuse_texture),
+            specular: mtl.specular.map_or(default.specular, glm::Vec3::from),
+            // Ns 0 is common in exported files and would make every pixel fully specular
+            shininess: mtl.shininess.unwrap_or(default.shininess).max(1.0),
+            // `d` is opacity, `Tr` is its inverse; tobj only parses `d`
+            opacity: mtl
+                .dissolve
+                .or_else(|| {
+                    let tr = mtl.unknown_param.get("Tr")?.trim().parse::<f32>().ok()?;
+                    Some(1.0 - tr)
+                })
+                .unwrap_or(default.opacity),
+            normal_texture: load(&mtl.normal_texture),
+        }
+    }
+
+    /// Uploads the material to `uMaterial` and binds its textures
+    pub fn apply(&self, shader: &Shader) -> Result<(), String> {
+        shader.set_vec3("uMaterial.diffuse", &self.diffuse)?;
+        shader.set_vec3("uMaterial.specular", &self.specular)?;
+        shader.set_float("uMaterial.shininess", self.shininess)?;
+        shader.set_float("uMaterial.opacity", self.opacity)?;
+
+        shader.set_bool("uMaterial.hasDiffuseMap", self.diffuse_texture.is_some())?;
+        if let Some(texture) = &self.diffuse_texture {
+            texture.bind(DIFFUSE_TEXTURE_UNIT);
+            shader.set_int("uDiffuseMap", DIFFUSE_TEXTURE_UNIT as i32)?;
+        }
+
+        shader.set_bool("uMaterial.hasNormalMap", self.normal_texture.is_some())?;
+        if let Some(texture) = &self.normal_texture {
+            texture.bind(NORMAL_TEXTURE_UNIT);
+            shader.set_int("uNormalMap", NORMAL_TEXTURE_UNIT as i32)?;
+        }
+
+        Ok(())
+    }
+}
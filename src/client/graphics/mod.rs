@@ -1,4 +1,23 @@
 pub mod camera;
 pub mod shader;
 pub mod windowing;
-pub mod texture;
\ No newline at end of file
+pub mod texture;
+pub mod hot_reload;
+pub mod material;
+pub mod preprocessor;
+pub mod uniform_buffer;
+pub mod gradient;
+pub mod shadow;
+pub mod sky;
+pub mod post;
+pub mod screenshot;
+pub mod frustum;
+pub mod debug_draw;
+pub mod line;
+pub mod font;
+pub mod text;
+pub mod debug_ui;
+pub mod id_buffer;
+pub mod render_stats;
+pub mod debug_view;
+pub mod particles;
\ No newline at end of file
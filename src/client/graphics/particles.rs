@@ -0,0 +1,506 @@
+use std::f32::consts::PI;
+
+use gl::types::GLuint;
+use nalgebra_glm as glm;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+use crate::ecs::ecs::World;
+use crate::graphics::camera::Camera3d;
+use crate::graphics::debug_view::DebugView;
+use crate::graphics::material::DIFFUSE_TEXTURE_UNIT;
+use crate::graphics::render_stats;
+use crate::graphics::shader::ShaderCache;
+use crate::graphics::texture::Texture;
+
+pub const PARTICLE_SHADER: (&str, &str) = (
+    "assets/shaders/particle.vert",
+    "assets/shaders/particle.frag",
+);
+
+/// Particles are simulated at this fixed rate, whatever the frame rate
+pub const PARTICLE_STEP: f32 = 1.0 / 60.0;
+/// Steps one frame can run before the rest is dropped, so a long hitch doesn't stall the next
+/// frame catching up
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+/// Center, size, then color and alpha, per particle
+const INSTANCE_FLOATS: usize = 8;
+/// Corners of the unit quad every particle is drawn with, as a triangle strip
+const QUAD_CORNERS: [f32; 8] = [-0.5, -0.5, 0.5, -0.5, -0.5, 0.5, 0.5, 0.5];
+
+/// Something a curve can blend between keys
+pub trait Lerp: Copy {
+    fn lerp(a: Self, b: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        a + (b - a) * t
+    }
+}
+
+impl Lerp for glm::Vec4 {
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        glm::lerp(&a, &b, t)
+    }
+}
+
+/// A value over a particle's life, blended linearly between keys at times from 0 (born) to
+/// 1 (dead). Holds the first and last key's values outside them
+#[derive(Debug, Clone)]
+pub struct Curve<T: Lerp> {
+    keys: Vec<(f32, T)>,
+}
+
+impl<T: Lerp> Curve<T> {
+    /// Keys are sorted by time, which is clamped to 0..1. Needs at least one key
+    pub fn new(mut keys: Vec<(f32, T)>) -> Result<Self, String> {
+        if keys.is_empty() {
+            return Err("A curve needs at least one key".to_owned());
+        }
+        for key in &mut keys {
+            key.0 = key.0.clamp(0.0, 1.0);
+        }
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Curve { keys })
+    }
+
+    pub fn constant(value: T) -> Self {
+        Curve {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    /// From `start` at birth to `end` at death
+    pub fn linear(start: T, end: T) -> Self {
+        Curve {
+            keys: vec![(0.0, start), (1.0, end)],
+        }
+    }
+
+    pub fn sample(&self, t: f32) -> T {
+        let next = self.keys.partition_point(|(time, _)| *time <= t);
+        if next == 0 {
+            return self.keys[0].1;
+        }
+        if next == self.keys.len() {
+            return self.keys[next - 1].1;
+        }
+        let (start, a) = self.keys[next - 1];
+        let (end, b) = self.keys[next];
+        T::lerp(a, b, (t - start) / (end - start).max(1e-6))
+    }
+}
+
+/// How particles are blended over what's behind them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleBlend {
+    /// Adds light, for fire and sparks. Order doesn't matter, so nothing is sorted
+    Additive,
+    /// Covers what's behind by the particle's alpha, for smoke and dust. Sorted back-to-front
+    Alpha,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Particle {
+    position: glm::Vec3,
+    velocity: glm::Vec3,
+    age: f32,
+    lifetime: f32,
+}
+
+/// Emits particles from the entity's `Position`. Particles live in world space, so moving
+/// the entity leaves the ones already emitted behind, like a trail.
+/// Colors are linear HDR, so additive particles brighter than 1 feed the bloom
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    pub emitting: bool,
+    /// Particles per second
+    pub rate: f32,
+    /// Seconds each particle lives
+    pub lifetime: f32,
+    /// Center of the cone particles are launched in, in world space
+    pub direction: glm::Vec3,
+    /// Angle from `direction` to the edge of the cone, in radians
+    pub spread: f32,
+    /// Launch speed, in world units per second
+    pub speed: f32,
+    /// Acceleration every particle feels, in world units per second squared
+    pub gravity: glm::Vec3,
+    pub color_over_life: Curve<glm::Vec4>,
+    /// Width of the billboard in world units
+    pub size_over_life: Curve<f32>,
+    /// Multiplied with the color. Untextured particles are soft round dots
+    pub texture: Option<Texture>,
+    pub blend: ParticleBlend,
+    /// New particles aren't emitted while this many are alive
+    pub max_particles: usize,
+    particles: Vec<Particle>,
+    /// Unspent time, under one `PARTICLE_STEP`
+    step_time: f32,
+    /// Fractional particles owed from `rate`
+    emit_debt: f32,
+    /// Seeded, so the same emitter always plays out the same way
+    rng: SmallRng,
+}
+
+impl ParticleEmitter {
+    pub fn new(rate: f32, lifetime: f32, blend: ParticleBlend) -> Self {
+        ParticleEmitter {
+            emitting: true,
+            rate,
+            lifetime,
+            direction: glm::vec3(0., 1., 0.),
+            spread: 0.3,
+            speed: 1.0,
+            gravity: glm::vec3(0., 0., 0.),
+            color_over_life: Curve::linear(glm::vec4(1., 1., 1., 1.), glm::vec4(1., 1., 1., 0.)),
+            size_over_life: Curve::constant(0.2),
+            texture: None,
+            blend,
+            max_particles: 1000,
+            particles: Vec::new(),
+            step_time: 0.0,
+            emit_debt: 0.0,
+            rng: SmallRng::seed_from_u64(0),
+        }
+    }
+
+    pub fn with_velocity(mut self, direction: glm::Vec3, spread: f32, speed: f32) -> Self {
+        self.direction = direction;
+        self.spread = spread;
+        self.speed = speed;
+        self
+    }
+
+    pub fn with_gravity(mut self, gravity: glm::Vec3) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    pub fn with_color(mut self, color_over_life: Curve<glm::Vec4>) -> Self {
+        self.color_over_life = color_over_life;
+        self
+    }
+
+    pub fn with_size(mut self, size_over_life: Curve<f32>) -> Self {
+        self.size_over_life = size_over_life;
+        self
+    }
+
+    pub fn with_texture(mut self, texture: Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = SmallRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Emits `count` particles at once, on top of the steady rate
+    pub fn burst(&mut self, origin: glm::Vec3, count: usize) {
+        for _ in 0..count {
+            self.spawn(origin);
+        }
+    }
+
+    /// Simulates `seconds` at once, so the emitter starts mid-effect instead of empty
+    pub fn prewarm(&mut self, origin: glm::Vec3, seconds: f32) {
+        for _ in 0..(seconds / PARTICLE_STEP) as u32 {
+            self.step(origin);
+        }
+    }
+
+    /// Runs as many fixed steps as `delta_time` adds up to, emitting from `origin`
+    pub fn update(&mut self, origin: glm::Vec3, delta_time: f32) {
+        self.step_time += delta_time;
+        let mut steps = 0;
+        while self.step_time >= PARTICLE_STEP {
+            self.step_time -= PARTICLE_STEP;
+            steps += 1;
+            if steps > MAX_STEPS_PER_FRAME {
+                self.step_time = 0.0;
+                break;
+            }
+            self.step(origin);
+        }
+    }
+
+    fn step(&mut self, origin: glm::Vec3) {
+        for particle in &mut self.particles {
+            particle.velocity += self.gravity * PARTICLE_STEP;
+            particle.position += particle.velocity * PARTICLE_STEP;
+            particle.age += PARTICLE_STEP;
+        }
+        self.particles
+            .retain(|particle| particle.age < particle.lifetime);
+
+        if self.emitting {
+            self.emit_debt += self.rate * PARTICLE_STEP;
+            while self.emit_debt >= 1.0 {
+                self.emit_debt -= 1.0;
+                self.spawn(origin);
+            }
+        }
+    }
+
+    fn spawn(&mut self, origin: glm::Vec3) {
+        if self.particles.len() >= self.max_particles || self.lifetime <= 0.0 {
+            return;
+        }
+        let direction = self.random_direction();
+        self.particles.push(Particle {
+            position: origin,
+            velocity: direction * self.speed,
+            age: 0.0,
+            lifetime: self.lifetime,
+        });
+    }
+
+    /// Uniform over the cap of the unit sphere within `spread` of `direction`
+    fn random_direction(&mut self) -> glm::Vec3 {
+        let axis = self
+            .direction
+            .try_normalize(1e-6)
+            .unwrap_or(glm::vec3(0., 1., 0.));
+        let cos_theta = 1.0 - self.rng.random::<f32>() * (1.0 - self.spread.min(PI).cos());
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = self.rng.random::<f32>() * 2.0 * PI;
+
+        // Any two vectors perpendicular to the axis and each other
+        let helper = if axis.y.abs() < 0.99 {
+            glm::vec3(0., 1., 0.)
+        } else {
+            glm::vec3(1., 0., 0.)
+        };
+        let tangent = axis.cross(&helper).normalize();
+        let bitangent = axis.cross(&tangent);
+        axis * cos_theta + (tangent * phi.cos() + bitangent * phi.sin()) * sin_theta
+    }
+}
+
+/// Moves every emitter along by `delta_time`. Emitters without a `Position` don't run
+pub fn update_particles(world: &mut World, delta_time: f32) {
+    for (entity, emitter) in &mut world.particle_emitters {
+        if let Some(position) = world.positions.get(entity) {
+            emitter.update(position.0, delta_time);
+        }
+    }
+}
+
+/// Draws every emitter's particles as camera-facing billboards, one instanced draw per emitter
+pub struct ParticleRenderer {
+    vao: GLuint,
+    instance_vbo: GLuint,
+    instances: Vec<f32>,
+}
+
+impl ParticleRenderer {
+    pub fn new() -> Self {
+        let (mut vao, mut quad_vbo, mut instance_vbo) = (0, 0, 0);
+        let float = std::mem::size_of::<f32>();
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            render_stats::bind_vertex_array(vao);
+
+            gl::GenBuffers(1, &mut quad_vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, quad_vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&QUAD_CORNERS) as isize,
+                QUAD_CORNERS.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+
+            // Center, size and color step once per particle instead of per vertex
+            gl::GenBuffers(1, &mut instance_vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, instance_vbo);
+            let stride = (INSTANCE_FLOATS * float) as i32;
+            for (location, size, offset) in [(1, 3, 0), (2, 1, 3), (3, 4, 4)] {
+                gl::VertexAttribPointer(
+                    location,
+                    size,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    stride,
+                    (offset * float) as *const _,
+                );
+                gl::EnableVertexAttribArray(location);
+                gl::VertexAttribDivisor(location, 1);
+            }
+            render_stats::bind_vertex_array(0);
+        }
+        render_stats::track_buffer_memory(std::mem::size_of_val(&QUAD_CORNERS) as isize);
+
+        ParticleRenderer {
+            vao,
+            instance_vbo,
+            instances: Vec::new(),
+        }
+    }
+
+    /// Call it during the scene pass, after opaque geometry, so particles are depth tested
+    /// against it without hiding each other. Only the lit and overdraw views show particles
+    pub fn render(
+        &mut self,
+        world: &World,
+        camera: &Camera3d,
+        shader_cache: &mut ShaderCache,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let overdraw = world.debug_view == DebugView::Overdraw;
+        if world.debug_view != DebugView::Lit && !overdraw {
+            return Ok(());
+        }
+
+        // Alpha blended emitters go back-to-front, then additive ones on top in any order
+        let mut emitters: Vec<_> = world
+            .particle_emitters
+            .iter()
+            .filter(|(_, emitter)| !emitter.particles.is_empty())
+            .map(|(entity, emitter)| {
+                let distance = world
+                    .positions
+                    .get(entity)
+                    .map_or(0.0, |p| glm::distance2(&p.0, &camera.position));
+                (emitter, distance)
+            })
+            .collect();
+        if emitters.is_empty() {
+            return Ok(());
+        }
+        emitters.sort_by(|(a, a_distance), (b, b_distance)| {
+            (a.blend == ParticleBlend::Additive)
+                .cmp(&(b.blend == ParticleBlend::Additive))
+                .then(b_distance.total_cmp(a_distance))
+        });
+
+        let shader = shader_cache.get(PARTICLE_SHADER.0, PARTICLE_SHADER.1, &[])?;
+        shader.use_program();
+        shader.set_bool("uOverdraw", overdraw)?;
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::DepthMask(gl::FALSE);
+            render_stats::bind_vertex_array(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instance_vbo);
+        }
+
+        for (emitter, _) in emitters {
+            self.fill_instances(emitter, camera);
+            let count = (self.instances.len() / INSTANCE_FLOATS) as i32;
+            shader.set_bool("uTextured", emitter.texture.is_some())?;
+            if let Some(texture) = &emitter.texture {
+                texture.bind(DIFFUSE_TEXTURE_UNIT);
+            }
+            unsafe {
+                match (overdraw, emitter.blend) {
+                    (true, _) | (false, ParticleBlend::Additive) => {
+                        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE)
+                    }
+                    (false, ParticleBlend::Alpha) => {
+                        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA)
+                    }
+                }
+                gl::BufferData(
+                    gl::ARRAY_BUFFER,
+                    std::mem::size_of_val(self.instances.as_slice()) as isize,
+                    self.instances.as_ptr() as *const _,
+                    gl::STREAM_DRAW,
+                );
+                gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, count);
+            }
+            // Two triangles a particle
+            render_stats::record_draw(gl::TRIANGLES, count * 6);
+        }
+
+        unsafe {
+            render_stats::bind_vertex_array(0);
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+        Ok(())
+    }
+
+    /// Packs an emitter's particles into `instances`, farthest first if they're alpha blended
+    fn fill_instances(&mut self, emitter: &ParticleEmitter, camera: &Camera3d) {
+        let mut particles: Vec<&Particle> = emitter.particles.iter().collect();
+        if emitter.blend == ParticleBlend::Alpha {
+            particles.sort_by(|a, b| {
+                glm::distance2(&b.position, &camera.position)
+                    .total_cmp(&glm::distance2(&a.position, &camera.position))
+            });
+        }
+
+        self.instances.clear();
+        for particle in particles {
+            let life = particle.age / particle.lifetime;
+            let size = emitter.size_over_life.sample(life);
+            let color = emitter.color_over_life.sample(life);
+            let p = particle.position;
+            self.instances
+                .extend_from_slice(&[p.x, p.y, p.z, size, color.x, color.y, color.z, color.w]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn curve_holds_its_ends() {
+        let curve = Curve::new(vec![(0.25, 1.), (0.75, 3.)]).unwrap();
+        assert_near(curve.sample(0.), 1.);
+        assert_near(curve.sample(0.25), 1.);
+        assert_near(curve.sample(0.75), 3.);
+        assert_near(curve.sample(1.), 3.);
+        // Ages past a particle's lifetime still sample the last key
+        assert_near(curve.sample(2.), 3.);
+    }
+
+    #[test]
+    fn curve_blends_between_keys() {
+        let curve = Curve::new(vec![(1., 10.), (0., 0.), (0.5, 2.)]).unwrap();
+        assert_near(curve.sample(0.25), 1.);
+        assert_near(curve.sample(0.5), 2.);
+        assert_near(curve.sample(0.75), 6.);
+    }
+
+    #[test]
+    fn curve_steps_at_duplicate_key_times() {
+        let curve = Curve::new(vec![(0., 0.), (0.5, 1.), (0.5, 5.), (1., 6.)]).unwrap();
+        // Approaching the step from below blends toward the first key, from the step on the
+        // last key holds
+        assert_near(curve.sample(0.25), 0.5);
+        assert_near(curve.sample(0.4999), 0.9998);
+        assert_near(curve.sample(0.5), 5.);
+        assert_near(curve.sample(0.75), 5.5);
+
+        let step = Curve::new(vec![(0.5, 1.), (0.5, 2.)]).unwrap();
+        assert_near(step.sample(0.25), 1.);
+        assert_near(step.sample(0.5), 2.);
+        assert_near(step.sample(0.75), 2.);
+    }
+
+    #[test]
+    fn curve_needs_a_key() {
+        assert!(Curve::<f32>::new(Vec::new()).is_err());
+        assert_near(Curve::constant(4.).sample(0.5), 4.);
+    }
+}
@@ -0,0 +1,369 @@
+use gl::types::{GLenum, GLint, GLuint};
+use image::RgbaImage;
+use log::{debug, error};
+use nalgebra_glm as glm;
+
+use crate::graphics::debug_view::DebugView;
+use crate::graphics::render_stats;
+use crate::graphics::screenshot::read_framebuffer;
+use crate::graphics::shader::ShaderCache;
+
+pub const POST_INPUT_TEXTURE_UNIT: u32 = 0;
+pub const POST_BLOOM_TEXTURE_UNIT: u32 = 1;
+
+const FULLSCREEN_VERT: &str = "assets/shaders/post/fullscreen.vert";
+const BLOOM_EXTRACT_FRAG: &str = "assets/shaders/post/bloom_extract.frag";
+const BLUR_FRAG: &str = "assets/shaders/post/blur.frag";
+const COMPOSITE_FRAG: &str = "assets/shaders/post/composite.frag";
+const FXAA_FRAG: &str = "assets/shaders/post/fxaa.frag";
+
+/// Values match `TONEMAP_*` in `post/composite.frag`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// Soft, lower contrast curve that never quite reaches white
+    Reinhard = 0,
+    /// Filmic curve with more contrast and saturated highlights
+    Aces = 1,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    /// Brightness (in linear HDR) above which pixels start to glow
+    pub threshold: f32,
+    pub intensity: f32,
+    /// Horizontal and vertical blur pairs. More passes spread the glow further
+    pub blur_passes: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ColorGrading {
+    pub enabled: bool,
+    pub contrast: f32,
+    /// 0 is greyscale, 1 leaves colors alone
+    pub saturation: f32,
+    /// Multiplied into every pixel after tone mapping
+    pub tint: glm::Vec3,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct VignetteSettings {
+    pub enabled: bool,
+    /// How dark the corners get, from 0 to 1
+    pub strength: f32,
+    /// Distance from the center where darkening starts, 1 being the corners
+    pub radius: f32,
+    pub softness: f32,
+}
+
+/// Every step of the post chain. The order is fixed: bloom, exposure, tone mapping,
+/// color grading, vignette, then FXAA on the final image
+#[derive(Debug, Clone, Copy)]
+pub struct PostSettings {
+    pub exposure: f32,
+    pub tonemapper: Tonemapper,
+    pub bloom: BloomSettings,
+    pub color_grading: ColorGrading,
+    pub vignette: VignetteSettings,
+    pub fxaa: bool,
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        PostSettings {
+            exposure: 1.0,
+            tonemapper: Tonemapper::Aces,
+            bloom: BloomSettings {
+                enabled: true,
+                threshold: 1.0,
+                intensity: 0.6,
+                blur_passes: 4,
+            },
+            color_grading: ColorGrading {
+                enabled: false,
+                contrast: 1.0,
+                saturation: 1.0,
+                tint: glm::vec3(1., 1., 1.),
+            },
+            vignette: VignetteSettings {
+                enabled: true,
+                strength: 0.35,
+                radius: 0.6,
+                softness: 0.5,
+            },
+            fxaa: true,
+        }
+    }
+}
+
+/// A framebuffer with one color texture and an optional depth/stencil renderbuffer
+struct RenderTarget {
+    fbo: GLuint,
+    color: GLuint,
+    depth_stencil: GLuint,
+    width: i32,
+    height: i32,
+    /// GPU memory used, for `RenderStats`
+    bytes: isize,
+}
+
+impl RenderTarget {
+    fn new(width: i32, height: i32, format: GLenum, with_depth: bool) -> RenderTarget {
+        let (mut fbo, mut color, mut depth_stencil) = (0, 0, 0);
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+
+            gl::GenTextures(1, &mut color);
+            gl::BindTexture(gl::TEXTURE_2D, color);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                format as GLint,
+                width,
+                height,
+                0,
+                gl::RGBA,
+                gl::FLOAT,
+                std::ptr::null(),
+            );
+            let params = [
+                (gl::TEXTURE_MIN_FILTER, gl::LINEAR),
+                (gl::TEXTURE_MAG_FILTER, gl::LINEAR),
+                (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE),
+            ];
+            for (name, value) in params {
+                gl::TexParameteri(gl::TEXTURE_2D, name, value as GLint);
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                color,
+                0,
+            );
+
+            if with_depth {
+                gl::GenRenderbuffers(1, &mut depth_stencil);
+                gl::BindRenderbuffer(gl::RENDERBUFFER, depth_stencil);
+                gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, width, height);
+                gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+                gl::FramebufferRenderbuffer(
+                    gl::FRAMEBUFFER,
+                    gl::DEPTH_STENCIL_ATTACHMENT,
+                    gl::RENDERBUFFER,
+                    depth_stencil,
+                );
+            }
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                error!("Framebuffer #{} is incomplete: 0x{:X}", fbo, status);
+            }
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+
+        let color_bytes = if format == gl::RGBA16F { 8 } else { 4 };
+        let depth_bytes = if with_depth { 4 } else { 0 };
+        let bytes = width as isize * height as isize * (color_bytes + depth_bytes);
+        render_stats::track_texture_memory(bytes);
+
+        RenderTarget {
+            fbo,
+            color,
+            depth_stencil,
+            width,
+            height,
+            bytes,
+        }
+    }
+
+    fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::Viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    fn bind_color(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.color);
+        }
+    }
+
+    fn delete(&self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(1, &self.color);
+            if self.depth_stencil != 0 {
+                gl::DeleteRenderbuffers(1, &self.depth_stencil);
+            }
+        }
+        render_stats::track_texture_memory(-self.bytes);
+    }
+}
+
+/// Owns the HDR target the scene is drawn into and runs the post chain that turns it into
+/// the final image on the default framebuffer
+pub struct PostProcessor {
+    pub settings: PostSettings,
+    hdr: RenderTarget,
+    /// Half resolution, ping-ponged between for the blur
+    bloom: [RenderTarget; 2],
+    /// Tone mapped image waiting for FXAA
+    ldr: RenderTarget,
+    /// Attribute-less VAO for the fullscreen triangle
+    vao: GLuint,
+}
+
+impl PostProcessor {
+    pub fn new(width: i32, height: i32) -> Self {
+        let mut vao = 0;
+        unsafe { gl::GenVertexArrays(1, &mut vao) };
+
+        let (hdr, bloom, ldr) = create_targets(width, height);
+        debug!("Created post-processing targets at {}x{}", width, height);
+
+        PostProcessor {
+            settings: PostSettings::default(),
+            hdr,
+            bloom,
+            ldr,
+            vao,
+        }
+    }
+
+    /// Recreates every target at the new framebuffer size
+    pub fn resize(&mut self, width: i32, height: i32) {
+        if width <= 0 || height <= 0 || (width, height) == (self.hdr.width, self.hdr.height) {
+            return;
+        }
+        self.hdr.delete();
+        self.ldr.delete();
+        for target in &self.bloom {
+            target.delete();
+        }
+        (self.hdr, self.bloom, self.ldr) = create_targets(width, height);
+        debug!("Resized post-processing targets to {}x{}", width, height);
+    }
+
+    /// Binds and clears the HDR target. Everything drawn until `finish` lands in it
+    pub fn begin(&self) {
+        self.hdr.bind();
+        unsafe {
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+        }
+    }
+
+    /// Runs the post chain on the HDR target and draws the result to the default framebuffer.
+    /// Debug views skip everything but FXAA, their colors are shown as drawn
+    pub fn finish(
+        &self,
+        shader_cache: &mut ShaderCache,
+        debug_view: DebugView,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let settings = &self.settings;
+        let bloom = settings.bloom.enabled && debug_view == DebugView::Lit;
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+            render_stats::bind_vertex_array(self.vao);
+        }
+
+        // Bloom: pull out the bright parts at half resolution, then blur them back and forth.
+        // Each pass pair ends back in bloom[0]
+        if bloom {
+            let extract = shader_cache.get(FULLSCREEN_VERT, BLOOM_EXTRACT_FRAG, &[])?;
+            extract.use_program();
+            extract.set_float("uThreshold", settings.bloom.threshold)?;
+            self.hdr.bind_color(POST_INPUT_TEXTURE_UNIT);
+            self.bloom[0].bind();
+            draw_fullscreen();
+
+            let blur = shader_cache.get(FULLSCREEN_VERT, BLUR_FRAG, &[])?;
+            blur.use_program();
+            for _ in 0..settings.bloom.blur_passes {
+                for (from, to, horizontal) in [(0, 1, true), (1, 0, false)] {
+                    blur.set_bool("uHorizontal", horizontal)?;
+                    self.bloom[from].bind_color(POST_INPUT_TEXTURE_UNIT);
+                    self.bloom[to].bind();
+                    draw_fullscreen();
+                }
+            }
+        }
+
+        // Composite into the LDR target when FXAA still has to run, straight to the screen otherwise
+        let composite = shader_cache.get(FULLSCREEN_VERT, COMPOSITE_FRAG, &[])?;
+        composite.use_program();
+        composite.set_bool("uBloomEnabled", bloom)?;
+        composite.set_float("uBloomIntensity", settings.bloom.intensity)?;
+        composite.set_float("uExposure", settings.exposure)?;
+        composite.set_int("uTonemapper", settings.tonemapper as i32)?;
+        composite.set_bool("uGradingEnabled", settings.color_grading.enabled)?;
+        composite.set_float("uContrast", settings.color_grading.contrast)?;
+        composite.set_float("uSaturation", settings.color_grading.saturation)?;
+        composite.set_vec3("uTint", &settings.color_grading.tint)?;
+        composite.set_bool("uVignetteEnabled", settings.vignette.enabled)?;
+        composite.set_float("uVignetteStrength", settings.vignette.strength)?;
+        composite.set_float("uVignetteRadius", settings.vignette.radius)?;
+        composite.set_float("uVignetteSoftness", settings.vignette.softness)?;
+        composite.set_int("uDebugView", debug_view.id())?;
+        self.hdr.bind_color(POST_INPUT_TEXTURE_UNIT);
+        self.bloom[0].bind_color(POST_BLOOM_TEXTURE_UNIT);
+        if settings.fxaa {
+            self.ldr.bind();
+        } else {
+            self.bind_screen();
+        }
+        draw_fullscreen();
+
+        if settings.fxaa {
+            let fxaa = shader_cache.get(FULLSCREEN_VERT, FXAA_FRAG, &[])?;
+            fxaa.use_program();
+            self.ldr.bind_color(POST_INPUT_TEXTURE_UNIT);
+            self.bind_screen();
+            draw_fullscreen();
+        }
+
+        unsafe {
+            render_stats::bind_vertex_array(0);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+        Ok(())
+    }
+
+    /// The scene as drawn into the HDR target, before any post-processing.
+    /// Colors above 1 are clamped
+    #[allow(dead_code)]
+    pub fn capture_scene(&self) -> RgbaImage {
+        read_framebuffer(self.hdr.fbo, self.hdr.width, self.hdr.height)
+    }
+
+    fn bind_screen(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, self.hdr.width, self.hdr.height);
+        }
+    }
+}
+
+fn create_targets(width: i32, height: i32) -> (RenderTarget, [RenderTarget; 2], RenderTarget) {
+    let (half_width, half_height) = ((width / 2).max(1), (height / 2).max(1));
+    (
+        RenderTarget::new(width, height, gl::RGBA16F, true),
+        [
+            RenderTarget::new(half_width, half_height, gl::RGBA16F, false),
+            RenderTarget::new(half_width, half_height, gl::RGBA16F, false),
+        ],
+        RenderTarget::new(width, height, gl::RGBA8, false),
+    )
+}
+
+fn draw_fullscreen() {
+    unsafe { gl::DrawArrays(gl::TRIANGLES, 0, 3) };
+    render_stats::record_draw(gl::TRIANGLES, 3);
+}
@@ -0,0 +1,330 @@
+use std::path::Path;
+
+use crate::util::file::load_file_as_cstr;
+
+/// `#include` paths are resolved relative to this directory
+pub const SHADER_INCLUDE_DIR: &str = "assets/shaders";
+
+/// A shader source with every `#include` expanded
+pub struct PreprocessedSource {
+    pub source: String,
+    /// Every file that went into `source`. A file's index is its GLSL source string number,
+    /// which is what compile errors report before the line number
+    pub files: Vec<String>,
+}
+
+/// Expands `#include "path"` directives and inserts `defines` right after `#version`.
+/// Each included file is wrapped in an include guard, so shared files don't need their own and
+/// their content is only compiled once even when several files include it. Guards rather than
+/// skipping repeat includes keep `#ifdef`s around an `#include` working: a file first included
+/// in an inactive branch is still there when it's included again outside one.
+/// `#line` directives are emitted around includes so compile errors point at the right file.
+///
+/// Defines are written as they'd appear after `#define`, e.g. `"TEXTURED"` or `"MAX_LIGHTS 8"`.
+/// Passing any to a source without a `#version` line is an error, there's nowhere to put them
+pub fn preprocess(path: &str, defines: &[&str]) -> Result<PreprocessedSource, String> {
+    preprocess_in(path, Path::new(SHADER_INCLUDE_DIR), defines)
+}
+
+fn preprocess_in(
+    path: &str,
+    include_dir: &Path,
+    defines: &[&str],
+) -> Result<PreprocessedSource, String> {
+    let mut out = PreprocessedSource {
+        source: String::new(),
+        files: Vec::new(),
+    };
+    expand(path, include_dir, defines, &mut out, &mut Vec::new())?;
+
+    let has_version = out
+        .source
+        .lines()
+        .any(|line| line.trim_start().starts_with("#version"));
+    if !defines.is_empty() && !has_version {
+        return Err(format!(
+            "{}: has no #version to put the defines {:?} after",
+            path, defines
+        ));
+    }
+    Ok(out)
+}
+
+/// Expands `path` into `out`. `stack` holds the files currently being expanded, an include of
+/// one of them is a cycle and is left out, its guard would hide it anyway
+fn expand(
+    path: &str,
+    include_dir: &Path,
+    defines: &[&str],
+    out: &mut PreprocessedSource,
+    stack: &mut Vec<String>,
+) -> Result<(), String> {
+    let file_index = match out.files.iter().position(|file| file == path) {
+        Some(index) => index,
+        None => {
+            out.files.push(path.to_owned());
+            out.files.len() - 1
+        }
+    };
+    let included = !stack.is_empty();
+
+    let source = load_file_as_cstr(path)
+        .map_err(|e| format!("Failed to read shader source {}: {}", path, e))?
+        .to_string_lossy()
+        .into_owned();
+
+    stack.push(path.to_owned());
+    if included {
+        out.source.push_str(&format!(
+            "#ifndef BUAT_INCLUDE_GUARD_{0}\n#define BUAT_INCLUDE_GUARD_{0}\n#line 1 {0}\n",
+            file_index
+        ));
+    }
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("#version") {
+            if included {
+                return Err(format!(
+                    "{}:{}: included files can't have a #version",
+                    path, line_number
+                ));
+            }
+            out.source.push_str(line);
+            out.source.push('\n');
+            for define in defines {
+                out.source.push_str(&format!("#define {}\n", define));
+            }
+            out.source
+                .push_str(&format!("#line {} {}\n", line_number + 1, file_index));
+        } else if let Some(rest) = trimmed.strip_prefix("#include") {
+            let include = parse_include(rest).ok_or_else(|| {
+                format!(
+                    "{}:{}: expected #include \"path\", got {}",
+                    path, line_number, trimmed
+                )
+            })?;
+            let include_path = include_dir.join(include).to_string_lossy().into_owned();
+
+            if !stack.contains(&include_path) {
+                expand(&include_path, include_dir, defines, out, stack)?;
+            }
+            out.source
+                .push_str(&format!("#line {} {}\n", line_number + 1, file_index));
+        } else {
+            out.source.push_str(line);
+            out.source.push('\n');
+        }
+    }
+
+    if included {
+        out.source.push_str("#endif\n");
+    }
+    stack.pop();
+    Ok(())
+}
+
+/// The path in what follows `#include`: `"path"`, optionally followed by a comment
+fn parse_include(rest: &str) -> Option<&str> {
+    let (include, after) = rest.trim_start().strip_prefix('"')?.split_once('"')?;
+    let after = after.trim();
+    (after.is_empty() || after.starts_with("//") || after.starts_with("/*")).then_some(include)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Writes `files` into a fresh directory named after the test
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("buat-preprocessor-{}-{}", std::process::id(), test));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in files {
+            std::fs::write(dir.join(name), contents).unwrap();
+        }
+        dir
+    }
+
+    fn run(dir: &Path, defines: &[&str]) -> Result<PreprocessedSource, String> {
+        preprocess_in(&dir.join("main.glsl").to_string_lossy(), dir, defines)
+    }
+
+    /// The lines the GLSL preprocessor would keep, following `#ifdef`, `#ifndef`, `#else`,
+    /// `#endif` and name-only `#define`s. Directives themselves are dropped
+    fn active_lines(source: &str, defines: &[&str]) -> Vec<String> {
+        let mut defined: Vec<String> = defines.iter().map(|d| d.to_string()).collect();
+        let mut active = vec![true];
+        let mut lines = Vec::new();
+        for line in source.lines() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let on = *active.last().unwrap();
+            match words.first().copied() {
+                Some("#ifdef") => active.push(on && defined.iter().any(|d| d == words[1])),
+                Some("#ifndef") => active.push(on && !defined.iter().any(|d| d == words[1])),
+                Some("#else") => {
+                    let branch = active.pop().unwrap();
+                    active.push(*active.last().unwrap() && !branch);
+                }
+                Some("#endif") => {
+                    active.pop();
+                }
+                Some("#define") if on => defined.push(words[1].to_owned()),
+                Some(word) if word.starts_with('#') => {}
+                _ if on => lines.push(line.to_owned()),
+                _ => {}
+            }
+        }
+        lines
+    }
+
+    /// Source string and line number `marker`'s line would be reported at
+    fn origin_of(source: &str, marker: &str) -> (usize, usize) {
+        let (mut file, mut line) = (0, 1);
+        for text in source.lines() {
+            if let Some(directive) = text.strip_prefix("#line ") {
+                let numbers: Vec<usize> = directive
+                    .split_whitespace()
+                    .map(|n| n.parse().unwrap())
+                    .collect();
+                (line, file) = (numbers[0], numbers[1]);
+                continue;
+            }
+            if text == marker {
+                return (file, line);
+            }
+            line += 1;
+        }
+        panic!("{} isn't in the source", marker);
+    }
+
+    #[test]
+    fn diamond_includes_are_compiled_once() {
+        let dir = write_files(
+            "diamond",
+            &[
+                (
+                    "main.glsl",
+                    "#version 330 core\n#include \"a.glsl\"\n#include \"b.glsl\"\nmain\n",
+                ),
+                ("a.glsl", "#include \"common.glsl\"\na\n"),
+                ("b.glsl", "#include \"common.glsl\"\nb\n"),
+                ("common.glsl", "common\n"),
+            ],
+        );
+        let out = run(&dir, &[]).unwrap();
+        assert_eq!(active_lines(&out.source, &[]), ["common", "a", "b", "main"]);
+        assert_eq!(out.files.len(), 4);
+    }
+
+    #[test]
+    fn include_in_an_inactive_branch_is_included_again_later() {
+        let dir = write_files(
+            "ifdef",
+            &[
+                (
+                    "main.glsl",
+                    "#version 330 core\n#ifdef SHADOWS\n#include \"a.glsl\"\n#endif\n\
+                     #include \"a.glsl\"\nmain\n",
+                ),
+                ("a.glsl", "a\n"),
+            ],
+        );
+        let out = run(&dir, &[]).unwrap();
+        assert_eq!(active_lines(&out.source, &[]), ["a", "main"]);
+        assert_eq!(active_lines(&out.source, &["SHADOWS"]), ["a", "main"]);
+    }
+
+    #[test]
+    fn cyclic_includes_stop() {
+        let dir = write_files(
+            "cycle",
+            &[
+                (
+                    "main.glsl",
+                    "#version 330 core\n#include \"a.glsl\"\nmain\n",
+                ),
+                ("a.glsl", "#include \"b.glsl\"\na\n"),
+                ("b.glsl", "#include \"a.glsl\"\nb\n"),
+            ],
+        );
+        let out = run(&dir, &[]).unwrap();
+        assert_eq!(active_lines(&out.source, &[]), ["b", "a", "main"]);
+    }
+
+    #[test]
+    fn line_numbers_resume_after_nested_includes() {
+        let dir = write_files(
+            "lines",
+            &[
+                (
+                    "main.glsl",
+                    "#version 330 core\n#include \"a.glsl\"\nmain3\n#include \"b.glsl\"\nmain5\n",
+                ),
+                ("a.glsl", "a1\n#include \"b.glsl\"\na3\n"),
+                ("b.glsl", "b1\nb2\n"),
+            ],
+        );
+        let out = run(&dir, &[]).unwrap();
+        let index = |name: &str| out.files.iter().position(|f| f.ends_with(name)).unwrap();
+        let (main, a, b) = (index("main.glsl"), index("a.glsl"), index("b.glsl"));
+        assert_eq!(origin_of(&out.source, "a1"), (a, 1));
+        assert_eq!(origin_of(&out.source, "b1"), (b, 1));
+        assert_eq!(origin_of(&out.source, "b2"), (b, 2));
+        assert_eq!(origin_of(&out.source, "a3"), (a, 3));
+        assert_eq!(origin_of(&out.source, "main3"), (main, 3));
+        assert_eq!(origin_of(&out.source, "main5"), (main, 5));
+    }
+
+    #[test]
+    fn version_in_an_include_is_rejected() {
+        let dir = write_files(
+            "version",
+            &[
+                ("main.glsl", "#version 330 core\n#include \"a.glsl\"\n"),
+                ("a.glsl", "#version 330 core\na\n"),
+            ],
+        );
+        let error = run(&dir, &[]).err().unwrap();
+        assert!(error.contains("can't have a #version"), "{}", error);
+    }
+
+    #[test]
+    fn defines_follow_version() {
+        let dir = write_files("defines", &[("main.glsl", "#version 330 core\nmain\n")]);
+        let out = run(&dir, &["TEXTURED", "MAX_LIGHTS 8"]).unwrap();
+        let lines: Vec<&str> = out.source.lines().collect();
+        assert_eq!(
+            lines[..4],
+            [
+                "#version 330 core",
+                "#define TEXTURED",
+                "#define MAX_LIGHTS 8",
+                "#line 2 0"
+            ]
+        );
+        assert_eq!(origin_of(&out.source, "main"), (0, 2));
+    }
+
+    #[test]
+    fn defines_without_version_are_an_error() {
+        let dir = write_files("no-version", &[("main.glsl", "main\n")]);
+        assert!(run(&dir, &["TEXTURED"]).is_err());
+        assert!(run(&dir, &[]).is_ok());
+    }
+
+    #[test]
+    fn include_can_have_a_trailing_comment() {
+        assert_eq!(parse_include(" \"a.glsl\" // lighting"), Some("a.glsl"));
+        assert_eq!(parse_include(" \"a.glsl\"/* x */"), Some("a.glsl"));
+        assert_eq!(parse_include(" \"a.glsl\""), Some("a.glsl"));
+        assert_eq!(parse_include(" \"a.glsl\" b.glsl"), None);
+        assert_eq!(parse_include(" a.glsl"), None);
+        assert_eq!(parse_include(" \"a.glsl"), None);
+    }
+}
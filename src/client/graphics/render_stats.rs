@@ -0,0 +1,340 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicIsize, AtomicU32, AtomicUsize, Ordering};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use gl::types::{GLenum, GLuint};
+use log::{debug, warn};
+
+use crate::ecs::render::FrameStats;
+
+pub const STATS_DIR: &str = "stats";
+
+/// Frames a GPU timer query gets to finish before its result is read. Reading sooner would
+/// stall until the GPU catches up
+const QUERY_FRAMES: usize = 4;
+
+// Counted from wherever the GL calls are made, so nothing has to thread the stats through
+static DRAW_CALLS: AtomicUsize = AtomicUsize::new(0);
+static TRIANGLES: AtomicUsize = AtomicUsize::new(0);
+static STATE_CHANGES: AtomicUsize = AtomicUsize::new(0);
+static TEXTURE_MEMORY: AtomicIsize = AtomicIsize::new(0);
+static BUFFER_MEMORY: AtomicIsize = AtomicIsize::new(0);
+/// What `use_program` and `bind_vertex_array` last bound, so binding it again isn't counted.
+/// Every program and vertex array bind outside ImGui goes through them to keep these right.
+/// `UNKNOWN` at the start of each frame, since ImGui binds its own without going through them
+static BOUND_PROGRAM: AtomicU32 = AtomicU32::new(UNKNOWN);
+static BOUND_VERTEX_ARRAY: AtomicU32 = AtomicU32::new(UNKNOWN);
+const UNKNOWN: GLuint = GLuint::MAX;
+
+/// Counts a draw call of `count` vertices drawn as `mode`
+pub fn record_draw(mode: GLenum, count: i32) {
+    DRAW_CALLS.fetch_add(1, Ordering::Relaxed);
+    if mode == gl::TRIANGLES {
+        TRIANGLES.fetch_add(count.max(0) as usize / 3, Ordering::Relaxed);
+    }
+}
+
+/// Counts a texture bind. Programs and vertex arrays are counted by `use_program` and
+/// `bind_vertex_array`
+pub fn record_state_change() {
+    STATE_CHANGES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Makes `program` current, counting a state change unless it already was
+pub fn use_program(program: GLuint) {
+    unsafe { gl::UseProgram(program) };
+    if BOUND_PROGRAM.swap(program, Ordering::Relaxed) != program {
+        record_state_change();
+    }
+}
+
+/// Binds `vao`, counting a state change unless it already was bound
+pub fn bind_vertex_array(vao: GLuint) {
+    unsafe { gl::BindVertexArray(vao) };
+    if BOUND_VERTEX_ARRAY.swap(vao, Ordering::Relaxed) != vao {
+        record_state_change();
+    }
+}
+
+/// Adds to the texture memory total. Pass a negative size when freeing
+pub fn track_texture_memory(bytes: isize) {
+    TEXTURE_MEMORY.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Adds to the buffer memory total. Pass a negative size when freeing
+pub fn track_buffer_memory(bytes: isize) {
+    BUFFER_MEMORY.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// What one frame drew
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameCounters {
+    pub draw_calls: usize,
+    pub triangles: usize,
+    pub state_changes: usize,
+    pub drawn: usize,
+    /// Skipped for being outside the camera's frustum
+    pub culled: usize,
+}
+
+/// `GL_TIME_ELAPSED` queries for one pass, one per frame in flight
+struct GpuTimer {
+    name: &'static str,
+    queries: [GLuint; QUERY_FRAMES],
+    pending: [bool; QUERY_FRAMES],
+}
+
+/// A CSV file getting a row per frame until `remaining` runs out
+struct Capture {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    remaining: f32,
+    /// Pass and system columns, fixed when the header is written
+    columns: Option<(Vec<&'static str>, Vec<&'static str>)>,
+}
+
+/// Where frame time goes: counts of what was drawn, memory in use, and how long each pass took
+/// on the GPU and each system on the CPU. Everything is from the last finished frame, except GPU
+/// times which lag a few frames behind
+pub struct RenderStats {
+    pub frame: FrameCounters,
+    /// Bytes, for the textures and static buffers the renderer creates. Buffers streamed every
+    /// draw and ImGui's own aren't counted
+    pub texture_memory: usize,
+    pub buffer_memory: usize,
+    /// Milliseconds per pass, in the order they ran
+    pub gpu_times: Vec<(&'static str, f32)>,
+    /// Milliseconds per system, in the order they ran
+    pub cpu_times: Vec<(&'static str, f32)>,
+    pub frame_time: f32,
+    timers: Vec<GpuTimer>,
+    /// What's being timed, since when, and whether it has a GPU query running
+    open: Option<(&'static str, Instant, bool)>,
+    current: FrameCounters,
+    current_cpu_times: Vec<(&'static str, f32)>,
+    frame_index: usize,
+    capture: Option<Capture>,
+}
+
+impl RenderStats {
+    pub fn new() -> Self {
+        RenderStats {
+            frame: FrameCounters::default(),
+            texture_memory: 0,
+            buffer_memory: 0,
+            gpu_times: Vec::new(),
+            cpu_times: Vec::new(),
+            frame_time: 0.0,
+            timers: Vec::new(),
+            open: None,
+            current: FrameCounters::default(),
+            current_cpu_times: Vec::new(),
+            frame_index: 0,
+            capture: None,
+        }
+    }
+
+    /// Finishes the previous frame's stats, logs them if capturing, and starts counting again.
+    /// Call it once at the top of the frame
+    pub fn begin_frame(&mut self, delta_time: f32) {
+        self.end();
+        self.current.draw_calls = DRAW_CALLS.swap(0, Ordering::Relaxed);
+        self.current.triangles = TRIANGLES.swap(0, Ordering::Relaxed);
+        self.current.state_changes = STATE_CHANGES.swap(0, Ordering::Relaxed);
+        BOUND_PROGRAM.store(UNKNOWN, Ordering::Relaxed);
+        BOUND_VERTEX_ARRAY.store(UNKNOWN, Ordering::Relaxed);
+        self.frame = std::mem::take(&mut self.current);
+        self.texture_memory = TEXTURE_MEMORY.load(Ordering::Relaxed).max(0) as usize;
+        self.buffer_memory = BUFFER_MEMORY.load(Ordering::Relaxed).max(0) as usize;
+        self.cpu_times = std::mem::take(&mut self.current_cpu_times);
+        self.frame_time = delta_time * 1000.0;
+        self.frame_index += 1;
+        self.read_timers();
+        self.write_capture_row(delta_time);
+    }
+
+    /// Starts timing a pass on both the GPU and the CPU, ending whatever was being timed.
+    /// Passes can't nest
+    pub fn begin_pass(&mut self, name: &'static str) {
+        self.end();
+        let slot = self.frame_index % QUERY_FRAMES;
+        let index = match self.timers.iter().position(|timer| timer.name == name) {
+            Some(index) => index,
+            None => {
+                let mut queries = [0; QUERY_FRAMES];
+                unsafe { gl::GenQueries(QUERY_FRAMES as i32, queries.as_mut_ptr()) };
+                self.timers.push(GpuTimer {
+                    name,
+                    queries,
+                    pending: [false; QUERY_FRAMES],
+                });
+                self.timers.len() - 1
+            }
+        };
+        let timer = &mut self.timers[index];
+        unsafe { gl::BeginQuery(gl::TIME_ELAPSED, timer.queries[slot]) };
+        timer.pending[slot] = true;
+        self.open = Some((name, Instant::now(), true));
+    }
+
+    /// Starts timing a system on the CPU only, ending whatever was being timed
+    pub fn begin_system(&mut self, name: &'static str) {
+        self.end();
+        self.open = Some((name, Instant::now(), false));
+    }
+
+    /// Stops timing the current pass or system
+    pub fn end(&mut self) {
+        let Some((name, start, gpu)) = self.open.take() else {
+            return;
+        };
+        if gpu {
+            unsafe { gl::EndQuery(gl::TIME_ELAPSED) };
+        }
+        let elapsed = start.elapsed().as_secs_f32() * 1000.0;
+        match self
+            .current_cpu_times
+            .iter_mut()
+            .find(|(system, _)| *system == name)
+        {
+            Some((_, time)) => *time += elapsed,
+            None => self.current_cpu_times.push((name, elapsed)),
+        }
+    }
+
+    /// Adds what `render::render_world` drew and culled to this frame
+    pub fn add_frame_stats(&mut self, stats: &FrameStats) {
+        self.current.drawn += stats.drawn;
+        self.current.culled += stats.culled;
+    }
+
+    pub fn capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Writes a CSV row per frame for the next `duration` seconds, to a new file in `STATS_DIR`
+    pub fn start_capture(&mut self, duration: f32) -> Result<PathBuf, String> {
+        std::fs::create_dir_all(STATS_DIR)
+            .map_err(|e| format!("Failed to create {}: {}", STATS_DIR, e))?;
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let path = Path::new(STATS_DIR).join(format!("render_stats_{}.csv", millis));
+        let file = File::create(&path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        debug!(
+            "Capturing render stats to {} for {}s",
+            path.display(),
+            duration
+        );
+        self.capture = Some(Capture {
+            path: path.clone(),
+            writer: BufWriter::new(file),
+            remaining: duration,
+            columns: None,
+        });
+        Ok(path)
+    }
+
+    /// Collects the oldest frame's GPU times, which should be done by now
+    fn read_timers(&mut self) {
+        let slot = self.frame_index % QUERY_FRAMES;
+        let mut times = Vec::with_capacity(self.timers.len());
+        for timer in &mut self.timers {
+            if !timer.pending[slot] {
+                continue;
+            }
+            timer.pending[slot] = false;
+            let (mut available, mut nanoseconds) = (0, 0u64);
+            unsafe {
+                gl::GetQueryObjectiv(
+                    timer.queries[slot],
+                    gl::QUERY_RESULT_AVAILABLE,
+                    &mut available,
+                );
+                if available == 0 {
+                    continue;
+                }
+                gl::GetQueryObjectui64v(timer.queries[slot], gl::QUERY_RESULT, &mut nanoseconds);
+            }
+            times.push((timer.name, nanoseconds as f32 / 1_000_000.0));
+        }
+        if !times.is_empty() {
+            self.gpu_times = times;
+        }
+    }
+
+    fn write_capture_row(&mut self, delta_time: f32) {
+        let Some(capture) = &mut self.capture else {
+            return;
+        };
+
+        let mut result = Ok(());
+        if capture.columns.is_none() {
+            // Every pass timed so far, since GPU times lag and some passes skip frames
+            let passes: Vec<_> = self.timers.iter().map(|timer| timer.name).collect();
+            let systems: Vec<_> = self.cpu_times.iter().map(|(name, _)| *name).collect();
+            let mut header: Vec<String> = [
+                "frame",
+                "frame_ms",
+                "draw_calls",
+                "triangles",
+                "state_changes",
+                "drawn",
+                "culled",
+                "texture_bytes",
+                "buffer_bytes",
+            ]
+            .iter()
+            .map(|column| column.to_string())
+            .collect();
+            header.extend(passes.iter().map(|name| format!("gpu_{}_ms", name)));
+            header.extend(systems.iter().map(|name| format!("cpu_{}_ms", name)));
+            result = writeln!(capture.writer, "{}", header.join(","));
+            capture.columns = Some((passes, systems));
+        }
+
+        let frame = &self.frame;
+        let mut row = vec![
+            self.frame_index.to_string(),
+            format!("{:.3}", self.frame_time),
+            frame.draw_calls.to_string(),
+            frame.triangles.to_string(),
+            frame.state_changes.to_string(),
+            frame.drawn.to_string(),
+            frame.culled.to_string(),
+            self.texture_memory.to_string(),
+            self.buffer_memory.to_string(),
+        ];
+        // Passes or systems that didn't run this frame are left empty
+        let lookup = |times: &[(&'static str, f32)], name: &str| {
+            times
+                .iter()
+                .find(|(other, _)| *other == name)
+                .map(|(_, time)| format!("{:.3}", time))
+                .unwrap_or_default()
+        };
+        let (passes, systems) = capture.columns.as_ref().unwrap();
+        row.extend(passes.iter().map(|name| lookup(&self.gpu_times, name)));
+        row.extend(systems.iter().map(|name| lookup(&self.cpu_times, name)));
+        result = result.and_then(|_| writeln!(capture.writer, "{}", row.join(",")));
+
+        capture.remaining -= delta_time;
+        if let Err(e) = result {
+            warn!("Failed to write {}: {}", capture.path.display(), e);
+            self.capture = None;
+        } else if capture.remaining <= 0.0 {
+            if let Err(e) = capture.writer.flush() {
+                warn!("Failed to write {}: {}", capture.path.display(), e);
+            }
+            debug!(
+                "Finished capturing render stats to {}",
+                capture.path.display()
+            );
+            self.capture = None;
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gl::types::{GLint, GLuint};
+use image::RgbaImage;
+use log::debug;
+
+use crate::graphics::windowing::GameWindow;
+
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+/// Reads the color of a framebuffer back into an image, flipped so the first row is the top.
+/// `fbo` 0 reads the default framebuffer's back buffer, so call it before swapping buffers
+pub fn read_framebuffer(fbo: GLuint, width: i32, height: i32) -> RgbaImage {
+    let (width, height) = (width.max(1), height.max(1));
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    unsafe {
+        let mut previous: GLint = 0;
+        gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut previous);
+
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+        gl::ReadBuffer(if fbo == 0 {
+            gl::BACK
+        } else {
+            gl::COLOR_ATTACHMENT0
+        });
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width,
+            height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut _,
+        );
+
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, previous as GLuint);
+    }
+
+    let mut image = RgbaImage::from_raw(width as u32, height as u32, pixels).unwrap();
+    image::imageops::flip_vertical_in_place(&mut image);
+    image
+}
+
+/// Whatever was last drawn to the window. Alpha is forced to opaque, the default framebuffer's
+/// alpha isn't meaningful
+pub fn capture_screen(game_window: &GameWindow) -> RgbaImage {
+    let (width, height) = game_window.win.get_framebuffer_size();
+    let mut image = read_framebuffer(0, width, height);
+    for pixel in image.pixels_mut() {
+        pixel[3] = 255;
+    }
+    image
+}
+
+/// Saves `image` as a PNG, creating missing directories on the way
+pub fn save_png(image: &RgbaImage, path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    image
+        .save_with_format(path, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to save screenshot {}: {}", path.display(), e))?;
+    debug!(
+        "Saved {}x{} screenshot to {}",
+        image.width(),
+        image.height(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// A fresh `screenshots/screenshot_<unix millis>.png` path
+pub fn screenshot_path() -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    Path::new(SCREENSHOT_DIR).join(format!("screenshot_{}.png", millis))
+}
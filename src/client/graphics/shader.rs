@@ -1,13 +1,76 @@
-use gl::types::{GLint, GLuint};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use gl::types::{GLenum, GLint, GLuint};
 use log::debug;
 use nalgebra_glm as glm;
 
-const ERROR_ON_NO_UNIFORM_FOUND: bool = false;
+use crate::graphics::material::{DIFFUSE_TEXTURE_UNIT, NORMAL_TEXTURE_UNIT};
+use crate::graphics::post::{POST_BLOOM_TEXTURE_UNIT, POST_INPUT_TEXTURE_UNIT};
+use crate::graphics::render_stats;
+use crate::graphics::shadow::{POINT_SHADOW_TEXTURE_UNIT, SUN_SHADOW_TEXTURE_UNIT};
+use crate::graphics::sky::SKYBOX_TEXTURE_UNIT;
+use crate::graphics::text::FONT_ATLAS_TEXTURE_UNIT;
+use crate::graphics::{preprocessor, uniform_buffer};
+use crate::object::part::material::PART_MATERIAL_TEXTURE_UNIT;
+
+/// Uniform types that are set through `glUniform1i`
+const INT_LIKE_TYPES: &[GLenum] = &[
+    gl::INT,
+    gl::BOOL,
+    gl::SAMPLER_2D,
+    gl::SAMPLER_3D,
+    gl::SAMPLER_CUBE,
+    gl::SAMPLER_2D_ARRAY,
+    gl::SAMPLER_2D_SHADOW,
+    gl::SAMPLER_2D_ARRAY_SHADOW,
+    gl::SAMPLER_CUBE_SHADOW,
+];
+
+/// Samplers that always read from the same texture unit. Programs are pointed at these after
+/// linking, because unset samplers all default to unit 0 and two samplers of different
+/// types on one unit make every draw with the program fail
+pub const SAMPLER_UNITS: &[(&str, u32)] = &[
+    ("uTexture", DIFFUSE_TEXTURE_UNIT),
+    ("uDiffuseMap", DIFFUSE_TEXTURE_UNIT),
+    ("uNormalMap", NORMAL_TEXTURE_UNIT),
+    ("uMaterialArray", PART_MATERIAL_TEXTURE_UNIT),
+    ("uSunShadowMap", SUN_SHADOW_TEXTURE_UNIT),
+    ("uPointShadowMap", POINT_SHADOW_TEXTURE_UNIT),
+    ("uSkybox", SKYBOX_TEXTURE_UNIT),
+    ("uInput", POST_INPUT_TEXTURE_UNIT),
+    ("uBloom", POST_BLOOM_TEXTURE_UNIT),
+    ("uFontAtlas", FONT_ATLAS_TEXTURE_UNIT),
+];
+
+/// Paths a program was built from, kept so it can be rebuilt when they change on disk
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderSources {
+    pub vertex_path: String,
+    pub fragment_path: String,
+    pub defines: Vec<String>,
+    /// Both stages plus everything they `#include`
+    pub files: Vec<String>,
+}
+
+/// An active uniform as reported by `glGetActiveUniform`
+#[derive(Debug, Clone, Copy)]
+pub struct UniformInfo {
+    pub location: GLint,
+    pub kind: GLenum,
+    pub size: GLint,
+}
+
+#[derive(Debug, Clone)]
 pub struct Shader {
     pub program: GLuint,
+    pub sources: Option<Rc<ShaderSources>>,
+    pub uniforms: Rc<HashMap<String, UniformInfo>>,
+    /// Whether setting a uniform the program doesn't have is an error, or silently skipped
+    pub error_on_no_uniform_found: bool,
 }
 
-fn compile_shader(source: &str, kind: GLuint) -> GLuint {
+fn compile_shader(source: &str, kind: GLuint) -> Result<GLuint, String> {
     debug!(
         "Compiling {} shader",
         match kind {
@@ -33,23 +96,67 @@ fn compile_shader(source: &str, kind: GLuint) -> GLuint {
         let mut len = 0;
         unsafe { gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len) };
 
-        let error_buffer = vec![0u8; (len as usize).saturating_sub(1)];
-        let error_log = std::ffi::CString::new(error_buffer).unwrap();
-
+        let mut error_log = vec![0u8; len.max(1) as usize];
         unsafe {
-            gl::GetShaderInfoLog(id, len, std::ptr::null_mut(), error_log.as_ptr() as *mut _);
+            gl::GetShaderInfoLog(
+                id,
+                len,
+                std::ptr::null_mut(),
+                error_log.as_mut_ptr() as *mut _,
+            );
+            gl::DeleteShader(id);
         }
 
-        panic!(
+        return Err(format!(
             "Shader compilation failed (type {}): {}",
             kind,
-            error_log.to_string_lossy()
-        );
+            String::from_utf8_lossy(&error_log).trim_end_matches('\0')
+        ));
     }
 
     debug!("Created shader with id {}", id);
 
-    id
+    Ok(id)
+}
+
+fn link_program(vert_shader: GLuint, frag_shader: GLuint) -> Result<GLuint, String> {
+    let program = unsafe {
+        let program = gl::CreateProgram();
+        gl::AttachShader(program, vert_shader);
+        gl::AttachShader(program, frag_shader);
+        gl::LinkProgram(program);
+
+        gl::DeleteShader(vert_shader);
+        gl::DeleteShader(frag_shader);
+
+        program
+    };
+
+    let mut success: GLint = 1;
+    unsafe { gl::GetProgramiv(program, gl::LINK_STATUS, &mut success) };
+
+    if success == 0 {
+        let mut len = 0;
+        unsafe { gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len) };
+
+        let mut error_log = vec![0u8; len.max(1) as usize];
+        unsafe {
+            gl::GetProgramInfoLog(
+                program,
+                len,
+                std::ptr::null_mut(),
+                error_log.as_mut_ptr() as *mut _,
+            );
+            gl::DeleteProgram(program);
+        }
+
+        return Err(format!(
+            "Program linking failed: {}",
+            String::from_utf8_lossy(&error_log).trim_end_matches('\0')
+        ));
+    }
+
+    Ok(program)
 }
 fn get_uniform_location(program: GLuint, name: &str) -> i32 {
     let c_name = std::ffi::CString::new(name)
@@ -59,94 +166,406 @@ fn get_uniform_location(program: GLuint, name: &str) -> i32 {
     unsafe { gl::GetUniformLocation(program, c_name.as_ptr()) }
 }
 
-impl Shader {
-    pub fn new(vertex_src: &str, fragment_src: &str) -> Self {
-        let vert_shader = compile_shader(vertex_src, gl::VERTEX_SHADER);
-        let frag_shader = compile_shader(fragment_src, gl::FRAGMENT_SHADER);
+/// Reads every active uniform of a linked program once, so setters never have to query GL.
+/// Arrays are registered under `name`, `name[0]` and each `name[i]`
+fn reflect_uniforms(program: GLuint) -> HashMap<String, UniformInfo> {
+    let mut uniforms = HashMap::new();
+
+    let (mut count, mut max_len) = (0, 0);
+    unsafe {
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_len);
+    }
+
+    let mut name_buffer = vec![0u8; max_len.max(1) as usize];
+    for index in 0..count as GLuint {
+        let (mut len, mut size, mut kind) = (0, 0, 0);
+        unsafe {
+            gl::GetActiveUniform(
+                program,
+                index,
+                name_buffer.len() as i32,
+                &mut len,
+                &mut size,
+                &mut kind,
+                name_buffer.as_mut_ptr() as *mut _,
+            );
+        }
+        let name = String::from_utf8_lossy(&name_buffer[..len as usize]).into_owned();
+
+        // Uniforms living in a uniform block have no location of their own
+        let location = get_uniform_location(program, &name);
+        if location == -1 {
+            continue;
+        }
+
+        match name.strip_suffix("[0]") {
+            Some(base) => {
+                for i in 1..size {
+                    let element = format!("{}[{}]", base, i);
+                    let element_location = get_uniform_location(program, &element);
+                    uniforms.insert(
+                        element,
+                        UniformInfo {
+                            location: element_location,
+                            kind,
+                            size: size - i,
+                        },
+                    );
+                }
+                uniforms.insert(
+                    base.to_owned(),
+                    UniformInfo {
+                        location,
+                        kind,
+                        size,
+                    },
+                );
+                uniforms.insert(
+                    name,
+                    UniformInfo {
+                        location,
+                        kind,
+                        size,
+                    },
+                );
+            }
+            None => {
+                uniforms.insert(
+                    name,
+                    UniformInfo {
+                        location,
+                        kind,
+                        size,
+                    },
+                );
+            }
+        }
+    }
 
-        let program = unsafe {
-            let program = gl::CreateProgram();
-            gl::AttachShader(program, vert_shader);
-            gl::AttachShader(program, frag_shader);
-            gl::LinkProgram(program);
+    debug!("Program #{} has {} active uniforms", program, count);
 
-            gl::DeleteShader(vert_shader);
-            gl::DeleteShader(frag_shader);
+    uniforms
+}
+
+/// Points each sampler in `SAMPLER_UNITS` the program declares at its texture unit
+fn bind_sampler_units(program: GLuint, uniforms: &HashMap<String, UniformInfo>) {
+    render_stats::use_program(program);
+    for (name, unit) in SAMPLER_UNITS {
+        if let Some(uniform) = uniforms.get(*name) {
+            unsafe { gl::Uniform1i(uniform.location, *unit as GLint) };
+        }
+    }
+    render_stats::use_program(0);
+}
 
-            program
+fn gl_type_name(kind: GLenum) -> &'static str {
+    match kind {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::BOOL => "bool",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_2D_ARRAY_SHADOW => "sampler2DArrayShadow",
+        gl::SAMPLER_CUBE_SHADOW => "samplerCubeShadow",
+        _ => "unknown type",
+    }
+}
+
+impl Shader {
+    pub fn new(vertex_src: &str, fragment_src: &str) -> Result<Self, String> {
+        let vert_shader = compile_shader(vertex_src, gl::VERTEX_SHADER)?;
+        let frag_shader = match compile_shader(fragment_src, gl::FRAGMENT_SHADER) {
+            Ok(id) => id,
+            Err(e) => {
+                unsafe { gl::DeleteShader(vert_shader) };
+                return Err(e);
+            }
         };
+
+        let program = link_program(vert_shader, frag_shader)?;
+        uniform_buffer::bind_uniform_blocks(program);
+        let uniforms = reflect_uniforms(program);
+        bind_sampler_units(program, &uniforms);
         debug!("Created program with id {}", program);
 
-        Shader { program }
+        Ok(Shader {
+            program,
+            sources: None,
+            uniforms: Rc::new(uniforms),
+            error_on_no_uniform_found: false,
+        })
     }
 
+    /// Loads both stages through the preprocessor, expanding `#include`s and adding `defines`
+    /// (e.g. `"TEXTURED"` or `"MAX_LIGHTS 8"`) to each
     pub fn from_files(
         vertex_path: &str,
         fragment_path: &str,
+        defines: &[&str],
     ) -> Result<Shader, Box<dyn std::error::Error>> {
-        let vertex_source = crate::util::file::load_file_as_cstr(vertex_path)?;
-        let fragment_source = crate::util::file::load_file_as_cstr(fragment_path)?;
+        let vertex = preprocessor::preprocess(vertex_path, defines)?;
+        let fragment = preprocessor::preprocess(fragment_path, defines)?;
+
+        let mut shader = Shader::new(&vertex.source, &fragment.source).map_err(|e| {
+            format!(
+                "{}\nVertex source strings: {:?}\nFragment source strings: {:?}",
+                e, vertex.files, fragment.files
+            )
+        })?;
+
+        let mut files = vertex.files;
+        for file in fragment.files {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+        shader.sources = Some(Rc::new(ShaderSources {
+            vertex_path: vertex_path.to_owned(),
+            fragment_path: fragment_path.to_owned(),
+            defines: defines.iter().map(|d| d.to_string()).collect(),
+            files,
+        }));
 
-        Ok(Shader::new(
-            &vertex_source.to_string_lossy(),
-            &fragment_source.to_string_lossy(),
-        ))
+        Ok(shader)
+    }
+
+    /// Builds a fresh program from the files this shader was loaded from.
+    /// The current program is left untouched, so callers can keep using it if this fails
+    pub fn rebuild(&self) -> Result<Shader, Box<dyn std::error::Error>> {
+        let mut shader = match &self.sources {
+            Some(sources) => {
+                let defines: Vec<&str> = sources.defines.iter().map(String::as_str).collect();
+                Shader::from_files(&sources.vertex_path, &sources.fragment_path, &defines)?
+            }
+            None => return Err("Shader was not loaded from files".into()),
+        };
+        shader.error_on_no_uniform_found = self.error_on_no_uniform_found;
+        Ok(shader)
     }
 
     pub fn use_program(&self) {
-        unsafe {
-            gl::UseProgram(self.program);
-        }
+        render_stats::use_program(self.program);
     }
 
-    pub fn set_mat4(&self, name: &str, mat: &glm::Mat4) -> Result<(), String> {
-        let location = get_uniform_location(self.program, name);
-        if location == -1 {
-            if ERROR_ON_NO_UNIFORM_FOUND {
+    /// Cached location of a uniform, or -1 if the program has no such active uniform
+    #[allow(dead_code)]
+    pub fn uniform_location(&self, name: &str) -> GLint {
+        self.uniforms.get(name).map_or(-1, |u| u.location)
+    }
+
+    /// Looks up a uniform and checks that it can hold `count` values of one of `accepted` types.
+    /// `Ok(None)` means the uniform doesn't exist and missing uniforms are being ignored
+    fn uniform(
+        &self,
+        name: &str,
+        accepted: &[GLenum],
+        count: usize,
+    ) -> Result<Option<GLint>, String> {
+        let Some(uniform) = self.uniforms.get(name) else {
+            if self.error_on_no_uniform_found {
                 return Err(format!("Uniform '{}' not found in shader program", name));
-            } else {
-                return Ok(());
             }
+            return Ok(None);
+        };
+
+        if !accepted.contains(&uniform.kind) {
+            return Err(format!(
+                "Uniform '{}' is a {}, not a {}",
+                name,
+                gl_type_name(uniform.kind),
+                gl_type_name(accepted[0])
+            ));
+        }
+        if count > uniform.size as usize {
+            return Err(format!(
+                "Uniform '{}' holds {} values, tried to set {}",
+                name, uniform.size, count
+            ));
         }
 
-        unsafe {
-            gl::UniformMatrix4fv(location, 1, gl::FALSE, mat.as_ptr());
+        Ok(Some(uniform.location))
+    }
+
+    pub fn set_float(&self, name: &str, v: f32) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, &[gl::FLOAT], 1)? {
+            unsafe { gl::Uniform1f(location, v) };
+        }
+        Ok(())
+    }
+
+    pub fn set_vec2(&self, name: &str, vec: &glm::Vec2) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, &[gl::FLOAT_VEC2], 1)? {
+            unsafe { gl::Uniform2fv(location, 1, vec.as_ptr()) };
         }
         Ok(())
     }
 
     pub fn set_vec3(&self, name: &str, vec: &glm::Vec3) -> Result<(), String> {
-        let location = get_uniform_location(self.program, name);
-        if location == -1 {
-            if ERROR_ON_NO_UNIFORM_FOUND {
-                return Err(format!("Uniform '{}' not found in shader program", name));
-            } else {
-                return Ok(());
-            }
+        if let Some(location) = self.uniform(name, &[gl::FLOAT_VEC3], 1)? {
+            unsafe { gl::Uniform3fv(location, 1, vec.as_ptr()) };
+        }
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn set_vec4(&self, name: &str, vec: &glm::Vec4) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, &[gl::FLOAT_VEC4], 1)? {
+            unsafe { gl::Uniform4fv(location, 1, vec.as_ptr()) };
         }
+        Ok(())
+    }
 
-        unsafe {
-            gl::Uniform3fv(location, 1, vec.as_ptr());
+    #[allow(dead_code)]
+    pub fn set_mat3(&self, name: &str, mat: &glm::Mat3) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, &[gl::FLOAT_MAT3], 1)? {
+            unsafe { gl::UniformMatrix3fv(location, 1, gl::FALSE, mat.as_ptr()) };
+        }
+        Ok(())
+    }
+
+    pub fn set_mat4(&self, name: &str, mat: &glm::Mat4) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, &[gl::FLOAT_MAT4], 1)? {
+            unsafe { gl::UniformMatrix4fv(location, 1, gl::FALSE, mat.as_ptr()) };
         }
         Ok(())
     }
 
+    /// Sets an int uniform, or the texture unit of a sampler
     pub fn set_int(&self, name: &str, v: i32) -> Result<(), String> {
-        let location = get_uniform_location(self.program, name);
-        if location == -1 {
-            if ERROR_ON_NO_UNIFORM_FOUND {
-                return Err(format!("Uniform '{}' not found in shader program", name));
-            } else {
-                return Ok(());
-            }
+        if let Some(location) = self.uniform(name, INT_LIKE_TYPES, 1)? {
+            unsafe { gl::Uniform1i(location, v) };
+        }
+        Ok(())
+    }
+
+    pub fn set_bool(&self, name: &str, v: bool) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, &[gl::BOOL], 1)? {
+            unsafe { gl::Uniform1i(location, v as i32) };
         }
+        Ok(())
+    }
 
-        unsafe {
-            gl::Uniform1i(location, v);
+    pub fn set_float_array(&self, name: &str, values: &[f32]) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, &[gl::FLOAT], values.len())? {
+            unsafe { gl::Uniform1fv(location, values.len() as i32, values.as_ptr()) };
+        }
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn set_int_array(&self, name: &str, values: &[i32]) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, INT_LIKE_TYPES, values.len())? {
+            unsafe { gl::Uniform1iv(location, values.len() as i32, values.as_ptr()) };
+        }
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn set_vec2_array(&self, name: &str, values: &[glm::Vec2]) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, &[gl::FLOAT_VEC2], values.len())? {
+            unsafe { gl::Uniform2fv(location, values.len() as i32, values.as_ptr() as *const f32) };
+        }
+        Ok(())
+    }
+
+    pub fn set_vec3_array(&self, name: &str, values: &[glm::Vec3]) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, &[gl::FLOAT_VEC3], values.len())? {
+            unsafe { gl::Uniform3fv(location, values.len() as i32, values.as_ptr() as *const f32) };
+        }
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn set_vec4_array(&self, name: &str, values: &[glm::Vec4]) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, &[gl::FLOAT_VEC4], values.len())? {
+            unsafe { gl::Uniform4fv(location, values.len() as i32, values.as_ptr() as *const f32) };
+        }
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn set_mat4_array(&self, name: &str, values: &[glm::Mat4]) -> Result<(), String> {
+        if let Some(location) = self.uniform(name, &[gl::FLOAT_MAT4], values.len())? {
+            unsafe {
+                gl::UniformMatrix4fv(
+                    location,
+                    values.len() as i32,
+                    gl::FALSE,
+                    values.as_ptr() as *const f32,
+                )
+            };
         }
         Ok(())
     }
 }
+/// Compiled programs keyed by their source files and defines, so each variant is built once
+pub struct ShaderCache {
+    shaders: HashMap<(String, String, Vec<String>), Shader>,
+}
+
+impl ShaderCache {
+    pub fn new() -> Self {
+        ShaderCache {
+            shaders: HashMap::new(),
+        }
+    }
+
+    /// Returns the cached variant, compiling it first if this is the first request for it
+    pub fn get(
+        &mut self,
+        vertex_path: &str,
+        fragment_path: &str,
+        defines: &[&str],
+    ) -> Result<Shader, Box<dyn std::error::Error>> {
+        let mut sorted_defines: Vec<String> = defines.iter().map(|d| d.to_string()).collect();
+        sorted_defines.sort();
+        let key = (
+            vertex_path.to_owned(),
+            fragment_path.to_owned(),
+            sorted_defines,
+        );
+
+        if let Some(shader) = self.shaders.get(&key) {
+            return Ok(shader.clone());
+        }
+
+        let shader = Shader::from_files(vertex_path, fragment_path, defines)?;
+        debug!(
+            "Cached program #{} for {} + {} {:?}",
+            shader.program, vertex_path, fragment_path, key.2
+        );
+        self.shaders.insert(key, shader.clone());
+        Ok(shader)
+    }
+
+    /// Swaps a reloaded program in for `old_program`
+    pub fn replace_shader(&mut self, old_program: GLuint, shader: &Shader) {
+        for cached in self.shaders.values_mut() {
+            if cached.program == old_program {
+                *cached = shader.clone();
+            }
+        }
+    }
+
+    pub fn shaders(&self) -> impl Iterator<Item = &Shader> {
+        self.shaders.values()
+    }
+}
+
 pub struct VertexArrayObject {
     pub id: GLuint,
 }
@@ -161,27 +580,70 @@ impl VertexArrayObject {
         VertexArrayObject { id }
     }
 
-    pub fn bind(&self) {
+    /// Uploads interleaved `vertices` and `indices` into a new VAO.
+    /// `attributes` lists `(location, float count)` for each attribute in the order they're interleaved
+    pub fn from_interleaved(
+        vertices: &[f32],
+        indices: &[u32],
+        attributes: &[(GLuint, i32)],
+    ) -> Self {
+        let vao = VertexArrayObject::new();
+        let (mut vbo, mut ebo) = (0, 0);
+        let floats_per_vertex: i32 = attributes.iter().map(|(_, count)| count).sum();
+        let stride = floats_per_vertex * std::mem::size_of::<f32>() as i32;
+
         unsafe {
-            gl::BindVertexArray(self.id);
+            gl::GenBuffers(1, &mut vbo);
+            gl::GenBuffers(1, &mut ebo);
+
+            render_stats::bind_vertex_array(vao.id);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(vertices) as _,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+            gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                std::mem::size_of_val(indices) as _,
+                indices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+
+            let mut offset = 0;
+            for &(location, count) in attributes {
+                gl::VertexAttribPointer(
+                    location,
+                    count,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    stride,
+                    (offset * std::mem::size_of::<f32>()) as *const _,
+                );
+                gl::EnableVertexAttribArray(location);
+                offset += count as usize;
+            }
+
+            render_stats::bind_vertex_array(0);
         }
+        render_stats::track_buffer_memory(
+            (std::mem::size_of_val(vertices) + std::mem::size_of_val(indices)) as isize,
+        );
+
+        vao
     }
 
-    pub fn unbind(&self) {
-        unsafe {
-            gl::BindVertexArray(0);
-        }
+    #[allow(dead_code)]
+    pub fn bind(&self) {
+        render_stats::bind_vertex_array(self.id);
     }
-}
 
-impl Clone for Shader {
-    fn clone(&self) -> Self {
-        Self { program: self.program.clone() }
+    #[allow(dead_code)]
+    pub fn unbind(&self) {
+        render_stats::bind_vertex_array(0);
     }
 }
-impl Copy for Shader {}
-impl std::fmt::Debug for Shader {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Shader").field("program", &self.program).finish()
-    }
-}
\ No newline at end of file
@@ -0,0 +1,351 @@
+use gl::types::{GLint, GLuint};
+use log::debug;
+use nalgebra_glm as glm;
+
+use crate::ecs::ecs::{self as ECS, World};
+use crate::ecs::render;
+use crate::graphics::camera::Camera3d;
+use crate::graphics::render_stats;
+use crate::graphics::shader::{Shader, ShaderCache};
+use crate::graphics::uniform_buffer::{
+    SHADOW_BLOCK_BINDING, SHADOW_CASCADES, ShadowBlock, UniformBuffer,
+};
+
+pub const SUN_SHADOW_TEXTURE_UNIT: u32 = 3;
+pub const POINT_SHADOW_TEXTURE_UNIT: u32 = 4;
+
+pub const SHADOW_DEPTH_SHADER: (&str, &str) = (
+    "assets/shaders/shadow_depth.vert",
+    "assets/shaders/shadow_depth.frag",
+);
+const POINT_SHADOW_DEFINES: &[&str] = &["POINT_SHADOW"];
+
+/// Sun shadows stop this far from the camera, even if the camera sees further
+const SUN_SHADOW_DISTANCE: f32 = 60.0;
+/// Blend between uniform (0) and logarithmic (1) cascade splits
+const CASCADE_SPLIT_LAMBDA: f32 = 0.75;
+/// How far behind each cascade casters are still picked up
+const SUN_CASTER_PADDING: f32 = 50.0;
+/// Nothing further from a point light than this is shadowed by it
+pub const POINT_SHADOW_FAR: f32 = 50.0;
+const POINT_SHADOW_NEAR: f32 = 0.1;
+
+/// Target and up vector of each cube map face, in `GL_TEXTURE_CUBE_MAP_POSITIVE_X` order
+const CUBE_FACES: [(glm::Vec3, glm::Vec3); 6] = [
+    (glm::Vec3::new(1., 0., 0.), glm::Vec3::new(0., -1., 0.)),
+    (glm::Vec3::new(-1., 0., 0.), glm::Vec3::new(0., -1., 0.)),
+    (glm::Vec3::new(0., 1., 0.), glm::Vec3::new(0., 0., 1.)),
+    (glm::Vec3::new(0., -1., 0.), glm::Vec3::new(0., 0., -1.)),
+    (glm::Vec3::new(0., 0., 1.), glm::Vec3::new(0., -1., 0.)),
+    (glm::Vec3::new(0., 0., -1.), glm::Vec3::new(0., -1., 0.)),
+];
+
+/// Depth maps for the sun (one layer per cascade) and the point light (a cube map),
+/// re-rendered every frame from the lights `render::sun_light` and `render::point_light` pick
+pub struct ShadowMaps {
+    pub sun_size: i32,
+    pub point_size: i32,
+    sun_texture: GLuint,
+    point_texture: GLuint,
+    fbo: GLuint,
+    ubo: UniformBuffer<ShadowBlock>,
+}
+
+impl ShadowMaps {
+    pub fn new(sun_size: i32, point_size: i32) -> Self {
+        let (mut sun_texture, mut point_texture, mut fbo) = (0, 0, 0);
+        unsafe {
+            gl::GenTextures(1, &mut sun_texture);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, sun_texture);
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                gl::DEPTH_COMPONENT24 as GLint,
+                sun_size,
+                sun_size,
+                SHADOW_CASCADES as GLint,
+                0,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                std::ptr::null(),
+            );
+            // Hardware depth comparison, which also gives 2x2 PCF for free with linear filtering
+            let params = [
+                (gl::TEXTURE_MIN_FILTER, gl::LINEAR),
+                (gl::TEXTURE_MAG_FILTER, gl::LINEAR),
+                (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER),
+                (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER),
+                (gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE),
+                (gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL),
+            ];
+            for (name, value) in params {
+                gl::TexParameteri(gl::TEXTURE_2D_ARRAY, name, value as GLint);
+            }
+            // Outside the map counts as lit
+            let border = [1.0f32; 4];
+            gl::TexParameterfv(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_BORDER_COLOR,
+                border.as_ptr(),
+            );
+
+            gl::GenTextures(1, &mut point_texture);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, point_texture);
+            for face in 0..6 {
+                gl::TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                    0,
+                    gl::DEPTH_COMPONENT24 as GLint,
+                    point_size,
+                    point_size,
+                    0,
+                    gl::DEPTH_COMPONENT,
+                    gl::FLOAT,
+                    std::ptr::null(),
+                );
+            }
+            let params = [
+                (gl::TEXTURE_MIN_FILTER, gl::NEAREST),
+                (gl::TEXTURE_MAG_FILTER, gl::NEAREST),
+                (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE),
+                (gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE),
+            ];
+            for (name, value) in params {
+                gl::TexParameteri(gl::TEXTURE_CUBE_MAP, name, value as GLint);
+            }
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        // 24 bit depth is padded to 32
+        render_stats::track_texture_memory(
+            (sun_size * sun_size * SHADOW_CASCADES as i32 + point_size * point_size * 6) as isize
+                * 4,
+        );
+        debug!(
+            "Created shadow maps: sun #{} ({} cascades at {}px), point #{} ({}px)",
+            sun_texture, SHADOW_CASCADES, sun_size, point_texture, point_size
+        );
+
+        ShadowMaps {
+            sun_size,
+            point_size,
+            sun_texture,
+            point_texture,
+            fbo,
+            ubo: UniformBuffer::new(SHADOW_BLOCK_BINDING),
+        }
+    }
+
+    /// Binds both maps to their texture units for the main pass
+    pub fn bind(&self) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + SUN_SHADOW_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.sun_texture);
+            gl::ActiveTexture(gl::TEXTURE0 + POINT_SHADOW_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.point_texture);
+        }
+    }
+
+    /// Renders every shadow caster into the maps of each light that has shadows turned on,
+    /// and updates `ShadowBlock`. Restores the viewport and default framebuffer afterwards
+    pub fn render(
+        &self,
+        world: &World,
+        camera: &Camera3d,
+        shader_cache: &mut ShaderCache,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut block = ShadowBlock {
+            sun_matrices: [glm::identity(); SHADOW_CASCADES],
+            cascade_splits: glm::vec4(0., 0., 0., 0.),
+            params: glm::vec4(0., 0., POINT_SHADOW_FAR, 0.),
+        };
+
+        let casters: Vec<usize> = world
+            .part_render_data
+            .keys()
+            .copied()
+            .filter(|&entity| {
+                matches!(world.entity_types.get(&entity), Some(ECS::EntityType::Part))
+                    && render::casts_shadow(world, entity)
+                    // Translucent surfaces let light through
+                    && render::opacity(world, entity) >= 1.0
+            })
+            .collect();
+
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+        }
+
+        if let Some((direction, light)) = render::sun_light(world)
+            && light.cast_shadows
+        {
+            let shader = shader_cache.get(SHADOW_DEPTH_SHADER.0, SHADOW_DEPTH_SHADER.1, &[])?;
+            let splits = cascade_splits(camera);
+            unsafe { gl::Viewport(0, 0, self.sun_size, self.sun_size) };
+
+            let mut near = camera.near;
+            for (cascade, &far) in splits.iter().enumerate() {
+                let matrix = cascade_matrix(camera, near, far, &direction, self.sun_size);
+                unsafe {
+                    gl::FramebufferTextureLayer(
+                        gl::FRAMEBUFFER,
+                        gl::DEPTH_ATTACHMENT,
+                        self.sun_texture,
+                        0,
+                        cascade as GLint,
+                    );
+                    gl::Clear(gl::DEPTH_BUFFER_BIT);
+                }
+                draw_casters(world, &casters, &shader, &matrix)?;
+
+                block.sun_matrices[cascade] = matrix;
+                block.cascade_splits[cascade] = far;
+                near = far;
+            }
+            block.params.x = 1.0;
+        }
+
+        if let Some((position, light)) = render::point_light(world)
+            && light.cast_shadows
+        {
+            let shader = shader_cache.get(
+                SHADOW_DEPTH_SHADER.0,
+                SHADOW_DEPTH_SHADER.1,
+                POINT_SHADOW_DEFINES,
+            )?;
+            shader.use_program();
+            shader.set_vec3("uLightPos", &position)?;
+            shader.set_float("uFarPlane", POINT_SHADOW_FAR)?;
+            unsafe { gl::Viewport(0, 0, self.point_size, self.point_size) };
+
+            let projection =
+                glm::perspective(1.0, 90f32.to_radians(), POINT_SHADOW_NEAR, POINT_SHADOW_FAR);
+            for (face, (target, up)) in CUBE_FACES.iter().enumerate() {
+                let matrix = projection * glm::look_at(&position, &(position + target), up);
+                unsafe {
+                    gl::FramebufferTexture2D(
+                        gl::FRAMEBUFFER,
+                        gl::DEPTH_ATTACHMENT,
+                        gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as GLuint,
+                        self.point_texture,
+                        0,
+                    );
+                    gl::Clear(gl::DEPTH_BUFFER_BIT);
+                }
+                draw_casters(world, &casters, &shader, &matrix)?;
+            }
+            block.params.y = 1.0;
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+        self.ubo.update(&block);
+
+        Ok(())
+    }
+}
+
+/// View-space distance where each cascade ends, mixing uniform and logarithmic splits so
+/// near cascades stay sharp without the far ones getting too stretched
+fn cascade_splits(camera: &Camera3d) -> [f32; SHADOW_CASCADES] {
+    let near = camera.near;
+    let far = camera.far.min(SUN_SHADOW_DISTANCE);
+    let mut splits = [0.0; SHADOW_CASCADES];
+    for (i, split) in splits.iter_mut().enumerate() {
+        let p = (i + 1) as f32 / SHADOW_CASCADES as f32;
+        let log = near * (far / near).powf(p);
+        let uniform = near + (far - near) * p;
+        *split = CASCADE_SPLIT_LAMBDA * log + (1.0 - CASCADE_SPLIT_LAMBDA) * uniform;
+    }
+    splits
+}
+
+/// Light-space matrix covering the slice of the camera frustum between `near` and `far`.
+/// The slice is fitted with a sphere and snapped to whole texels, so the cascade doesn't
+/// change size when the camera turns or shimmer when it moves
+fn cascade_matrix(
+    camera: &Camera3d,
+    near: f32,
+    far: f32,
+    direction: &glm::Vec3,
+    size: i32,
+) -> glm::Mat4 {
+    let projection = glm::perspective(camera.aspect_ratio, camera.zoom.to_radians(), near, far);
+    let inverse = glm::inverse(&(projection * camera.get_view_matrix()));
+
+    let mut corners = Vec::with_capacity(8);
+    for x in [-1., 1.] {
+        for y in [-1., 1.] {
+            for z in [-1., 1.] {
+                let corner = inverse * glm::vec4(x, y, z, 1.);
+                corners.push(corner.xyz() / corner.w);
+            }
+        }
+    }
+    let center = corners.iter().sum::<glm::Vec3>() / corners.len() as f32;
+    let radius = corners
+        .iter()
+        .map(|corner| glm::distance(corner, &center))
+        .fold(0.0, f32::max)
+        .ceil();
+
+    let up = if direction.y.abs() > 0.99 {
+        glm::vec3(0., 0., 1.)
+    } else {
+        glm::vec3(0., 1., 0.)
+    };
+    let view = glm::look_at(&(center - direction * radius), &center, &up);
+    let mut projection = glm::ortho(
+        -radius,
+        radius,
+        -radius,
+        radius,
+        -SUN_CASTER_PADDING,
+        radius * 2.0,
+    );
+
+    // Snap the world origin to a texel, which moves every other point by whole texels too
+    let half_size = size as f32 / 2.0;
+    let origin = projection * view * glm::vec4(0., 0., 0., 1.) * half_size;
+    let offset = (glm::round(&origin.xy()) - origin.xy()) / half_size;
+    projection[(0, 3)] += offset.x;
+    projection[(1, 3)] += offset.y;
+
+    projection * view
+}
+
+fn draw_casters(
+    world: &World,
+    casters: &[usize],
+    shader: &Shader,
+    light_matrix: &glm::Mat4,
+) -> Result<(), String> {
+    shader.use_program();
+    shader.set_mat4("uLightMatrix", light_matrix)?;
+    for &entity in casters {
+        let render_data = world.part_render_data.get(&entity).unwrap();
+        shader.set_mat4("model", &render::model_matrix(world, entity))?;
+        unsafe {
+            render_stats::bind_vertex_array(render_data.vao_id);
+            gl::DrawElements(
+                gl::TRIANGLES,
+                render_data.index_count,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+        render_stats::record_draw(gl::TRIANGLES, render_data.index_count);
+    }
+    render_stats::bind_vertex_array(0);
+    Ok(())
+}
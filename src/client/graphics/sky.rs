@@ -0,0 +1,136 @@
+use gl::types::GLuint;
+use nalgebra_glm as glm;
+
+use crate::graphics::render_stats;
+use crate::graphics::shader::ShaderCache;
+use crate::graphics::texture::{Cubemap, load_cubemap_from_files};
+
+/// Must match the comment on `uSkybox` in `sky.frag`
+pub const SKYBOX_TEXTURE_UNIT: u32 = 5;
+
+pub const SKY_SHADER: (&str, &str) = ("assets/shaders/sky.vert", "assets/shaders/sky.frag");
+
+/// The dusk skybox in assets, in the order `Sky::cubemap` takes
+pub const SKYBOX_FACES: [&str; 6] = [
+    "assets/skybox/px.png",
+    "assets/skybox/nx.png",
+    "assets/skybox/py.png",
+    "assets/skybox/ny.png",
+    "assets/skybox/pz.png",
+    "assets/skybox/nz.png",
+];
+
+/// A sky blended from the horizon up to the zenith and down to the ground, with a disk
+/// drawn where the sun is. Colors are linear
+#[derive(Debug, Clone, Copy)]
+pub struct ProceduralSky {
+    pub zenith_color: glm::Vec3,
+    pub horizon_color: glm::Vec3,
+    pub ground_color: glm::Vec3,
+    /// Angular radius of the sun disk, in degrees
+    pub sun_size: f32,
+}
+
+impl Default for ProceduralSky {
+    fn default() -> Self {
+        ProceduralSky {
+            zenith_color: glm::vec3(0.15, 0.35, 0.8),
+            horizon_color: glm::vec3(0.7, 0.8, 0.95),
+            ground_color: glm::vec3(0.25, 0.22, 0.2),
+            sun_size: 1.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SkyMode {
+    Procedural(ProceduralSky),
+    Cubemap(Cubemap),
+}
+
+/// What's drawn behind the scene, and the ambient light it gives off.
+/// The sun disk follows the scene's directional light
+pub struct Sky {
+    pub mode: SkyMode,
+    pub ambient_color: glm::Vec3,
+    pub ambient_intensity: f32,
+    /// Attribute-less VAO for the fullscreen triangle, created on the first draw
+    vao: GLuint,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky::procedural(ProceduralSky::default())
+    }
+}
+
+impl Sky {
+    pub fn procedural(sky: ProceduralSky) -> Self {
+        Sky {
+            mode: SkyMode::Procedural(sky),
+            ambient_color: sky.horizon_color,
+            ambient_intensity: 0.1,
+            vao: 0,
+        }
+    }
+
+    /// A skybox from six images, in +X, -X, +Y, -Y, +Z, -Z order
+    pub fn cubemap(faces: &[&str; 6]) -> Result<Self, String> {
+        Ok(Sky {
+            mode: SkyMode::Cubemap(load_cubemap_from_files(faces)?),
+            ambient_color: glm::vec3(1., 1., 1.),
+            ambient_intensity: 0.1,
+            vao: 0,
+        })
+    }
+
+    /// Ambient light as uploaded in `LightBlock`: rgb is the color, a the intensity
+    pub fn ambient(&self) -> glm::Vec4 {
+        glm::vec4(
+            self.ambient_color.x,
+            self.ambient_color.y,
+            self.ambient_color.z,
+            self.ambient_intensity,
+        )
+    }
+
+    /// Fills the screen with the sky. Meant to be drawn before the scene, it doesn't test
+    /// or write depth
+    pub fn render(
+        &mut self,
+        shader_cache: &mut ShaderCache,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let shader = shader_cache.get(SKY_SHADER.0, SKY_SHADER.1, &[])?;
+        shader.use_program();
+
+        match &self.mode {
+            SkyMode::Procedural(sky) => {
+                shader.set_bool("uProcedural", true)?;
+                shader.set_vec3("uZenithColor", &sky.zenith_color)?;
+                shader.set_vec3("uHorizonColor", &sky.horizon_color)?;
+                shader.set_vec3("uGroundColor", &sky.ground_color)?;
+                shader.set_float("uSunSize", sky.sun_size.to_radians())?;
+            }
+            SkyMode::Cubemap(cubemap) => {
+                shader.set_bool("uProcedural", false)?;
+                cubemap.bind(SKYBOX_TEXTURE_UNIT);
+            }
+        }
+
+        unsafe {
+            if self.vao == 0 {
+                gl::GenVertexArrays(1, &mut self.vao);
+            }
+            gl::Disable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::FALSE);
+            render_stats::bind_vertex_array(self.vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            render_stats::bind_vertex_array(0);
+            gl::DepthMask(gl::TRUE);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+        render_stats::record_draw(gl::TRIANGLES, 3);
+
+        Ok(())
+    }
+}
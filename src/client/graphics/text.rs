@@ -0,0 +1,262 @@
+use gl::types::GLuint;
+use nalgebra_glm as glm;
+
+use crate::ecs::ecs::World;
+use crate::graphics::camera::Camera3d;
+use crate::graphics::font::{DEFAULT_FONT, FONT_BASE_SIZE, Font, TextAlign};
+use crate::graphics::line::viewport_size;
+use crate::graphics::render_stats;
+use crate::graphics::shader::{Shader, ShaderCache};
+
+pub const TEXT_SHADER: (&str, &str) = ("assets/shaders/text.vert", "assets/shaders/text.frag");
+/// Must match the unit bound to `uFontAtlas` in `SAMPLER_UNITS`
+pub const FONT_ATLAS_TEXTURE_UNIT: u32 = 0;
+
+/// Position then atlas UV
+const TEXT_VERTEX_FLOATS: usize = 4;
+
+/// Where a `Text` is drawn. Values match `SPACE_*` in `text.vert`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextSpace {
+    /// A label `size` pixels tall, with its anchor this many pixels from the window's top left.
+    /// Drawn after post-processing so it stays crisp
+    Screen(glm::Vec2),
+    /// Faces the camera with its bottom this far above the entity's `Position`.
+    /// `size` is in world units, so it shrinks with distance
+    Billboard(glm::Vec3),
+    /// Like `Billboard`, but `size` is in pixels so it reads the same at any distance,
+    /// like a nametag
+    FixedBillboard(glm::Vec3),
+}
+
+impl TextSpace {
+    fn id(&self) -> i32 {
+        match self {
+            TextSpace::Screen(_) => 0,
+            TextSpace::Billboard(_) => 1,
+            TextSpace::FixedBillboard(_) => 2,
+        }
+    }
+}
+
+/// Text drawn for an entity. `size` is the font's em size, in pixels or world units
+/// depending on `space`, and `max_width` wraps lines in the same units
+#[derive(Debug, Clone)]
+pub struct Text {
+    pub content: String,
+    pub size: f32,
+    pub color: glm::Vec3,
+    pub align: TextAlign,
+    pub max_width: Option<f32>,
+    pub space: TextSpace,
+}
+
+impl Text {
+    pub fn new(content: &str, size: f32, space: TextSpace) -> Self {
+        Text {
+            content: content.to_owned(),
+            size,
+            color: glm::vec3(1., 1., 1.),
+            align: TextAlign::Left,
+            max_width: None,
+            space,
+        }
+    }
+
+    /// Centered over the entity, which is what billboards usually want
+    pub fn billboard(content: &str, size: f32, offset: glm::Vec3) -> Self {
+        Text::new(content, size, TextSpace::Billboard(offset)).with_align(TextAlign::Center)
+    }
+
+    pub fn with_color(mut self, color: glm::Vec3) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+}
+
+/// Draws every entity's `Text` with one shared font
+pub struct TextRenderer {
+    pub font: Font,
+    vao: GLuint,
+    vbo: GLuint,
+    vertices: Vec<f32>,
+}
+
+impl TextRenderer {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let font = Font::load(DEFAULT_FONT)?;
+
+        let (mut vao, mut vbo) = (0, 0);
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            render_stats::bind_vertex_array(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            let stride = (TEXT_VERTEX_FLOATS * std::mem::size_of::<f32>()) as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(
+                2,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (2 * std::mem::size_of::<f32>()) as *const _,
+            );
+            gl::EnableVertexAttribArray(2);
+            render_stats::bind_vertex_array(0);
+        }
+
+        Ok(TextRenderer {
+            font,
+            vao,
+            vbo,
+            vertices: Vec::new(),
+        })
+    }
+
+    /// Draws world-space text. Call it during the scene pass, so it's depth tested and
+    /// post-processed like everything else
+    pub fn render_billboards(
+        &mut self,
+        world: &World,
+        camera: &Camera3d,
+        shader_cache: &mut ShaderCache,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let shader = shader_cache.get(TEXT_SHADER.0, TEXT_SHADER.1, &[])?;
+        let view = camera.get_view_matrix();
+        self.begin(&shader)?;
+        unsafe { gl::DepthMask(gl::FALSE) };
+
+        for (entity, text) in &world.texts {
+            let offset = match text.space {
+                TextSpace::Billboard(offset) | TextSpace::FixedBillboard(offset) => offset,
+                TextSpace::Screen(_) => continue,
+            };
+            let Some(position) = world.positions.get(entity) else {
+                continue;
+            };
+            let anchor = position.0 + offset;
+            // Behind the camera, where fixed size text would flip onto the screen
+            if (view * glm::vec4(anchor.x, anchor.y, anchor.z, 1.)).z >= 0.0 {
+                continue;
+            }
+            shader.set_vec3("uAnchor", &anchor)?;
+            self.draw(text, &shader, true)?;
+        }
+
+        unsafe { gl::DepthMask(gl::TRUE) };
+        self.end();
+        Ok(())
+    }
+
+    /// Draws screen-space labels over everything. Call it after post-processing
+    pub fn render_screen(
+        &mut self,
+        world: &World,
+        shader_cache: &mut ShaderCache,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let shader = shader_cache.get(TEXT_SHADER.0, TEXT_SHADER.1, &[])?;
+        self.begin(&shader)?;
+        unsafe { gl::Disable(gl::DEPTH_TEST) };
+
+        for text in world.texts.values() {
+            if let TextSpace::Screen(position) = text.space {
+                shader.set_vec2("uScreenPos", &position)?;
+                self.draw(text, &shader, false)?;
+            }
+        }
+
+        unsafe { gl::Enable(gl::DEPTH_TEST) };
+        self.end();
+        Ok(())
+    }
+
+    fn begin(&self, shader: &Shader) -> Result<(), String> {
+        shader.use_program();
+        shader.set_vec2("uViewport", &viewport_size())?;
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + FONT_ATLAS_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, self.font.atlas);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            render_stats::bind_vertex_array(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+        }
+        render_stats::record_state_change();
+        Ok(())
+    }
+
+    fn end(&self) {
+        unsafe {
+            render_stats::bind_vertex_array(0);
+            gl::Disable(gl::BLEND);
+        }
+    }
+
+    /// Lays out and draws one text. Billboards sit on their anchor, everything else hangs
+    /// from it
+    fn draw(&mut self, text: &Text, shader: &Shader, billboard: bool) -> Result<(), String> {
+        let scale = text.size / FONT_BASE_SIZE;
+        let layout = self.font.layout(
+            &text.content,
+            text.align,
+            text.max_width.map(|width| width / scale),
+        );
+        if layout.quads.is_empty() {
+            return Ok(());
+        }
+        let lift = if billboard { layout.height } else { 0.0 };
+
+        self.vertices.clear();
+        for quad in &layout.quads {
+            let (min, max) = (
+                glm::vec2(quad.min.x, quad.min.y + lift),
+                glm::vec2(quad.max.x, quad.max.y + lift),
+            );
+            // Counter-clockwise, with the top of the glyph at the lower v
+            let corners = [
+                (min.x, min.y, quad.uv_min.x, quad.uv_max.y),
+                (max.x, min.y, quad.uv_max.x, quad.uv_max.y),
+                (max.x, max.y, quad.uv_max.x, quad.uv_min.y),
+                (min.x, max.y, quad.uv_min.x, quad.uv_min.y),
+            ];
+            for i in [0, 1, 2, 0, 2, 3] {
+                let (x, y, u, v) = corners[i];
+                self.vertices.extend_from_slice(&[x, y, u, v]);
+            }
+        }
+
+        shader.set_int("uTextSpace", text.space.id())?;
+        shader.set_float("uScale", scale)?;
+        shader.set_vec3("uColor", &text.color)?;
+        unsafe {
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(self.vertices.as_slice()) as isize,
+                self.vertices.as_ptr() as *const _,
+                gl::STREAM_DRAW,
+            );
+            gl::DrawArrays(
+                gl::TRIANGLES,
+                0,
+                (self.vertices.len() / TEXT_VERTEX_FLOATS) as i32,
+            );
+        }
+        render_stats::record_draw(
+            gl::TRIANGLES,
+            (self.vertices.len() / TEXT_VERTEX_FLOATS) as i32,
+        );
+        Ok(())
+    }
+}
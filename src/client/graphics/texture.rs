@@ -2,9 +2,14 @@ use gl::types::{GLint, GLuint};
 use image;
 use log::debug;
 
+use crate::graphics::render_stats;
+
+#[derive(Debug, Clone, Copy)]
 pub struct Texture {
     pub id: GLuint,
+    #[allow(dead_code)]
     pub width: i32,
+    #[allow(dead_code)]
     pub height: i32,
 }
 
@@ -14,20 +19,49 @@ impl Texture {
             gl::ActiveTexture(gl::TEXTURE0 + unit);
             gl::BindTexture(gl::TEXTURE_2D, self.id);
         }
+        render_stats::record_state_change();
     }
 }
 
-impl Clone for Texture {
-    fn clone(&self) -> Self {
-        Texture {
-            id: self.id,
-            width: self.width,
-            height: self.height,
+/// A `GL_TEXTURE_2D_ARRAY`, every layer the same size
+#[derive(Debug, Clone, Copy)]
+pub struct TextureArray {
+    pub id: GLuint,
+    #[allow(dead_code)]
+    pub width: i32,
+    #[allow(dead_code)]
+    pub height: i32,
+    #[allow(dead_code)]
+    pub layers: i32,
+}
+
+impl TextureArray {
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.id);
         }
+        render_stats::record_state_change();
     }
 }
 
-impl Copy for Texture {}
+/// A `GL_TEXTURE_CUBE_MAP`
+#[derive(Debug, Clone, Copy)]
+pub struct Cubemap {
+    pub id: GLuint,
+    #[allow(dead_code)]
+    pub size: i32,
+}
+
+impl Cubemap {
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+        }
+        render_stats::record_state_change();
+    }
+}
 
 pub struct TextureLoadOptions {
     pub generate_mipmaps: bool,
@@ -106,6 +140,7 @@ pub fn load_texture_from_file(
         return Err("Failed to generate texture ID".to_string());
     }
 
+    render_stats::track_texture_memory(rgba8_bytes(width, height, load_options.generate_mipmaps));
     debug!(
         "Loaded texture from {} ({}x{}), with ID #{}",
         path, width, height, texture_id
@@ -117,3 +152,251 @@ pub fn load_texture_from_file(
         height: height as i32,
     })
 }
+
+/// Loads each image as one layer of a texture array, in order. Layers take the size of the
+/// largest image and smaller ones are scaled up to match. Images are decoded in parallel
+pub fn load_texture_array_from_files(
+    paths: &[&str],
+    load_options: TextureLoadOptions,
+) -> Result<TextureArray, String> {
+    let images = std::thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .iter()
+            .map(|path| {
+                scope.spawn(move || {
+                    image::open(path)
+                        .map(|img| img.flipv().into_rgba8())
+                        .map_err(|e| format!("Failed to load texture {}: {}", path, e))
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Result<Vec<_>, String>>()
+    })?;
+
+    let width = images.iter().map(|img| img.width()).max().unwrap_or(1);
+    let height = images.iter().map(|img| img.height()).max().unwrap_or(1);
+
+    let mut texture_id: GLuint = 0;
+    unsafe {
+        gl::GenTextures(1, &mut texture_id);
+        gl::BindTexture(gl::TEXTURE_2D_ARRAY, texture_id);
+
+        gl::TexImage3D(
+            gl::TEXTURE_2D_ARRAY,
+            0,
+            gl::RGBA8 as GLint,
+            width as GLint,
+            height as GLint,
+            images.len() as GLint,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            std::ptr::null(),
+        );
+    }
+
+    for (layer, (img, path)) in images.into_iter().zip(paths).enumerate() {
+        let img = if img.dimensions() == (width, height) {
+            img
+        } else {
+            debug!(
+                "Scaling {} from {}x{} to {}x{}",
+                path,
+                img.width(),
+                img.height(),
+                width,
+                height
+            );
+            image::imageops::resize(&img, width, height, image::imageops::FilterType::Triangle)
+        };
+        unsafe {
+            gl::TexSubImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                0,
+                0,
+                layer as GLint,
+                width as GLint,
+                height as GLint,
+                1,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                img.as_raw().as_ptr() as *const _,
+            );
+        }
+    }
+
+    unsafe {
+        if load_options.generate_mipmaps {
+            gl::GenerateMipmap(gl::TEXTURE_2D_ARRAY);
+        }
+
+        gl::TexParameteri(
+            gl::TEXTURE_2D_ARRAY,
+            gl::TEXTURE_WRAP_S,
+            load_options.wrap_s,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D_ARRAY,
+            gl::TEXTURE_WRAP_T,
+            load_options.wrap_t,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D_ARRAY,
+            gl::TEXTURE_MIN_FILTER,
+            load_options.min_filter,
+        );
+        gl::TexParameteri(
+            gl::TEXTURE_2D_ARRAY,
+            gl::TEXTURE_MAG_FILTER,
+            load_options.mag_filter,
+        );
+
+        gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+    }
+
+    if texture_id == 0 {
+        return Err("Failed to generate texture ID".to_string());
+    }
+
+    render_stats::track_texture_memory(
+        rgba8_bytes(width, height, load_options.generate_mipmaps) * paths.len() as isize,
+    );
+    debug!(
+        "Loaded {} textures into array #{} ({}x{})",
+        paths.len(),
+        texture_id,
+        width,
+        height
+    );
+
+    Ok(TextureArray {
+        id: texture_id,
+        width: width as i32,
+        height: height as i32,
+        layers: paths.len() as i32,
+    })
+}
+
+/// Loads a cube map from six square images of the same size, in +X, -X, +Y, -Y, +Z, -Z order.
+/// Unlike 2D textures these aren't flipped, cube maps expect rows from the top down.
+/// Every face is read and checked before anything is created on the GPU, so a bad face
+/// doesn't leave a half-filled texture behind
+pub fn load_cubemap_from_files(paths: &[&str; 6]) -> Result<Cubemap, String> {
+    let mut faces: Vec<image::RgbaImage> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let img = image::open(path)
+            .map_err(|e| format!("Failed to load cube map face {}: {}", path, e))?
+            .into_rgba8();
+        let (width, height) = img.dimensions();
+        if width != height {
+            return Err(format!(
+                "Cube map face {} isn't square ({}x{})",
+                path, width, height
+            ));
+        }
+        if let Some(first) = faces.first()
+            && first.width() != width
+        {
+            return Err(format!(
+                "Cube map face {} is {}px, but {} is {}px",
+                path,
+                width,
+                paths[0],
+                first.width()
+            ));
+        }
+        faces.push(img);
+    }
+    let size = faces[0].width();
+
+    let mut texture_id: GLuint = 0;
+    unsafe {
+        gl::GenTextures(1, &mut texture_id);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, texture_id);
+        for (face, img) in faces.iter().enumerate() {
+            gl::TexImage2D(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as GLuint,
+                0,
+                gl::RGBA8 as GLint,
+                size as GLint,
+                size as GLint,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                img.as_raw().as_ptr() as *const _,
+            );
+        }
+
+        let params = [
+            (gl::TEXTURE_MIN_FILTER, gl::LINEAR),
+            (gl::TEXTURE_MAG_FILTER, gl::LINEAR),
+            (gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE),
+            (gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE),
+            (gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE),
+        ];
+        for (name, value) in params {
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, name, value as GLint);
+        }
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+    }
+
+    render_stats::track_texture_memory(rgba8_bytes(size, size, false) * 6);
+    debug!(
+        "Loaded cube map from {} ({}px faces), with ID #{}",
+        paths[0], size, texture_id
+    );
+
+    Ok(Cubemap {
+        id: texture_id,
+        size: size as i32,
+    })
+}
+
+/// GPU memory for an RGBA8 image, with a third more for its mipmaps
+fn rgba8_bytes(width: u32, height: u32, mipmaps: bool) -> isize {
+    let bytes = width as isize * height as isize * 4;
+    if mipmaps { bytes * 4 / 3 } else { bytes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a blank `width` x `height` face named after the test, returning its path
+    fn write_face(test: &str, name: &str, width: u32, height: u32) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("buat-cubemap-{}-{}", std::process::id(), test));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.png", name));
+        image::RgbaImage::new(width, height).save(&path).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    // Both fail before anything is created on the GPU, so they don't need a GL context
+
+    #[test]
+    fn cubemap_faces_must_be_square() {
+        let square = write_face("square", "square", 4, 4);
+        let wide = write_face("square", "wide", 8, 4);
+        let s = square.as_str();
+        let error = load_cubemap_from_files(&[s, s, wide.as_str(), s, s, s])
+            .err()
+            .unwrap();
+        assert!(error.contains("isn't square"), "{}", error);
+    }
+
+    #[test]
+    fn cubemap_faces_must_match() {
+        let small = write_face("match", "small", 4, 4);
+        let large = write_face("match", "large", 8, 8);
+        let s = small.as_str();
+        let error = load_cubemap_from_files(&[s, s, s, s, s, large.as_str()])
+            .err()
+            .unwrap();
+        assert!(error.contains("is 8px, but"), "{}", error);
+    }
+}
@@ -0,0 +1,111 @@
+use std::ffi::CString;
+use std::marker::PhantomData;
+
+use gl::types::GLuint;
+use log::debug;
+use nalgebra_glm as glm;
+
+use crate::graphics::render_stats;
+
+pub const CAMERA_BLOCK_BINDING: GLuint = 0;
+pub const LIGHT_BLOCK_BINDING: GLuint = 1;
+pub const SHADOW_BLOCK_BINDING: GLuint = 2;
+
+/// Must match `SHADOW_CASCADES` in `common/shadows.glsl`
+pub const SHADOW_CASCADES: usize = 3;
+
+/// Every shared uniform block and the binding point it lives on.
+/// GLSL 330 has no `layout(binding = N)`, so programs are pointed at these after linking
+pub const UNIFORM_BLOCKS: &[(&str, GLuint)] = &[
+    ("CameraBlock", CAMERA_BLOCK_BINDING),
+    ("LightBlock", LIGHT_BLOCK_BINDING),
+    ("ShadowBlock", SHADOW_BLOCK_BINDING),
+];
+
+/// std140 layout of `CameraBlock`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CameraBlock {
+    pub view: glm::Mat4,
+    pub projection: glm::Mat4,
+    pub view_pos: glm::Vec4, // vec3 padded to 16 bytes
+}
+
+/// std140 layout of `LightBlock`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LightBlock {
+    pub light_pos: glm::Vec4,     // vec3 padded to 16 bytes
+    pub light_color: glm::Vec4,   // vec3 padded to 16 bytes
+    pub sun_direction: glm::Vec4, // vec3 padded to 16 bytes
+    pub sun_color: glm::Vec4,     // vec3 padded to 16 bytes
+    pub ambient_light: glm::Vec4, // rgb: color, a: intensity
+}
+
+/// std140 layout of `ShadowBlock`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowBlock {
+    pub sun_matrices: [glm::Mat4; SHADOW_CASCADES],
+    pub cascade_splits: glm::Vec4,
+    /// x: sun shadows on, y: point shadows on, z: point shadow far plane
+    pub params: glm::Vec4,
+}
+
+/// A uniform buffer holding one `T`, bound to a fixed binding point and shared by all programs
+pub struct UniformBuffer<T> {
+    pub id: GLuint,
+    #[allow(dead_code)]
+    pub binding: GLuint,
+    _block: PhantomData<T>,
+}
+
+impl<T> UniformBuffer<T> {
+    pub fn new(binding: GLuint) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut id);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, id);
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                std::mem::size_of::<T>() as _,
+                std::ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, id);
+        }
+        render_stats::track_buffer_memory(std::mem::size_of::<T>() as isize);
+        debug!("Created UBO #{} on binding {}", id, binding);
+
+        UniformBuffer {
+            id,
+            binding,
+            _block: PhantomData,
+        }
+    }
+
+    pub fn update(&self, data: &T) {
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.id);
+            gl::BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0,
+                std::mem::size_of::<T>() as _,
+                data as *const T as *const _,
+            );
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+    }
+}
+
+/// Connects each shared block a program declares to its binding point
+pub fn bind_uniform_blocks(program: GLuint) {
+    for (name, binding) in UNIFORM_BLOCKS {
+        let c_name = CString::new(*name).unwrap();
+        let index = unsafe { gl::GetUniformBlockIndex(program, c_name.as_ptr()) };
+        if index != gl::INVALID_INDEX {
+            unsafe { gl::UniformBlockBinding(program, index, *binding) };
+        }
+    }
+}
@@ -14,10 +14,7 @@ fn check_hyprland() -> bool {
     let out_p: String = String::from_utf8(out.stdout).unwrap();
     debug!("{}", out_p.as_str());
     let re = Regex::new(r"hyprland").unwrap();
-    if re.is_match(out_p.as_str()) {
-        return true;
-    }
-    return false;
+    re.is_match(out_p.as_str())
 }
 
 pub struct GameWindow {
@@ -38,7 +35,7 @@ impl GameWindow {
     pub fn new(hints: GameWindowHints) -> Result<Self, String> {
         debug!("{}", check_hyprland());
         debug!(
-            "Creating GameWindow with hints:\n\tContext Version: {}.{}\n\tProfile: {}\n\tTitle: <{}>\n\tFullscreen?: {}\n\tSize: {}x{}",
+            "Creating GameWindow with hints:\n\tContext Version: {}.{}\n\tProfile: {}\n\tTitle: <{}>\n\tFullscreen?: {}\n\tSize: {}x{}\n\tHeadless?: {}",
             hints.gl_context.0,
             hints.gl_context.1,
             match hints.profile {
@@ -49,8 +46,18 @@ impl GameWindow {
             hints.title,
             hints.fullscreen,
             hints.size.0,
-            hints.size.1
+            hints.size.1,
+            hints.headless
         );
+        // Without a display server there's nothing to open even a hidden window on, so
+        // fall back to GLFW's null platform with an OSMesa (llvmpipe) context
+        let offscreen = hints.headless
+            && std::env::var_os("DISPLAY").is_none()
+            && std::env::var_os("WAYLAND_DISPLAY").is_none();
+        if offscreen {
+            glfw::init_hint(glfw::InitHint::Platform(glfw::Platform::Null));
+            debug!("No display found, using the null platform");
+        }
         let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
         debug!("Initialized GLFW");
         glfw.window_hint(glfw::WindowHint::ContextVersion(
@@ -71,6 +78,15 @@ impl GameWindow {
             }
         );
         glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
+        if hints.headless {
+            glfw.window_hint(glfw::WindowHint::Visible(false));
+            debug!("Hiding window for headless rendering");
+        }
+        if offscreen {
+            glfw.window_hint(glfw::WindowHint::ContextCreationApi(
+                glfw::ContextCreationApi::OsMesa,
+            ));
+        }
 
         let (mut p, ev) = glfw
             .create_window(
@@ -93,9 +109,9 @@ impl GameWindow {
             pos: (0, 0),
             fullscreen: hints.fullscreen,
             dim: hints.size,
-            glfw: glfw,
+            glfw,
             win: p,
-            ev: ev,
+            ev,
             dt: 0.,
             last_frame: 0.,
         })
@@ -117,7 +133,7 @@ impl GameWindow {
         self.fullscreen = !self.fullscreen;
         if self.fullscreen {
             self.pos = self.win.get_pos();
-            self.dim = (self.win.get_size().0 as i32, self.win.get_size().1 as i32);
+            self.dim = self.win.get_size();
 
             self.glfw.with_primary_monitor(|_, monitor| {
                 if let Some(monitor) = monitor {
@@ -159,4 +175,6 @@ pub struct GameWindowHints<'a> {
     pub title: &'a str,
     pub fullscreen: bool,
     pub size: (i32, i32),
+    /// Never show the window. Rendering still happens, meant for screenshots without a display
+    pub headless: bool,
 }
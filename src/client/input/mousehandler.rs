@@ -3,17 +3,15 @@ pub struct MouseHandler {
     last_y: f32,
     first_mouse: bool,
     pub locked: bool,
-    sy: f32,
 }
 
 impl MouseHandler {
     pub fn new(width: f32, height: f32) -> MouseHandler {
         Self {
-            last_x: width as f32 / 2.0,
-            last_y: height as f32 / 2.0,
+            last_x: width / 2.0,
+            last_y: height / 2.0,
             first_mouse: true,
             locked: true,
-            sy: 0.,
         }
     }
 
@@ -1,7 +1,7 @@
-use gl;
+use std::path::PathBuf;
+
 use glfw::{Action, Context, Key};
-use log::debug;
-use log4rs;
+use log::{debug, error};
 use mini_redis::client;
 use nalgebra_glm::{self as glm, Vec3};
 
@@ -14,17 +14,54 @@ mod util;
 use crate::{
     ecs::{
         ecs::{self as ECS, Position},
-        funcs::{add_render_data_to_world, spawn_line, spawn_part},
+        funcs::{
+            PART_SHADER, PART_TEX_SHADER, add_render_data_to_world, spawn_line, spawn_part,
+            spawn_polyline,
+        },
+        picking, render,
     },
     graphics::{
         camera::{self, Camera3d},
-        shader, texture,
+        debug_ui::DebugUi,
+        debug_view::{self, DebugView},
+        font::TextAlign,
+        gradient::{Gradient, GradientAxis, GradientInterpolation},
+        hot_reload::ShaderWatcher,
+        id_buffer::IdBuffer,
+        line::{LineCap, LineStyle},
+        particles::{self, Curve, ParticleBlend, ParticleEmitter, ParticleRenderer},
+        post::PostProcessor,
+        screenshot,
+        shader::ShaderCache,
+        shadow::ShadowMaps,
+        sky::{SKYBOX_FACES, Sky},
+        text::{Text, TextRenderer, TextSpace},
+        texture::load_texture_from_file,
+        uniform_buffer::{
+            CAMERA_BLOCK_BINDING, CameraBlock, LIGHT_BLOCK_BINDING, LightBlock, UniformBuffer,
+        },
         windowing::{self, GameWindow, GameWindowHints},
     },
     input::mousehandler::MouseHandler,
-    object::{mesh::obj_loader::load_obj_to_render_data, part::Part},
+    object::{
+        mesh::obj_loader::load_obj_to_render_data,
+        part::{
+            material::{PartMaterial, PartMaterialLibrary},
+            shape::PartShape,
+        },
+    },
 };
 
+/// Frames rendered in headless mode before the screenshot is taken, giving anything that
+/// lags a frame behind (resizes, the light following the camera) time to settle
+const HEADLESS_FRAMES: u32 = 3;
+/// Fixed time step in headless mode, so the same scene always renders the same image
+const HEADLESS_FRAME_TIME: f32 = 1.0 / 60.0;
+/// How far away clicks can pick entities
+const PICK_DISTANCE: f32 = 100.0;
+/// How long picking rays and hit markers stay on screen, in seconds
+const PICK_MARKER_TIME: f32 = 10.0;
+
 // ======================== Server Connection ========================
 async fn preconnect(uri: &str) {
     match client::connect(uri).await {
@@ -39,6 +76,44 @@ async fn preconnect(uri: &str) {
     }
 }
 
+// ============================ Command Line =========================
+const USAGE: &str = "Usage: client <id> [server address] [--camera x,y,z,yaw,pitch] [--sky <sky>]
+       client --headless <file.png> [--camera x,y,z,yaw,pitch] [--sky <sky>]
+<sky> is procedural (the default) or cubemap";
+
+/// Prints `message` and the usage, then exits
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    std::process::exit(2);
+}
+
+/// Removes `flag` and the value after it from `args`, returning the value
+fn take_flag_value(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, String> {
+    let Some(i) = args.iter().position(|a| a == flag) else {
+        return Ok(None);
+    };
+    let value = args
+        .get(i + 1)
+        .filter(|value| !value.starts_with("--"))
+        .cloned()
+        .ok_or_else(|| format!("{} needs a value", flag))?;
+    args.drain(i..=i + 1);
+    Ok(Some(value))
+}
+
+/// Parses `--camera`'s `x,y,z,yaw,pitch`
+fn parse_camera_start(value: &str) -> Result<Vec<f32>, String> {
+    let values = value
+        .split(',')
+        .map(|v| v.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid --camera value {}: {}", value, e))?;
+    if values.len() != 5 {
+        return Err(format!("--camera takes x,y,z,yaw,pitch, got {}", value));
+    }
+    Ok(values)
+}
+
 // ============================ Main Program =========================
 #[tokio::main]
 async fn main() {
@@ -47,17 +122,38 @@ async fn main() {
     debug!("Launched BUAT v{}", env!("CARGO_PKG_VERSION"));
 
     // ------------------------ Connect to Server ---------------------
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
     debug!("Client args: {:?}", args);
 
-    let uri = args.get(2).map(|s| s.as_str()).unwrap_or("127.0.0.1:6700");
-    let _sid: u64 = args
-        .get(1)
-        .expect("No ID specified!")
-        .parse()
-        .expect("Invalid ID!");
-
-    preconnect(uri).await;
+    // `--headless <file.png>` renders a few frames in a hidden window, saves the last one and
+    // exits. It doesn't need a server
+    let headless_output = take_flag_value(&mut args, "--headless")
+        .unwrap_or_else(|e| usage_error(&e))
+        .map(PathBuf::from);
+    // `--camera x,y,z,yaw,pitch` starts the camera somewhere other than the origin
+    let camera_start = take_flag_value(&mut args, "--camera")
+        .and_then(|value| value.map(|v| parse_camera_start(&v)).transpose())
+        .unwrap_or_else(|e| usage_error(&e));
+    // `--sky cubemap` draws the skybox in assets/skybox instead of the procedural sky
+    let cubemap_sky = match take_flag_value(&mut args, "--sky")
+        .unwrap_or_else(|e| usage_error(&e))
+        .as_deref()
+    {
+        None | Some("procedural") => false,
+        Some("cubemap") => true,
+        Some(other) => usage_error(&format!("Unknown sky {}", other)),
+    };
+
+    if headless_output.is_none() {
+        let uri = args.get(2).map(|s| s.as_str()).unwrap_or("127.0.0.1:6700");
+        let _sid: u64 = args
+            .get(1)
+            .expect("No ID specified!")
+            .parse()
+            .expect("Invalid ID!");
+
+        preconnect(uri).await;
+    }
 
     // --------------------------- Window -----------------------------
     let mut game_window = GameWindow::new(GameWindowHints {
@@ -66,6 +162,7 @@ async fn main() {
         title: &format!("BUAT {}", env!("CARGO_PKG_VERSION")),
         fullscreen: false,
         size: (1080, 720),
+        headless: headless_output.is_some(),
     })
     .unwrap();
 
@@ -85,25 +182,30 @@ async fn main() {
     }
 
     // ---------------------------- Shaders ---------------------------
-    let shader_norm = shader::Shader::from_files(
-        "assets/shaders/part_default.vert",
-        "assets/shaders/part_default.frag",
-    )
-    .unwrap();
-    let shader_tex = shader::Shader::from_files(
-        "assets/shaders/part_tex.vert",
-        "assets/shaders/part_tex.frag",
-    )
-    .unwrap();
-    let shader_mesh = shader::Shader::from_files(
-        "assets/shaders/mesh_default.vert",
-        "assets/shaders/mesh_default.frag",
-    )
-    .unwrap();
-    let shader_line =
-        shader::Shader::from_files("assets/shaders/line.vert", "assets/shaders/line.frag").unwrap();
-    let texture_test =
-        texture::load_texture_from_file("assets/material/wood.png", Default::default()).unwrap();
+    let mut shader_cache = ShaderCache::new();
+    for (vert, frag) in [PART_SHADER, PART_TEX_SHADER] {
+        shader_cache.get(vert, frag, &[]).unwrap();
+    }
+    let shader_mesh = shader_cache
+        .get(
+            "assets/shaders/mesh_default.vert",
+            "assets/shaders/mesh_default.frag",
+            &[],
+        )
+        .unwrap();
+    let part_materials = PartMaterialLibrary::load().unwrap();
+
+    let camera_ubo = UniformBuffer::<CameraBlock>::new(CAMERA_BLOCK_BINDING);
+    let light_ubo = UniformBuffer::<LightBlock>::new(LIGHT_BLOCK_BINDING);
+    let shadow_maps = ShadowMaps::new(2048, 1024);
+    let (fb_width, fb_height) = game_window.win.get_framebuffer_size();
+    let mut post = PostProcessor::new(fb_width, fb_height);
+    let mut text_renderer = TextRenderer::new().unwrap();
+    let mut particle_renderer = ParticleRenderer::new();
+    let mut id_buffer = IdBuffer::new(fb_width, fb_height);
+    let mut debug_ui = DebugUi::new(&mut game_window);
+
+    let mut shader_watcher = ShaderWatcher::new();
 
     // --------------------------- Camera -----------------------------
     let (width, height) = game_window.win.get_size();
@@ -120,19 +222,173 @@ async fn main() {
         0.1,
         100.0,
     );
+    if let Some(start) = camera_start {
+        camera.position = glm::vec3(start[0], start[1], start[2]);
+        camera.yaw = start[3];
+        camera.pitch = start[4];
+        camera.update_vectors();
+    }
 
     // ---------------------------- ECS Setup -------------------------
     let mut world = ECS::World::new();
+    if cubemap_sky {
+        world.sky = Sky::cubemap(&SKYBOX_FACES).unwrap();
+    }
 
-    spawn_part(
+    let light = spawn_part(
         &mut world,
+        &mut shader_cache,
+        PartShape::Sphere,
         glm::vec3(0., 0., 0.),
         glm::vec3(0., 0., 0.),
         glm::vec3(1., 1., 1.),
         glm::vec3(1., 1., 1.),
-        &shader_norm,
         None,
+    )
+    .unwrap();
+    world.lights.insert(
+        light,
+        ECS::Light {
+            color: glm::vec3(1., 1., 1.),
+            intensity: 1.0,
+            kind: ECS::LightKind::Point,
+            cast_shadows: true,
+        },
     );
+    // The bulb sits on the light, so it would shadow everything
+    world.cast_shadows.insert(light, ECS::CastShadow(false));
+    world
+        .receive_shadows
+        .insert(light, ECS::ReceiveShadow(false));
+
+    let sun = world.create_entity();
+    world.lights.insert(
+        sun,
+        ECS::Light {
+            color: glm::vec3(1., 0.95, 0.85),
+            intensity: 0.6,
+            kind: ECS::LightKind::Directional(glm::vec3(-0.4, -1., -0.3)),
+            cast_shadows: true,
+        },
+    );
+
+    let floor = spawn_part(
+        &mut world,
+        &mut shader_cache,
+        PartShape::Block,
+        glm::vec3(0., -2., 0.),
+        glm::vec3(0., 0., 0.),
+        glm::vec3(8., 1., 4.),
+        glm::vec3(1., 1., 1.),
+        None,
+    )
+    .unwrap();
+    world.part_materials.insert(floor, PartMaterial::WoodPlanks);
+
+    // One of each shape along the floor, fading from red at the bottom to yellow at the top
+    let shape_gradient = Gradient::even(
+        &[
+            glm::vec3(0.8, 0.1, 0.1),
+            glm::vec3(0.9, 0.5, 0.1),
+            glm::vec3(1., 0.9, 0.2),
+        ],
+        GradientAxis::Y,
+        GradientInterpolation::Smooth,
+    )
+    .unwrap();
+    for (i, shape) in PartShape::ALL.iter().enumerate() {
+        let part = spawn_part(
+            &mut world,
+            &mut shader_cache,
+            *shape,
+            glm::vec3(i as f32 * 1.5 - 3., -1., -1.),
+            glm::vec3(0., 0., 0.),
+            glm::vec3(1., 1., 1.),
+            glm::vec3(0.8, 0.3, 0.2),
+            None,
+        )
+        .unwrap();
+        world.gradients.insert(part, shape_gradient.clone());
+    }
+
+    let caption = world.create_entity();
+    world
+        .positions
+        .insert(caption, Position(glm::vec3(0., -0.2, -1.)));
+    world.texts.insert(
+        caption,
+        Text::billboard(
+            "One of every part shape, shaded with a gradient",
+            0.25,
+            glm::vec3(0., 0., 0.),
+        )
+        .with_max_width(3.),
+    );
+
+    // See-through parts in front of the shapes
+    let glass = spawn_part(
+        &mut world,
+        &mut shader_cache,
+        PartShape::Block,
+        glm::vec3(0., -1., 0.5),
+        glm::vec3(0., 0., 0.),
+        glm::vec3(4., 1., 0.1),
+        glm::vec3(0.8, 0.9, 1.),
+        None,
+    )
+    .unwrap();
+    world.part_materials.insert(glass, PartMaterial::Glass);
+    let ghost = spawn_part(
+        &mut world,
+        &mut shader_cache,
+        PartShape::Sphere,
+        glm::vec3(3., -1., 0.5),
+        glm::vec3(0., 0., 0.),
+        glm::vec3(0.8, 0.8, 0.8),
+        glm::vec3(0.3, 1., 0.5),
+        None,
+    )
+    .unwrap();
+    world.transparencies.insert(ghost, ECS::Transparency(0.5));
+    // Nametag that stays the same size on screen
+    world.texts.insert(
+        ghost,
+        Text::new(
+            "Ghost",
+            24.,
+            TextSpace::FixedBillboard(glm::vec3(0., 0.6, 0.)),
+        )
+        .with_align(TextAlign::Center)
+        .with_color(glm::vec3(0.3, 1., 0.5)),
+    );
+
+    // A dashed measurement along the front of the floor, and the arc of something thrown over it
+    spawn_line(
+        &mut world,
+        &mut shader_cache,
+        glm::vec3(-4., -1.45, 2.1),
+        glm::vec3(4., -1.45, 2.1),
+        glm::vec3(1., 1., 0.2),
+        LineStyle::pixels(3.)
+            .with_cap(LineCap::Round)
+            .dashed(0.3, 0.15),
+    )
+    .unwrap();
+    let arc: Vec<Vec3> = (0..=24)
+        .map(|i| {
+            let t = i as f32 / 24.;
+            glm::vec3(8. * t - 4., 12. * t * (1. - t) - 1., -1.8)
+        })
+        .collect();
+    spawn_polyline(
+        &mut world,
+        &mut shader_cache,
+        &arc,
+        false,
+        glm::vec3(0.2, 0.8, 1.),
+        LineStyle::world(0.05).with_cap(LineCap::Round),
+    )
+    .unwrap();
 
     for obj in &["assets/rising_sun.obj", "assets/voidstar.obj"] {
         let position = if *obj == "assets/voidstar.obj" {
@@ -140,18 +396,14 @@ async fn main() {
         } else {
             glm::vec3(0., 0., 0.)
         };
-        let color = if *obj == "assets/voidstar.obj" {
-            glm::vec3(0., 1., 0.)
-        } else {
-            glm::vec3(1., 0., 0.)
-        };
+        // Colors and textures come from the OBJ's materials
         for render_data in load_obj_to_render_data(obj, true, true).unwrap() {
             add_render_data_to_world(
                 &mut world,
                 position,
                 glm::vec3(0., 0., 0.),
                 glm::vec3(1., 1., 1.),
-                color,
+                glm::vec3(1., 1., 1.),
                 &render_data,
                 &shader_mesh,
                 None,
@@ -159,43 +411,91 @@ async fn main() {
         }
     }
 
+    // A campfire on the corner of the floor, smoke drifting off the flames
+    let campfire = glm::vec3(-3.2, -1.45, 1.3);
+    let flames = world.create_entity();
+    world.positions.insert(flames, Position(campfire));
+    world.particle_emitters.insert(
+        flames,
+        ParticleEmitter::new(80., 0.7, ParticleBlend::Additive)
+            .with_velocity(Vec3::y(), 0.35, 1.3)
+            .with_color(
+                Curve::new(vec![
+                    (0., glm::vec4(4., 1.8, 0.5, 1.)),
+                    (0.5, glm::vec4(2.5, 0.6, 0.1, 0.7)),
+                    (1., glm::vec4(0.6, 0.1, 0., 0.)),
+                ])
+                .unwrap(),
+            )
+            .with_size(Curve::linear(0.35, 0.1)),
+    );
+    let smoke = world.create_entity();
+    world
+        .positions
+        .insert(smoke, Position(campfire + glm::vec3(0., 0.6, 0.)));
+    world.particle_emitters.insert(
+        smoke,
+        ParticleEmitter::new(10., 4., ParticleBlend::Alpha)
+            .with_velocity(Vec3::y(), 0.25, 0.5)
+            .with_gravity(glm::vec3(0.15, 0.05, 0.))
+            .with_color(
+                Curve::new(vec![
+                    (0., glm::vec4(0.25, 0.25, 0.25, 0.)),
+                    (0.15, glm::vec4(0.3, 0.3, 0.3, 0.5)),
+                    (1., glm::vec4(0.45, 0.45, 0.45, 0.)),
+                ])
+                .unwrap(),
+            )
+            .with_size(Curve::linear(0.3, 1.6))
+            .with_texture(
+                load_texture_from_file("assets/particles/smoke.png", Default::default()).unwrap(),
+            )
+            .with_max_particles(60)
+            .with_seed(1),
+    );
+    // Already burning, with the smoke risen, rather than just lit
+    for emitter in [flames, smoke] {
+        let origin = world.positions[&emitter].0;
+        let emitter = world.particle_emitters.get_mut(&emitter).unwrap();
+        emitter.prewarm(origin, emitter.lifetime);
+    }
+
+    let fps_label = world.create_entity();
+    world.texts.insert(
+        fps_label,
+        Text::new("", 20., TextSpace::Screen(glm::vec2(10., 10.))),
+    );
+
     // ------------------------- Mouse Handler ------------------------
     let mut mousehandler = MouseHandler::new(0., 0.);
     game_window.win.set_cursor_mode(glfw::CursorMode::Disabled);
 
-    // ------------------------- Spawn Axis Lines ---------------------
-    let axes = [
-        (
-            Vec3::new(0., 0., 0.),
-            Vec3::new(2., 0., 0.),
-            Vec3::new(1., 0., 0.),
-        ),
-        (
-            Vec3::new(0., 0., 0.),
-            Vec3::new(0., 2., 0.),
-            Vec3::new(0., 1., 0.),
-        ),
-        (
-            Vec3::new(0., 0., 0.),
-            Vec3::new(0., 0., 2.),
-            Vec3::new(0., 0., 1.),
-        ),
-    ];
-    for (start, end, color) in axes {
-        spawn_line(&mut world, start, end, color, &shader_line);
-    }
-
     // ------------------------- Main Loop ----------------------------
     debug!("Starting main loop...");
     let mut total_time = 0.0;
+    // Frame stats are shown in the title, averaged over about a second
+    let mut stats_time = 0.0;
+    let mut stats_frames: u32 = 0;
+    let mut frame: u32 = 0;
+    let mut screenshot_requested = false;
+    // Clicks pick through the id buffer instead of raycasting while this is on
+    let mut gpu_picking = false;
+    let mut cursor = glm::vec2(0., 0.);
+    let mut pick_at = None;
 
     while !game_window.win.should_close() {
-        let delta_time = game_window.tick();
+        let mut delta_time = game_window.tick();
+        if headless_output.is_some() {
+            delta_time = HEADLESS_FRAME_TIME;
+        }
         total_time += delta_time;
+        world.render_stats.begin_frame(delta_time);
+        world.render_stats.begin_system("input");
         game_window.glfw.poll_events();
 
         // ------------------------- Input -----------------------------
         for (_, event) in glfw::flush_messages(&game_window.ev) {
+            debug_ui.handle_event(&event);
             match event {
                 glfw::WindowEvent::Key(key, _, action, _)
                     if (key as usize) < windowing::KEY_COUNT =>
@@ -206,7 +506,29 @@ async fn main() {
                     if key == Key::Escape && action == Action::Press {
                         game_window.win.set_should_close(true);
                     }
-                    if key == Key::LeftAlt && action == Action::Press {
+                    if key == Key::F12 && action == Action::Press {
+                        screenshot_requested = true;
+                    }
+                    if key == Key::F3 && action == Action::Press {
+                        gpu_picking = !gpu_picking;
+                        debug!("GPU picking {}", if gpu_picking { "on" } else { "off" });
+                    }
+                    if key == Key::F4 && action == Action::Press {
+                        world.debug_view = world.debug_view.next();
+                        debug!("Debug view: {}", world.debug_view.name());
+                    }
+                    // The debug UI takes the mouse and keyboard from the camera while it's open
+                    if key == Key::F1 && action == Action::Press {
+                        debug_ui.visible = !debug_ui.visible;
+                        mousehandler.locked = !debug_ui.visible;
+                        let mode = if mousehandler.locked {
+                            glfw::CursorMode::Disabled
+                        } else {
+                            glfw::CursorMode::Normal
+                        };
+                        game_window.win.set_cursor_mode(mode);
+                    }
+                    if key == Key::LeftAlt && action == Action::Press && !debug_ui.visible {
                         mousehandler.locked = !mousehandler.locked;
                         let mode = if mousehandler.locked {
                             glfw::CursorMode::Disabled
@@ -220,8 +542,12 @@ async fn main() {
                 | glfw::WindowEvent::FramebufferSize(width, height) => {
                     unsafe { gl::Viewport(0, 0, width, height) };
                     camera.aspect_ratio = width as f32 / height as f32;
+                    let (fb_width, fb_height) = game_window.win.get_framebuffer_size();
+                    post.resize(fb_width, fb_height);
+                    id_buffer.resize(fb_width, fb_height);
                 }
                 glfw::WindowEvent::CursorPos(x, y) => {
+                    cursor = glm::vec2(x as f32, y as f32);
                     let (dx, dy) = mousehandler.handle_mouse(x as f32, y as f32);
                     if mousehandler.locked {
                         camera.process_mouse(dx, dy);
@@ -230,98 +556,215 @@ async fn main() {
                 glfw::WindowEvent::Scroll(_, yoffset) if mousehandler.locked => {
                     camera.process_scroll(yoffset as f32);
                 }
-                glfw::WindowEvent::MouseButton(_, Action::Press, _) if mousehandler.locked => {
-                    spawn_line(
-                        &mut world,
-                        camera.position,
-                        camera.position + camera.front * 100.0,
-                        glm::vec3(rand::random(), rand::random(), rand::random()),
-                        &shader_line,
-                    );
+                // Locked, clicks pick at the crosshair. Otherwise they pick under the cursor,
+                // unless it's over the debug UI
+                glfw::WindowEvent::MouseButton(glfw::MouseButton::Button1, Action::Press, _) => {
+                    if mousehandler.locked {
+                        let (width, height) = game_window.win.get_size();
+                        pick_at = Some(glm::vec2(width as f32, height as f32) * 0.5);
+                    } else if !debug_ui.wants_mouse() {
+                        pick_at = Some(cursor);
+                    }
                 }
                 _ => {}
             }
         }
 
-        camera::debug_camera_movement(&mut camera, &game_window, delta_time);
+        if !debug_ui.visible {
+            camera::debug_camera_movement(&mut camera, &game_window, delta_time);
+        }
 
-        // ------------------------- Rendering --------------------------
-        unsafe {
-            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+        world.render_stats.begin_system("particles");
+        particles::update_particles(&mut world, delta_time);
+
+        // -------------------------- Picking ---------------------------
+        world.render_stats.begin_system("picking");
+        if let Some(screen) = pick_at.take() {
+            let (width, height) = game_window.win.get_size();
+            let window_size = glm::vec2(width as f32, height as f32);
+            let ray = if mousehandler.locked {
+                camera.crosshair_ray()
+            } else {
+                camera.screen_ray(screen, window_size)
+            };
+            let hit = if gpu_picking {
+                let (fb_width, fb_height) = game_window.win.get_framebuffer_size();
+                picking::pick_with_id_buffer(
+                    &world,
+                    &camera,
+                    &id_buffer,
+                    &mut shader_cache,
+                    screen,
+                    window_size,
+                    glm::vec2(fb_width as f32, fb_height as f32),
+                )
+                .unwrap()
+            } else {
+                picking::raycast(&world, &ray, PICK_DISTANCE)
+            };
+
+            match hit {
+                Some(hit) => {
+                    debug!(
+                        "Picked entity #{} at {:?}, {:.2} away",
+                        hit.entity, hit.point, hit.distance
+                    );
+                    debug_ui.selected = Some(hit.entity);
+                    let color = glm::vec3(1., 0.9, 0.2);
+                    world
+                        .debug_draw
+                        .line(ray.origin, hit.point, color, PICK_MARKER_TIME);
+                    world.debug_draw.arrow(
+                        hit.point,
+                        hit.point + hit.normal * 0.5,
+                        color,
+                        PICK_MARKER_TIME,
+                    );
+                    if let Some(bounds) = render::world_bounds(&world, hit.entity) {
+                        world.debug_draw.aabb(&bounds, color, PICK_MARKER_TIME);
+                    }
+                }
+                None => world.debug_draw.ray(
+                    ray.origin,
+                    ray.direction,
+                    PICK_DISTANCE,
+                    glm::vec3(1., 0.2, 0.2),
+                    PICK_MARKER_TIME,
+                ),
+            }
+        }
+
+        // ----------------------- Shader Reload ------------------------
+        world.render_stats.begin_system("shader_reload");
+        for (old_program, shader) in shader_watcher.poll(&shader_cache) {
+            world.replace_shader(old_program, &shader);
+            shader_cache.replace_shader(old_program, &shader);
+            unsafe { gl::DeleteProgram(old_program) };
         }
 
+        // ------------------------- Rendering --------------------------
         let view = camera.get_view_matrix();
         let projection = camera.get_projection_matrix();
 
         world.positions.insert(
-            0,
+            light,
             Position(
                 camera.position + Vec3::new(25. * total_time.sin(), 0., 25. * total_time.cos()),
             ),
         );
 
-        for (&entity, render_data) in &world.part_render_data {
-            if let Some(shader_ref) = world.shaders.get(&entity) {
-                let shader = &shader_ref.0;
-                let pos = world.positions.get(&entity).unwrap();
-                let rot = world.rotations.get(&entity).unwrap();
-                let scale = world.scales.get(&entity).unwrap();
+        // ------------------------ Frame Uniforms ----------------------
+        camera_ubo.update(&CameraBlock {
+            view,
+            projection,
+            view_pos: glm::vec3_to_vec4(&camera.position),
+        });
+        light_ubo.update(&render::light_block(&world));
+
+        world.render_stats.begin_pass("shadows");
+        shadow_maps
+            .render(&world, &camera, &mut shader_cache)
+            .unwrap();
+        shadow_maps.bind();
+
+        world.render_stats.begin_pass("sky");
+        post.begin();
+        if world.debug_view == DebugView::Lit {
+            world.sky.render(&mut shader_cache).unwrap();
+        }
+        part_materials.bind();
+
+        world.render_stats.begin_pass("world");
+        let frame_stats = match world.debug_view {
+            DebugView::Lit => render::render_world(&world, &camera),
+            _ => debug_view::render_debug_view(&world, &camera, &mut shader_cache).unwrap(),
+        };
+        world.render_stats.add_frame_stats(&frame_stats);
+        if world.debug_view == DebugView::Normals {
+            debug_view::draw_normals(&mut world, &camera);
+        }
 
-                let rotation_matrix = glm::rotation(rot.0.y, &glm::vec3(0., 1., 0.))
-                    * glm::rotation(rot.0.x, &glm::vec3(1., 0., 0.))
-                    * glm::rotation(rot.0.z, &glm::vec3(0., 0., 1.));
+        world.render_stats.begin_pass("particles");
+        particle_renderer
+            .render(&world, &camera, &mut shader_cache)
+            .unwrap();
 
-                let model = glm::translate(&glm::identity(), &pos.0)
-                    * rotation_matrix
-                    * glm::scaling(&scale.0);
+        // Axis gizmo and grid at the origin, and a labelled marker on each particle emitter
+        world.render_stats.begin_pass("debug_draw");
+        let origin = Vec3::new(0., 0., 0.);
+        for axis in [Vec3::x(), Vec3::y(), Vec3::z()] {
+            world.debug_draw.arrow(origin, axis * 2., axis, 0.);
+        }
+        world
+            .debug_draw
+            .grid(origin, 4., 8, glm::vec3(0.4, 0.4, 0.4), 0.);
+        let emitter_color = glm::vec3(1., 0.6, 0.2);
+        for (entity, emitter) in &world.particle_emitters {
+            let position = world.positions[entity].0;
+            world.debug_draw.sphere(position, 0.15, emitter_color, 0.);
+            world.debug_draw.text3d(
+                position - glm::vec3(0., 0.3, 0.),
+                &format!("{:?}", emitter.blend),
+                0.08,
+                emitter_color,
+                0.,
+            );
+        }
+        world
+            .debug_draw
+            .render(&camera, delta_time, &mut shader_cache)
+            .unwrap();
+        world.render_stats.begin_pass("text");
+        text_renderer
+            .render_billboards(&world, &camera, &mut shader_cache)
+            .unwrap();
+        world.render_stats.begin_pass("post");
+        post.finish(&mut shader_cache, world.debug_view).unwrap();
+        world.render_stats.begin_pass("screen_text");
+        text_renderer
+            .render_screen(&world, &mut shader_cache)
+            .unwrap();
+        world.render_stats.begin_pass("debug_ui");
+        debug_ui.render(
+            &game_window,
+            &mut world,
+            &mut camera,
+            &mut post.settings,
+            delta_time,
+        );
+        world.render_stats.end();
+
+        stats_time += delta_time;
+        stats_frames += 1;
+        if stats_time >= 1.0 {
+            let stats = format!(
+                "{:.0} fps | {} drawn, {} culled",
+                stats_frames as f32 / stats_time,
+                frame_stats.drawn,
+                frame_stats.culled
+            );
+            game_window
+                .win
+                .set_title(&format!("BUAT {} | {}", env!("CARGO_PKG_VERSION"), stats));
+            world.texts.get_mut(&fps_label).unwrap().content = stats;
+            stats_time = 0.0;
+            stats_frames = 0;
+        }
 
-                unsafe {
-                    gl::UseProgram(render_data.program_id);
-                }
-                shader.use_program();
-                shader.set_mat4("view", &view).unwrap();
-                shader.set_mat4("projection", &projection).unwrap();
-
-                match world.entity_types.get(&entity) {
-                    Some(ECS::EntityType::Line(color)) => {
-                        shader.set_mat4("model", &glm::identity()).unwrap();
-                        shader.set_vec3("uColor", color).unwrap();
-                        unsafe {
-                            gl::BindVertexArray(render_data.vao_id);
-                            gl::DrawArrays(gl::LINES, 0, 2);
-                            gl::BindVertexArray(0);
-                        }
-                    }
-                    Some(ECS::EntityType::Part) => {
-                        shader.set_mat4("model", &model).unwrap();
-                        shader
-                            .set_vec3("uColor", &world.colors.get(&entity).unwrap().0)
-                            .unwrap();
-                        shader.set_vec3("viewPos", &camera.position).unwrap();
-                        shader
-                            .set_vec3("lightPos", &world.positions.get(&0).unwrap().0)
-                            .unwrap();
-                        shader
-                            .set_vec3("lightColor", &world.colors.get(&0).unwrap().0)
-                            .unwrap();
-
-                        if let Some(tex) = world.textures.get(&entity) {
-                            tex.bind(0);
-                        }
-
-                        unsafe {
-                            gl::BindVertexArray(render_data.vao_id);
-                            gl::DrawElements(
-                                gl::TRIANGLES,
-                                render_data.index_count,
-                                gl::UNSIGNED_INT,
-                                std::ptr::null(),
-                            );
-                        }
-                    }
-                    _ => {}
-                }
+        // ------------------------- Screenshots ------------------------
+        // Read back before swapping, the back buffer is undefined afterwards
+        frame += 1;
+        if let Some(path) = &headless_output
+            && frame >= HEADLESS_FRAMES
+        {
+            screenshot::save_png(&screenshot::capture_screen(&game_window), path).unwrap();
+            game_window.win.set_should_close(true);
+        }
+        if screenshot_requested {
+            screenshot_requested = false;
+            let path = screenshot::screenshot_path();
+            if let Err(e) = screenshot::save_png(&screenshot::capture_screen(&game_window), &path) {
+                error!("{}", e);
             }
         }
 
@@ -1 +1,34 @@
-pub mod obj_loader;
\ No newline at end of file
+use nalgebra_glm as glm;
+
+pub mod obj_loader;
+
+/// Triangles out of an indexed, interleaved vertex buffer whose vertices start with x, y, z
+pub fn mesh_triangles(vertices: &[f32], stride: usize, indices: &[u32]) -> Vec<[glm::Vec3; 3]> {
+    let position = |index: u32| {
+        let i = index as usize * stride;
+        glm::vec3(vertices[i], vertices[i + 1], vertices[i + 2])
+    };
+    indices
+        .chunks_exact(3)
+        .map(|t| [position(t[0]), position(t[1]), position(t[2])])
+        .collect()
+}
+
+/// Positions and normals of an interleaved vertex buffer whose vertices start with x, y, z
+/// and have their normal `normal_offset` floats in
+pub fn mesh_normals(
+    vertices: &[f32],
+    stride: usize,
+    normal_offset: usize,
+) -> Vec<(glm::Vec3, glm::Vec3)> {
+    vertices
+        .chunks_exact(stride)
+        .map(|v| {
+            let n = normal_offset;
+            (
+                glm::vec3(v[0], v[1], v[2]),
+                glm::vec3(v[n], v[n + 1], v[n + 2]),
+            )
+        })
+        .collect()
+}
@@ -1,11 +1,21 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use log::warn;
 use tobj;
 
 // Assuming these are your types
+use crate::graphics::frustum::Aabb;
+use crate::graphics::material::Material;
+use crate::graphics::render_stats;
 use crate::graphics::shader::VertexArrayObject;
+use crate::object::mesh::{mesh_normals, mesh_triangles};
 use crate::object::part::RenderData;
 
 #[derive(Debug)]
 pub enum MeshLoadError {
+    #[allow(dead_code)]
     Tobj(tobj::LoadError),
     NoMeshes,
 }
@@ -18,12 +28,13 @@ impl From<tobj::LoadError> for MeshLoadError {
 
 /// Loads an OBJ file and converts it to RenderData
 /// Returns a Vec because OBJ files can contain multiple meshes
+/// Each mesh carries the material its MTL file assigns it, if any
 pub fn load_obj_to_render_data(
     file_path: &str,
     include_normals: bool,
     include_texcoords: bool,
 ) -> Result<Vec<RenderData>, MeshLoadError> {
-    let (models, _materials) = tobj::load_obj(
+    let (models, materials) = tobj::load_obj(
         file_path,
         &tobj::LoadOptions {
             triangulate: true,
@@ -36,6 +47,20 @@ pub fn load_obj_to_render_data(
         return Err(MeshLoadError::NoMeshes);
     }
 
+    // A missing or broken MTL file shouldn't stop the geometry from loading
+    let base_dir = Path::new(file_path).parent().unwrap_or(Path::new(""));
+    let mut textures = HashMap::new();
+    let materials: Vec<Material> = match materials {
+        Ok(materials) => materials
+            .iter()
+            .map(|mtl| Material::from_mtl(mtl, base_dir, &mut textures))
+            .collect(),
+        Err(e) => {
+            warn!("Failed to load materials for {}: {}", file_path, e);
+            Vec::new()
+        }
+    };
+
     let mut render_data_vec = Vec::new();
 
     for model in models {
@@ -73,12 +98,13 @@ pub fn load_obj_to_render_data(
         }
 
         // Create OpenGL buffers
-        let render_data = create_render_data(
+        let mut render_data = create_render_data(
             &interleaved_data,
             &mesh.indices,
             include_normals,
             include_texcoords,
         );
+        render_data.material = mesh.material_id.and_then(|id| materials.get(id)).cloned();
 
         render_data_vec.push(render_data);
     }
@@ -110,14 +136,14 @@ fn create_render_data(
     unsafe {
         // Create and bind VAO
         gl::GenVertexArrays(1, &mut vao.id);
-        gl::BindVertexArray(vao.id);
+        render_stats::bind_vertex_array(vao.id);
 
         // Create and upload VBO
         gl::GenBuffers(1, &mut vbo);
         gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
         gl::BufferData(
             gl::ARRAY_BUFFER,
-            (vertices.len() * std::mem::size_of::<f32>()) as _,
+            std::mem::size_of_val(vertices) as _,
             vertices.as_ptr() as *const _,
             gl::STATIC_DRAW,
         );
@@ -127,7 +153,7 @@ fn create_render_data(
         gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
         gl::BufferData(
             gl::ELEMENT_ARRAY_BUFFER,
-            (indices.len() * std::mem::size_of::<u32>()) as _,
+            std::mem::size_of_val(indices) as _,
             indices.as_ptr() as *const _,
             gl::STATIC_DRAW,
         );
@@ -175,12 +201,25 @@ fn create_render_data(
         }
 
         // Unbind VAO
-        gl::BindVertexArray(0);
+        render_stats::bind_vertex_array(0);
     }
+    render_stats::track_buffer_memory(
+        (std::mem::size_of_val(vertices) + std::mem::size_of_val(indices)) as isize,
+    );
 
     RenderData {
         vao,
         index_count: indices.len() as i32,
         program_id: 0,
+        material: None,
+        bounds: Aabb::from_vertices(vertices, floats_per_vertex),
+        triangles: Rc::new(mesh_triangles(vertices, floats_per_vertex, indices)),
+        normals: Rc::new(if has_normals {
+            // After the position, and the UVs if there are any
+            let offset = if has_texcoords { 5 } else { 3 };
+            mesh_normals(vertices, floats_per_vertex, offset)
+        } else {
+            Vec::new()
+        }),
     }
 }
\ No newline at end of file
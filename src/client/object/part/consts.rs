@@ -1,66 +1,53 @@
-pub const PART_VERTICES: [f32; 8 * 3] = [
-    // Back face (z = -0.5)
-    -0.5, -0.5, -0.5, // left,  bottom, back
-    0.5, -0.5, -0.5, // right, bottom, back
-    -0.5, 0.5, -0.5, // left,  top,    back
-    0.5, 0.5, -0.5, // right, top,    back
-    // Front face (z = +0.5)
-    -0.5, -0.5, 0.5, // left,  bottom, front
-    0.5, -0.5, 0.5, // right, bottom, front
-    -0.5, 0.5, 0.5, // left,  top,    front
-    0.5, 0.5, 0.5, // right, top,    front
-];
-
-pub const PART_VERTICES_INVERSE: [f32; 8 * 3] = [
-    // Back face (z = -0.5)
-    -0.5, -0.5, -0.5, // left,  bottom, back
-    0.5, -0.5, -0.5, // right, bottom, back
-    -0.5, -0.5, 0.5, // left,  top,    back
-    0.5, -0.5, 0.5, // right, top,    back
-    // Front face (z = +0.5)
-    -0.5, 0.5, -0.5, // left,  bottom, front
-    0.5, 0.5, -0.5, // right, bottom, front
-    -0.5, 0.5, 0.5, // left,  top,    front
-    0.5, 0.5, 0.5, // right, top,    front
-];
+/// Floats per vertex in `PART_VERTICES`: position, normal, UV
+pub const PART_VERTEX_FLOATS: usize = 8;
 
-pub const PART_INDICES_COLOR: [u32; 3 * 2 * 6] = [
-    0, 3, 1, 0, 2, 3, // back
-    4, 5, 7, 4, 7, 6, // front
-    4, 2, 0, 4, 6, 2, // left
-    1, 3, 7, 1, 7, 5, // right
-    2, 6, 7, 2, 7, 3, // top
-    0, 1, 5, 0, 5, 4, // bottom
-];
+/// `(location, float count)` of each attribute in `PART_VERTICES`, matching the mesh shaders:
+/// position at 0, normal at 3, UV at 2
+pub const PART_ATTRIBUTES: &[(u32, i32)] = &[(0, 3), (3, 3), (2, 2)];
 
-pub const PART_VERTICES_TEX: [f32; 24 * 5] = [
+/// Unit cube with 4 vertices per face so each face gets its own normal and UVs.
+/// Faces are wound counter-clockwise seen from outside
+#[rustfmt::skip]
+pub const PART_VERTICES: [f32; 24 * PART_VERTEX_FLOATS] = [
+    // x, y, z, nx, ny, nz, u, v
     // Back
-    -0.5, -0.5, -0.5, 0.0, 0.0, 0.5, -0.5, -0.5, 1.0, 0.0, 0.5, 0.5, -0.5, 1.0, 1.0, -0.5, 0.5,
-    -0.5, 0.0, 1.0, // Front
-    -0.5, -0.5, 0.5, 0.0, 0.0, 0.5, -0.5, 0.5, 1.0, 0.0, 0.5, 0.5, 0.5, 1.0, 1.0, -0.5, 0.5, 0.5,
-    0.0, 1.0, // Left
-    -0.5, -0.5, 0.5, 0.0, 0.0, -0.5, -0.5, -0.5, 1.0, 0.0, -0.5, 0.5, -0.5, 1.0, 1.0, -0.5, 0.5,
-    0.5, 0.0, 1.0, // Right
-    0.5, -0.5, -0.5, 0.0, 0.0, 0.5, -0.5, 0.5, 1.0, 0.0, 0.5, 0.5, 0.5, 1.0, 1.0, 0.5, 0.5, -0.5,
-    0.0, 1.0, // Top
-    -0.5, 0.5, -0.5, 0.0, 0.0, 0.5, 0.5, -0.5, 1.0, 0.0, 0.5, 0.5, 0.5, 1.0, 1.0, -0.5, 0.5, 0.5,
-    0.0, 1.0, // Bottom
-    -0.5, -0.5, 0.5, 0.0, 0.0, 0.5, -0.5, 0.5, 1.0, 0.0, 0.5, -0.5, -0.5, 1.0, 1.0, -0.5, -0.5,
-    -0.5, 0.0, 1.0,
+    0.5, -0.5, -0.5, 0.0, 0.0, -1.0, 0.0, 0.0,
+    -0.5, -0.5, -0.5, 0.0, 0.0, -1.0, 1.0, 0.0,
+    -0.5, 0.5, -0.5, 0.0, 0.0, -1.0, 1.0, 1.0,
+    0.5, 0.5, -0.5, 0.0, 0.0, -1.0, 0.0, 1.0,
+    // Front
+    -0.5, -0.5, 0.5, 0.0, 0.0, 1.0, 0.0, 0.0,
+    0.5, -0.5, 0.5, 0.0, 0.0, 1.0, 1.0, 0.0,
+    0.5, 0.5, 0.5, 0.0, 0.0, 1.0, 1.0, 1.0,
+    -0.5, 0.5, 0.5, 0.0, 0.0, 1.0, 0.0, 1.0,
+    // Left
+    -0.5, -0.5, -0.5, -1.0, 0.0, 0.0, 0.0, 0.0,
+    -0.5, -0.5, 0.5, -1.0, 0.0, 0.0, 1.0, 0.0,
+    -0.5, 0.5, 0.5, -1.0, 0.0, 0.0, 1.0, 1.0,
+    -0.5, 0.5, -0.5, -1.0, 0.0, 0.0, 0.0, 1.0,
+    // Right
+    0.5, -0.5, 0.5, 1.0, 0.0, 0.0, 0.0, 0.0,
+    0.5, -0.5, -0.5, 1.0, 0.0, 0.0, 1.0, 0.0,
+    0.5, 0.5, -0.5, 1.0, 0.0, 0.0, 1.0, 1.0,
+    0.5, 0.5, 0.5, 1.0, 0.0, 0.0, 0.0, 1.0,
+    // Top
+    -0.5, 0.5, 0.5, 0.0, 1.0, 0.0, 0.0, 0.0,
+    0.5, 0.5, 0.5, 0.0, 1.0, 0.0, 1.0, 0.0,
+    0.5, 0.5, -0.5, 0.0, 1.0, 0.0, 1.0, 1.0,
+    -0.5, 0.5, -0.5, 0.0, 1.0, 0.0, 0.0, 1.0,
+    // Bottom
+    -0.5, -0.5, -0.5, 0.0, -1.0, 0.0, 0.0, 0.0,
+    0.5, -0.5, -0.5, 0.0, -1.0, 0.0, 1.0, 0.0,
+    0.5, -0.5, 0.5, 0.0, -1.0, 0.0, 1.0, 1.0,
+    -0.5, -0.5, 0.5, 0.0, -1.0, 0.0, 0.0, 1.0,
 ];
-pub const PART_INDICES_TEX: [u32; 36] = [
-    1, 0, 2, 2, 0, 3, // front
-    4, 5, 6, 4, 6, 7, // back
-    9, 8, 10, 10, 8, 11, // left
-    13, 12, 14, 14, 12, 15, // right
-    17, 16, 18, 18, 16, 19, // top
-    21, 20, 22, 22, 20, 23, // bottom
-];
-pub const PART_INDICES_INVERT_TEX: [u32; 36] = [
-    0, 1, 2, 0, 2, 3, // front
-    5, 4, 6, 6, 4, 7, // back
+
+#[rustfmt::skip]
+pub const PART_INDICES: [u32; 36] = [
+    0, 1, 2, 0, 2, 3, // back
+    4, 5, 6, 4, 6, 7, // front
     8, 9, 10, 8, 10, 11, // left
     12, 13, 14, 12, 14, 15, // right
     16, 17, 18, 16, 18, 19, // top
     20, 21, 22, 20, 22, 23, // bottom
-];
\ No newline at end of file
+];
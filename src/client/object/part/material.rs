@@ -0,0 +1,162 @@
+use crate::graphics::shader::Shader;
+use crate::graphics::texture::{TextureArray, load_texture_array_from_files};
+
+pub const PART_MATERIAL_DIR: &str = "assets/material";
+/// Must match the comment on `uMaterialArray` in `common/part_material.glsl`
+pub const PART_MATERIAL_TEXTURE_UNIT: u32 = 2;
+
+/// Built-in surface materials for parts, one for each texture in `assets/material`.
+/// The discriminant is the material's layer in the library's texture array
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PartMaterial {
+    Asphalt,
+    Bricks,
+    Cobble,
+    Concrete,
+    DiamondPlate,
+    Fabric,
+    Foil,
+    Glass,
+    Granite,
+    Grass,
+    Ice,
+    Marble,
+    Plastic,
+    Rust,
+    Sand,
+    Slate,
+    SmoothPlastic,
+    Wood,
+    WoodPlanks,
+}
+
+/// How a material's surface reacts to light, independent of its texture
+#[derive(Debug, Clone, Copy)]
+pub struct PartMaterialProperties {
+    /// 0 is mirror-like, 1 is completely matte
+    pub roughness: f32,
+    /// Strength of specular highlights, from 0 to 1
+    pub reflectance: f32,
+    /// 0 is opaque, 1 is invisible
+    pub transparency: f32,
+}
+
+impl PartMaterial {
+    pub const ALL: [PartMaterial; 19] = [
+        PartMaterial::Asphalt,
+        PartMaterial::Bricks,
+        PartMaterial::Cobble,
+        PartMaterial::Concrete,
+        PartMaterial::DiamondPlate,
+        PartMaterial::Fabric,
+        PartMaterial::Foil,
+        PartMaterial::Glass,
+        PartMaterial::Granite,
+        PartMaterial::Grass,
+        PartMaterial::Ice,
+        PartMaterial::Marble,
+        PartMaterial::Plastic,
+        PartMaterial::Rust,
+        PartMaterial::Sand,
+        PartMaterial::Slate,
+        PartMaterial::SmoothPlastic,
+        PartMaterial::Wood,
+        PartMaterial::WoodPlanks,
+    ];
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            PartMaterial::Asphalt => "asphalt.png",
+            PartMaterial::Bricks => "bricks.png",
+            PartMaterial::Cobble => "cobble.png",
+            PartMaterial::Concrete => "conrete.png",
+            PartMaterial::DiamondPlate => "diamond_plate.png",
+            PartMaterial::Fabric => "fabric.png",
+            PartMaterial::Foil => "foil.png",
+            PartMaterial::Glass => "glass.png",
+            PartMaterial::Granite => "granite.png",
+            PartMaterial::Grass => "grass.png",
+            PartMaterial::Ice => "ice.png",
+            PartMaterial::Marble => "marble.png",
+            PartMaterial::Plastic => "plastic.png",
+            PartMaterial::Rust => "rust.png",
+            PartMaterial::Sand => "sand.png",
+            PartMaterial::Slate => "slate.png",
+            PartMaterial::SmoothPlastic => "smooth_plastic.png",
+            PartMaterial::Wood => "wood.png",
+            PartMaterial::WoodPlanks => "wood_planks.png",
+        }
+    }
+
+    pub fn layer(&self) -> i32 {
+        *self as i32
+    }
+
+    pub fn properties(&self) -> PartMaterialProperties {
+        let (roughness, reflectance, transparency) = match self {
+            PartMaterial::Asphalt => (0.9, 0.02, 0.0),
+            PartMaterial::Bricks => (0.85, 0.03, 0.0),
+            PartMaterial::Cobble => (0.8, 0.04, 0.0),
+            PartMaterial::Concrete => (0.85, 0.03, 0.0),
+            PartMaterial::DiamondPlate => (0.35, 0.6, 0.0),
+            PartMaterial::Fabric => (1.0, 0.0, 0.0),
+            PartMaterial::Foil => (0.25, 0.8, 0.0),
+            PartMaterial::Glass => (0.05, 0.5, 0.6),
+            PartMaterial::Granite => (0.6, 0.15, 0.0),
+            PartMaterial::Grass => (0.95, 0.02, 0.0),
+            PartMaterial::Ice => (0.15, 0.4, 0.3),
+            PartMaterial::Marble => (0.2, 0.3, 0.0),
+            PartMaterial::Plastic => (0.5, 0.1, 0.0),
+            PartMaterial::Rust => (0.85, 0.1, 0.0),
+            PartMaterial::Sand => (1.0, 0.01, 0.0),
+            PartMaterial::Slate => (0.7, 0.08, 0.0),
+            PartMaterial::SmoothPlastic => (0.25, 0.2, 0.0),
+            PartMaterial::Wood => (0.7, 0.05, 0.0),
+            PartMaterial::WoodPlanks => (0.75, 0.05, 0.0),
+        };
+        PartMaterialProperties {
+            roughness,
+            reflectance,
+            transparency,
+        }
+    }
+
+    /// Uploads the material to `uPartMaterial`. Only uniforms change, the texture array
+    /// stays bound for the whole frame
+    pub fn apply(&self, shader: &Shader) -> Result<(), String> {
+        let properties = self.properties();
+        shader.set_int("uPartMaterial.layer", self.layer())?;
+        shader.set_float("uPartMaterial.roughness", properties.roughness)?;
+        shader.set_float("uPartMaterial.reflectance", properties.reflectance)?;
+        shader.set_float("uPartMaterial.transparency", properties.transparency)?;
+        shader.set_int("uMaterialArray", PART_MATERIAL_TEXTURE_UNIT as i32)
+    }
+
+    /// Turns part materials off for the next draw with `shader`
+    pub fn clear(shader: &Shader) -> Result<(), String> {
+        shader.set_int("uPartMaterial.layer", -1)
+    }
+}
+
+/// Every `PartMaterial` texture, loaded once into a single texture array
+pub struct PartMaterialLibrary {
+    pub textures: TextureArray,
+}
+
+impl PartMaterialLibrary {
+    pub fn load() -> Result<Self, String> {
+        let paths: Vec<String> = PartMaterial::ALL
+            .iter()
+            .map(|material| format!("{}/{}", PART_MATERIAL_DIR, material.file_name()))
+            .collect();
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+
+        Ok(PartMaterialLibrary {
+            textures: load_texture_array_from_files(&paths, Default::default())?,
+        })
+    }
+
+    pub fn bind(&self) {
+        self.textures.bind(PART_MATERIAL_TEXTURE_UNIT);
+    }
+}
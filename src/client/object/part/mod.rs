@@ -1,22 +1,33 @@
 #![allow(dead_code)]
-use std::any::Any;
+use std::rc::Rc;
 
-use gl::{self};
 use nalgebra_glm as glm;
 
 // use crate::Render;
-use crate::{graphics::shader::Shader, object::part::consts::PART_VERTICES};
+use crate::graphics::frustum::Aabb;
+use crate::graphics::material::Material;
+use crate::graphics::shader::{Shader, VertexArrayObject};
 use crate::graphics::texture::Texture;
-use crate::shader::VertexArrayObject;
+use crate::object::mesh::{mesh_normals, mesh_triangles};
 
 pub mod consts;
+pub mod material;
+pub mod shape;
 
-use consts::{PART_INDICES_COLOR, PART_VERTICES_INVERSE, PART_INDICES_TEX, PART_VERTICES_TEX};
+use consts::{PART_ATTRIBUTES, PART_INDICES, PART_VERTEX_FLOATS, PART_VERTICES};
 
 pub struct RenderData {
     pub vao: VertexArrayObject,
     pub index_count: i32,
     pub program_id: u32,
+    pub material: Option<Material>,
+    /// Local-space bounds of the vertices
+    pub bounds: Aabb,
+    /// Local-space triangles, kept on the CPU for picking
+    pub triangles: Rc<Vec<[glm::Vec3; 3]>>,
+    /// Local-space vertex positions and normals, for the normals debug view. Empty if the
+    /// mesh has no normals
+    pub normals: Rc<Vec<(glm::Vec3, glm::Vec3)>>,
 }
 
 pub struct Part {
@@ -28,8 +39,6 @@ pub struct Part {
     pub texture: Option<Texture>,
 }
 
-
-
 impl Part {
     pub fn new(
         pos: glm::Vec3,
@@ -38,44 +47,8 @@ impl Part {
         color: glm::Vec3,
         shader: &Shader,
     ) -> Box<Part> {
-
-        let (mut vbo, mut ebo) = (0, 0);
-        let mut vao = VertexArrayObject::new();
-
-        unsafe {
-            gl::GenVertexArrays(1, &mut vao.id);
-            gl::GenBuffers(1, &mut vbo);
-            gl::GenBuffers(1, &mut ebo);
-
-            gl::BindVertexArray(vao.id);
-
-            // -------- VBO --------
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (PART_VERTICES.len() * std::mem::size_of::<f32>()) as _,
-                PART_VERTICES.as_ptr() as *const _,
-                gl::STATIC_DRAW,
-            );
-
-            // -------- EBO --------
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                (PART_INDICES_COLOR.len() * std::mem::size_of::<u32>()) as _,
-                PART_INDICES_COLOR.as_ptr() as *const _,
-                gl::STATIC_DRAW,
-            );
-
-            // -------- Vertex Attributes --------
-            let stride = 3 * std::mem::size_of::<f32>() as i32;
-
-            // Position (location = 0)
-            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
-            gl::EnableVertexAttribArray(0);
-
-            gl::BindVertexArray(0);
-        }
+        let vao =
+            VertexArrayObject::from_interleaved(&PART_VERTICES, &PART_INDICES, PART_ATTRIBUTES);
 
         Box::new(Part {
             pos,
@@ -84,8 +57,16 @@ impl Part {
             color,
             render_data: RenderData {
                 vao,
-                index_count: PART_INDICES_COLOR.len() as i32,
+                index_count: PART_INDICES.len() as i32,
                 program_id: shader.program,
+                material: None,
+                bounds: Aabb::from_vertices(&PART_VERTICES, PART_VERTEX_FLOATS),
+                triangles: Rc::new(mesh_triangles(
+                    &PART_VERTICES,
+                    PART_VERTEX_FLOATS,
+                    &PART_INDICES,
+                )),
+                normals: Rc::new(mesh_normals(&PART_VERTICES, PART_VERTEX_FLOATS, 3)),
             },
             texture: None,
         })
@@ -99,67 +80,9 @@ impl Part {
         texture: Texture,
         shader: &Shader,
     ) -> Box<Part> {
-        let (mut vbo, mut ebo) = (0, 0);
-        let mut vao = VertexArrayObject::new();
-
-        unsafe {
-            gl::GenVertexArrays(1, &mut vao.id);
-            gl::GenBuffers(1, &mut vbo);
-            gl::GenBuffers(1, &mut ebo);
-
-            gl::BindVertexArray(vao.id);
-
-            // -------- VBO --------
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (PART_VERTICES_TEX.len() * std::mem::size_of::<f32>()) as _,
-                PART_VERTICES_TEX.as_ptr() as *const _,
-                gl::STATIC_DRAW,
-            );
-
-            // -------- EBO --------
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                (PART_INDICES_TEX.len() * std::mem::size_of::<u32>()) as _,
-                PART_INDICES_TEX.as_ptr() as *const _,
-                gl::STATIC_DRAW,
-            );
-
-            // -------- Vertex Attributes --------
-            let stride = 5 * std::mem::size_of::<f32>() as i32;
-
-            // Position (location = 0)
-            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
-            gl::EnableVertexAttribArray(0);
-
-            // Color (location = 1)
-            gl::VertexAttribPointer(
-                1,
-                2,
-                gl::FLOAT,
-                gl::FALSE,
-                stride,
-                (3 * std::mem::size_of::<f32>()) as *const _,
-            );
-            gl::EnableVertexAttribArray(1);
-
-            gl::BindVertexArray(0);
-        }
-
-        Box::new(Part {
-            pos,
-            rot,
-            size,
-            color: basecolor,
-            render_data: RenderData {
-                vao,
-                index_count: PART_INDICES_TEX.len() as i32,
-                program_id: shader.program,
-            },
-            texture: Some(texture),
-        })
+        let mut part = Part::new(pos, rot, size, basecolor, shader);
+        part.texture = Some(texture);
+        part
     }
 }
 
@@ -195,4 +118,4 @@ impl Part {
 //     fn scale(&mut self, scale: glm::Vec3) {
 //         self.size += scale;
 //     }
-// }
\ No newline at end of file
+// }
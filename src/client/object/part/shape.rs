@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::rc::Rc;
+
+use log::debug;
+use nalgebra_glm as glm;
+
+use crate::graphics::frustum::Aabb;
+use crate::graphics::shader::VertexArrayObject;
+use crate::object::mesh::mesh_normals;
+use crate::object::part::consts::{
+    PART_ATTRIBUTES, PART_INDICES, PART_VERTEX_FLOATS, PART_VERTICES,
+};
+
+const SPHERE_SEGMENTS: u32 = 24;
+const SPHERE_RINGS: u32 = 16;
+const CYLINDER_SEGMENTS: u32 = 32;
+
+/// Shapes a part can take. Every shape fits the unit cube centered on the origin,
+/// so a part's `Scale` is its size regardless of shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PartShape {
+    #[default]
+    Block,
+    /// Ramp rising from the front (-Z) bottom edge to the back (+Z) top edge
+    Wedge,
+    /// Pyramid with its apex above the back right (+X, +Z) corner
+    CornerWedge,
+    Sphere,
+    /// Upright cylinder along the Y axis
+    Cylinder,
+}
+
+/// Interleaved vertices in the `PART_VERTICES` layout, with their indices
+pub struct MeshData {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    fn new() -> Self {
+        MeshData {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn vertex(&mut self, pos: glm::Vec3, normal: glm::Vec3, uv: glm::Vec2) -> u32 {
+        let index = (self.vertices.len() / PART_VERTEX_FLOATS) as u32;
+        self.vertices.extend_from_slice(&[
+            pos.x, pos.y, pos.z, normal.x, normal.y, normal.z, uv.x, uv.y,
+        ]);
+        index
+    }
+
+    /// Adds a flat convex polygon. Points can be given in either winding; faces are flipped
+    /// to point away from `interior`, any point strictly inside the (convex) shape.
+    /// UVs are the points projected onto the plane `tiledTexCoord` picks from the normal when
+    /// tiling by part size, so sloped faces tile the same way as the sides of a block
+    fn flat_polygon(&mut self, points: &[glm::Vec3], interior: glm::Vec3) {
+        let mut normal = (points[1] - points[0])
+            .cross(&(points[2] - points[0]))
+            .normalize();
+        let centroid = points.iter().sum::<glm::Vec3>() / points.len() as f32;
+        let reversed = normal.dot(&(centroid - interior)) < 0.0;
+        if reversed {
+            normal = -normal;
+        }
+
+        let n = normal.abs();
+        let first = (self.vertices.len() / PART_VERTEX_FLOATS) as u32;
+        for p in points {
+            let uv = if n.x > 0.5 {
+                glm::vec2(p.z, p.y)
+            } else if n.y > 0.5 {
+                glm::vec2(p.x, p.z)
+            } else {
+                glm::vec2(p.x, p.y)
+            };
+            self.vertex(*p, normal, uv.add_scalar(0.5));
+        }
+
+        for i in 1..points.len() as u32 - 1 {
+            let (b, c) = (first + i, first + i + 1);
+            if reversed {
+                self.indices.extend_from_slice(&[first, c, b]);
+            } else {
+                self.indices.extend_from_slice(&[first, b, c]);
+            }
+        }
+    }
+}
+
+impl PartShape {
+    pub const ALL: [PartShape; 5] = [
+        PartShape::Block,
+        PartShape::Wedge,
+        PartShape::CornerWedge,
+        PartShape::Sphere,
+        PartShape::Cylinder,
+    ];
+
+    pub fn generate(&self) -> MeshData {
+        match self {
+            PartShape::Block => MeshData {
+                vertices: PART_VERTICES.to_vec(),
+                indices: PART_INDICES.to_vec(),
+            },
+            PartShape::Wedge => wedge(),
+            PartShape::CornerWedge => corner_wedge(),
+            PartShape::Sphere => sphere(),
+            PartShape::Cylinder => cylinder(),
+        }
+    }
+}
+
+fn wedge() -> MeshData {
+    let mut mesh = MeshData::new();
+    let (l, h) = (-0.5, 0.5);
+    // The average of its six corners
+    let interior = glm::vec3(0.0, -1.0 / 6.0, 1.0 / 6.0);
+
+    // bottom, back, slope
+    mesh.flat_polygon(
+        &[
+            glm::vec3(l, l, l),
+            glm::vec3(h, l, l),
+            glm::vec3(h, l, h),
+            glm::vec3(l, l, h),
+        ],
+        interior,
+    );
+    mesh.flat_polygon(
+        &[
+            glm::vec3(l, l, h),
+            glm::vec3(h, l, h),
+            glm::vec3(h, h, h),
+            glm::vec3(l, h, h),
+        ],
+        interior,
+    );
+    mesh.flat_polygon(
+        &[
+            glm::vec3(l, l, l),
+            glm::vec3(l, h, h),
+            glm::vec3(h, h, h),
+            glm::vec3(h, l, l),
+        ],
+        interior,
+    );
+    // left and right triangles
+    for x in [l, h] {
+        mesh.flat_polygon(
+            &[glm::vec3(x, l, l), glm::vec3(x, l, h), glm::vec3(x, h, h)],
+            interior,
+        );
+    }
+
+    mesh
+}
+
+fn corner_wedge() -> MeshData {
+    let mut mesh = MeshData::new();
+    let (l, h) = (-0.5, 0.5);
+    let apex = glm::vec3(h, h, h);
+    let base = [
+        glm::vec3(l, l, l),
+        glm::vec3(h, l, l),
+        glm::vec3(h, l, h),
+        glm::vec3(l, l, h),
+    ];
+    let interior = (base.iter().sum::<glm::Vec3>() + apex) / 5.0;
+
+    mesh.flat_polygon(&base, interior);
+    // One triangle from each base edge up to the apex. The two touching the apex's corner
+    // are the vertical right and back faces, the other two are the slopes
+    for i in 0..4 {
+        mesh.flat_polygon(&[base[i], base[(i + 1) % 4], apex], interior);
+    }
+
+    mesh
+}
+
+fn sphere() -> MeshData {
+    let mut mesh = MeshData::new();
+
+    for ring in 0..=SPHERE_RINGS {
+        let v = ring as f32 / SPHERE_RINGS as f32;
+        let phi = v * PI; // 0 at the top, PI at the bottom
+        for segment in 0..=SPHERE_SEGMENTS {
+            let u = segment as f32 / SPHERE_SEGMENTS as f32;
+            let theta = u * 2.0 * PI;
+            let normal = glm::vec3(phi.sin() * theta.cos(), phi.cos(), -phi.sin() * theta.sin());
+            mesh.vertex(normal * 0.5, normal, glm::vec2(u, 1.0 - v));
+        }
+    }
+
+    let row = SPHERE_SEGMENTS + 1;
+    for ring in 0..SPHERE_RINGS {
+        for segment in 0..SPHERE_SEGMENTS {
+            let top_left = ring * row + segment;
+            let bottom_left = top_left + row;
+            mesh.indices.extend_from_slice(&[
+                top_left,
+                bottom_left,
+                bottom_left + 1,
+                top_left,
+                bottom_left + 1,
+                top_left + 1,
+            ]);
+        }
+    }
+
+    mesh
+}
+
+fn cylinder() -> MeshData {
+    let mut mesh = MeshData::new();
+
+    // Side, with smooth normals and the texture wrapped once around
+    for segment in 0..=CYLINDER_SEGMENTS {
+        let u = segment as f32 / CYLINDER_SEGMENTS as f32;
+        let theta = u * 2.0 * PI;
+        let normal = glm::vec3(theta.cos(), 0.0, -theta.sin());
+        for y in [-0.5, 0.5] {
+            let pos = glm::vec3(normal.x * 0.5, y, normal.z * 0.5);
+            mesh.vertex(pos, normal, glm::vec2(u, y + 0.5));
+        }
+    }
+    for segment in 0..CYLINDER_SEGMENTS {
+        let bottom = segment * 2;
+        mesh.indices.extend_from_slice(&[
+            bottom,
+            bottom + 2,
+            bottom + 3,
+            bottom,
+            bottom + 3,
+            bottom + 1,
+        ]);
+    }
+
+    // Caps
+    for y in [-0.5, 0.5] {
+        let rim: Vec<glm::Vec3> = (0..CYLINDER_SEGMENTS)
+            .map(|segment| {
+                let theta = segment as f32 / CYLINDER_SEGMENTS as f32 * 2.0 * PI;
+                glm::vec3(0.5 * theta.cos(), y, -0.5 * theta.sin())
+            })
+            .collect();
+        mesh.flat_polygon(&rim, glm::Vec3::zeros());
+    }
+
+    mesh
+}
+
+/// A shape's mesh on the GPU
+#[derive(Debug, Clone)]
+pub struct PartMesh {
+    pub vao_id: u32,
+    pub index_count: i32,
+    pub bounds: Aabb,
+    /// Vertex positions and normals, for the normals debug view
+    pub normals: Rc<Vec<(glm::Vec3, glm::Vec3)>>,
+}
+
+/// Uploads each shape once, so every part of the same shape shares one VAO
+pub struct PartMeshCache {
+    meshes: HashMap<PartShape, PartMesh>,
+}
+
+impl PartMeshCache {
+    pub fn new() -> Self {
+        PartMeshCache {
+            meshes: HashMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, shape: PartShape) -> PartMesh {
+        let mesh = self.meshes.entry(shape).or_insert_with(|| {
+            let data = shape.generate();
+            let vao =
+                VertexArrayObject::from_interleaved(&data.vertices, &data.indices, PART_ATTRIBUTES);
+            debug!("Uploaded {:?} part mesh as VAO #{}", shape, vao.id);
+            PartMesh {
+                vao_id: vao.id,
+                index_count: data.indices.len() as i32,
+                bounds: Aabb::from_vertices(&data.vertices, PART_VERTEX_FLOATS),
+                normals: Rc::new(mesh_normals(&data.vertices, PART_VERTEX_FLOATS, 3)),
+            }
+        });
+        mesh.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(mesh: &MeshData, index: u32) -> glm::Vec3 {
+        let v = &mesh.vertices[index as usize * PART_VERTEX_FLOATS..];
+        glm::vec3(v[0], v[1], v[2])
+    }
+
+    fn normal(mesh: &MeshData, index: u32) -> glm::Vec3 {
+        let v = &mesh.vertices[index as usize * PART_VERTEX_FLOATS..];
+        glm::vec3(v[3], v[4], v[5])
+    }
+
+    #[test]
+    fn faces_point_away_from_the_interior() {
+        for shape in PartShape::ALL {
+            let mesh = shape.generate();
+            // Every shape is convex, so the average of its vertices is inside it
+            let vertex_count = (mesh.vertices.len() / PART_VERTEX_FLOATS) as u32;
+            let interior = (0..vertex_count)
+                .map(|i| position(&mesh, i))
+                .sum::<glm::Vec3>()
+                / vertex_count as f32;
+
+            for triangle in mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| position(&mesh, triangle[i]));
+                let face_normal = (b - a).cross(&(c - a));
+                // The sphere's rows at the poles collapse into zero-area triangles
+                if face_normal.norm() < 1e-6 {
+                    continue;
+                }
+                let outwards = (a + b + c) / 3.0 - interior;
+                assert!(
+                    face_normal.dot(&outwards) > 0.0,
+                    "{:?} triangle {:?} is wound towards its interior",
+                    shape,
+                    triangle
+                );
+                for &index in triangle {
+                    assert!(
+                        normal(&mesh, index).dot(&face_normal) > 0.0,
+                        "{:?} vertex {} has a normal facing away from its triangle {:?}",
+                        shape,
+                        index,
+                        triangle
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,25 @@
+#!/bin/sh
+# Builds the EGL stand-in for GLFW into the given directory (target/egl-glfw by default),
+# next to a glfw3.pc that points the glfw crate at it. Then build and run with:
+#
+#   export PKG_CONFIG_PATH=<dir> LD_LIBRARY_PATH=<dir>
+#
+# Needs a C compiler and the EGL headers, e.g. Debian's gcc and libegl-dev.
+set -eu
+
+out=$(realpath -m "${1:-target/egl-glfw}")
+mkdir -p "$out"
+
+cc -shared -fPIC -O2 "$(dirname "$0")/glfw.c" -o "$out/libglfw.so.3" -lEGL
+ln -sf libglfw.so.3 "$out/libglfw.so"
+
+# glfw-sys wants GLFW 3.4 or later
+cat > "$out/glfw3.pc" <<EOF
+libdir=$out
+
+Name: GLFW
+Description: EGL stand-in for GLFW, for rendering without a display
+Version: 3.4.0
+Libs: -L\${libdir} -lglfw
+Cflags:
+EOF
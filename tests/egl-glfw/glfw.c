@@ -0,0 +1,161 @@
+/*
+ * A stand-in for the GLFW library, for rendering the client without a display. Every window
+ * is a Mesa EGL pbuffer on the surfaceless platform, so with llvmpipe neither a display nor a
+ * GPU is needed. Input never arrives, and only the functions the client calls are here: if it
+ * starts calling another, linking against this fails until it's added.
+ *
+ * Built and used by build.sh, see the Tests section of the README.
+ */
+#include <EGL/egl.h>
+#include <EGL/eglext.h>
+#include <stdlib.h>
+#include <time.h>
+
+#define GLFW_CONTEXT_VERSION_MAJOR 0x00022002
+#define GLFW_CONTEXT_VERSION_MINOR 0x00022003
+
+typedef struct {
+    EGLSurface surface;
+    EGLContext context;
+    int width, height;
+    int should_close;
+    void *user_pointer;
+} Window;
+
+static EGLDisplay display;
+static EGLConfig config;
+static Window *current;
+static int context_major = 3, context_minor = 3;
+static double start_time;
+
+static double now(void)
+{
+    struct timespec t;
+    clock_gettime(CLOCK_MONOTONIC, &t);
+    return t.tv_sec + t.tv_nsec * 1e-9;
+}
+
+int glfwInit(void)
+{
+    PFNEGLGETPLATFORMDISPLAYEXTPROC get_platform_display =
+        (void *)eglGetProcAddress("eglGetPlatformDisplayEXT");
+    if (!get_platform_display)
+        return 0;
+    display = get_platform_display(EGL_PLATFORM_SURFACELESS_MESA, EGL_DEFAULT_DISPLAY, NULL);
+    if (!eglInitialize(display, NULL, NULL))
+        return 0;
+
+    EGLint attributes[] = {
+        EGL_SURFACE_TYPE, EGL_PBUFFER_BIT,
+        EGL_RENDERABLE_TYPE, EGL_OPENGL_BIT,
+        EGL_RED_SIZE, 8, EGL_GREEN_SIZE, 8, EGL_BLUE_SIZE, 8, EGL_ALPHA_SIZE, 8,
+        EGL_DEPTH_SIZE, 24, EGL_STENCIL_SIZE, 8,
+        EGL_NONE,
+    };
+    EGLint count;
+    if (!eglChooseConfig(display, attributes, &config, 1, &count) || count < 1)
+        return 0;
+    eglBindAPI(EGL_OPENGL_API);
+    start_time = now();
+    return 1;
+}
+
+void glfwTerminate(void) { eglTerminate(display); }
+void glfwInitHint(int hint, int value) {}
+void *glfwSetErrorCallback(void *callback) { return NULL; }
+
+void glfwWindowHint(int hint, int value)
+{
+    if (hint == GLFW_CONTEXT_VERSION_MAJOR)
+        context_major = value;
+    if (hint == GLFW_CONTEXT_VERSION_MINOR)
+        context_minor = value;
+}
+
+void glfwWindowHintString(int hint, const char *value) {}
+
+void *glfwCreateWindow(int width, int height, const char *title, void *monitor, void *share)
+{
+    Window *window = calloc(1, sizeof *window);
+    window->width = width;
+    window->height = height;
+
+    EGLint surface_attributes[] = {EGL_WIDTH, width, EGL_HEIGHT, height, EGL_NONE};
+    window->surface = eglCreatePbufferSurface(display, config, surface_attributes);
+    EGLint context_attributes[] = {
+        EGL_CONTEXT_MAJOR_VERSION, context_major,
+        EGL_CONTEXT_MINOR_VERSION, context_minor,
+        EGL_CONTEXT_OPENGL_PROFILE_MASK, EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+        EGL_NONE,
+    };
+    window->context = eglCreateContext(display, config, EGL_NO_CONTEXT, context_attributes);
+    if (!window->surface || !window->context) {
+        free(window);
+        return NULL;
+    }
+    return window;
+}
+
+void glfwDestroyWindow(Window *window)
+{
+    eglDestroySurface(display, window->surface);
+    eglDestroyContext(display, window->context);
+    free(window);
+}
+
+void glfwMakeContextCurrent(Window *window)
+{
+    current = window;
+    if (window)
+        eglMakeCurrent(display, window->surface, window->surface, window->context);
+    else
+        eglMakeCurrent(display, EGL_NO_SURFACE, EGL_NO_SURFACE, EGL_NO_CONTEXT);
+}
+
+void *glfwGetCurrentContext(void) { return current; }
+void *glfwGetProcAddress(const char *name) { return (void *)eglGetProcAddress(name); }
+void glfwSwapBuffers(Window *window) { eglSwapBuffers(display, window->surface); }
+void glfwPollEvents(void) {}
+double glfwGetTime(void) { return now() - start_time; }
+
+void glfwGetFramebufferSize(Window *window, int *width, int *height)
+{
+    if (width)
+        *width = window->width;
+    if (height)
+        *height = window->height;
+}
+
+void glfwGetWindowSize(Window *window, int *width, int *height)
+{
+    glfwGetFramebufferSize(window, width, height);
+}
+
+int glfwWindowShouldClose(Window *window) { return window->should_close; }
+void glfwSetWindowShouldClose(Window *window, int value) { window->should_close = value; }
+void glfwSetWindowUserPointer(Window *window, void *pointer) { window->user_pointer = pointer; }
+void *glfwGetWindowUserPointer(Window *window) { return window->user_pointer; }
+void glfwSetWindowTitle(Window *window, const char *title) {}
+void glfwSetInputMode(Window *window, int mode, int value) {}
+void glfwDestroyCursor(void *cursor) {}
+
+/* Events never arrive, so callbacks are accepted and dropped */
+#define CALLBACK(name) \
+    void *name(Window *window, void *callback) { return NULL; }
+CALLBACK(glfwSetCharCallback)
+CALLBACK(glfwSetCharModsCallback)
+CALLBACK(glfwSetCursorEnterCallback)
+CALLBACK(glfwSetCursorPosCallback)
+CALLBACK(glfwSetDropCallback)
+CALLBACK(glfwSetFramebufferSizeCallback)
+CALLBACK(glfwSetKeyCallback)
+CALLBACK(glfwSetMouseButtonCallback)
+CALLBACK(glfwSetScrollCallback)
+CALLBACK(glfwSetWindowCloseCallback)
+CALLBACK(glfwSetWindowContentScaleCallback)
+CALLBACK(glfwSetWindowFocusCallback)
+CALLBACK(glfwSetWindowIconifyCallback)
+CALLBACK(glfwSetWindowMaximizeCallback)
+CALLBACK(glfwSetWindowPosCallback)
+CALLBACK(glfwSetWindowRefreshCallback)
+CALLBACK(glfwSetWindowSizeCallback)
@@ -0,0 +1,136 @@
+//! Golden-image tests. Each one renders the client's scene headlessly from a fixed camera and
+//! compares it against a reference PNG in `tests/golden/`.
+//!
+//! Rendering goes through Mesa's llvmpipe, so no GPU is needed, but Mesa is. They're ignored by
+//! default, run them with `cargo test --test golden -- --ignored`. The Tests section of the
+//! README has how the references were rendered, and how to render them the same way.
+//! Set `BUAT_UPDATE_GOLDEN=1` to write the current renders as the new references.
+//! When a render doesn't match, `<name>.actual.png` and `<name>.diff.png` are written to
+//! `target/tmp/golden/`. Mismatched pixels are red in the diff.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use image::{Rgba, RgbaImage};
+
+/// Largest difference allowed in any channel before a pixel counts as mismatched
+const CHANNEL_TOLERANCE: u8 = 4;
+/// Fraction of mismatched pixels allowed, for rasterization differences between Mesa versions
+const MAX_MISMATCHED_FRACTION: f64 = 0.001;
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn output_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+/// Renders the scene from `camera` (`x,y,z,yaw,pitch`) with the client's other `args` and
+/// saves it to `output`
+fn render(camera: &str, args: &[&str], output: &Path) {
+    let status = Command::new(env!("CARGO_BIN_EXE_client"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("--headless")
+        .arg(output)
+        .arg("--camera")
+        .arg(camera)
+        .args(args)
+        .env("LIBGL_ALWAYS_SOFTWARE", "1")
+        .env("GALLIUM_DRIVER", "llvmpipe")
+        .status()
+        .expect("Failed to launch the client");
+    assert!(status.success(), "Client exited with {}", status);
+}
+
+/// Number of mismatched pixels, and an image of the reference dimmed to grey with the
+/// mismatches in red
+fn compare(actual: &RgbaImage, reference: &RgbaImage) -> (usize, RgbaImage) {
+    let mut mismatched = 0;
+    let diff = RgbaImage::from_fn(reference.width(), reference.height(), |x, y| {
+        let a = actual.get_pixel(x, y);
+        let r = reference.get_pixel(x, y);
+        if a.0
+            .iter()
+            .zip(r.0)
+            .any(|(a, r)| a.abs_diff(r) > CHANNEL_TOLERANCE)
+        {
+            mismatched += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let grey = ((r[0] as u32 + r[1] as u32 + r[2] as u32) / 9) as u8;
+            Rgba([grey, grey, grey, 255])
+        }
+    });
+    (mismatched, diff)
+}
+
+fn check_golden(name: &str, camera: &str, args: &[&str]) {
+    std::fs::create_dir_all(output_dir()).unwrap();
+    let actual_path = output_dir().join(format!("{}.actual.png", name));
+    let diff_path = output_dir().join(format!("{}.diff.png", name));
+    let reference_path = golden_dir().join(format!("{}.png", name));
+    let _ = std::fs::remove_file(&diff_path);
+
+    render(camera, args, &actual_path);
+    let actual = image::open(&actual_path).unwrap().to_rgba8();
+
+    if std::env::var_os("BUAT_UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save(&reference_path).unwrap();
+        return;
+    }
+
+    let reference = match image::open(&reference_path) {
+        Ok(reference) => reference.to_rgba8(),
+        Err(e) => panic!(
+            "No reference at {} ({}). Run with BUAT_UPDATE_GOLDEN=1 to create it",
+            reference_path.display(),
+            e
+        ),
+    };
+    assert_eq!(
+        actual.dimensions(),
+        reference.dimensions(),
+        "{} rendered at the wrong size, see {}",
+        name,
+        actual_path.display()
+    );
+
+    let (mismatched, diff) = compare(&actual, &reference);
+    let allowed = (MAX_MISMATCHED_FRACTION * (actual.width() * actual.height()) as f64) as usize;
+    if mismatched > allowed {
+        diff.save(&diff_path).unwrap();
+        panic!(
+            "{} differs from its reference in {} pixels (allowed {}), see {}",
+            name,
+            mismatched,
+            allowed,
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+#[ignore = "needs Mesa, run with --ignored"]
+fn golden_front() {
+    check_golden("front", "0,1,8,0,-10", &[]);
+}
+
+#[test]
+#[ignore = "needs Mesa, run with --ignored"]
+fn golden_above() {
+    check_golden("above", "0,8,4,0,-60", &[]);
+}
+
+#[test]
+#[ignore = "needs Mesa, run with --ignored"]
+fn golden_side() {
+    check_golden("side", "9,0,0,-90,-5", &[]);
+}
+
+#[test]
+#[ignore = "needs Mesa, run with --ignored"]
+fn golden_skybox() {
+    check_golden("skybox", "0,1,8,0,5", &["--sky", "cubemap"]);
+}